@@ -0,0 +1,174 @@
+//! Baseline of accepted findings.
+//!
+//! A baseline lets CI fail only on new findings: the findings that were reviewed and accepted
+//! are recorded in a file by their fingerprint, and a finding whose fingerprint is in the file
+//! does not count as a failure.
+//!
+//! The fingerprint of a finding must not change between runs on the same code.
+//! It is derived from the entry point, the spans where the threads block
+//! and the spans where the synchronization primitives involved were created,
+//! but not from the labels of the Petri net or the indices of the primitives,
+//! which depend on the order of the translation.
+//!
+//! Only deadlocks are findings. Lost signals, panics, held guards and the other properties cannot be baselined.
+//!
+//! The baseline file contains one fingerprint per line, followed by a description of the finding.
+//! Empty lines and lines starting with `#` are ignored.
+
+use std::collections::BTreeSet;
+use std::io::Write;
+
+use crate::report::{EntryPointReport, Verdict};
+use crate::trace::{Resource, Trace};
+
+/// The header written at the start of every baseline file.
+const HEADER: &str = "# Accepted findings of cargo-check-deadlock, one fingerprint per line.\n# Regenerate this file with the flag `--update-baseline`.";
+
+/// A finding of the analysis identified by a stable fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Finding {
+    /// The hexadecimal hash of the components of the finding.
+    pub fingerprint: String,
+    /// A one-line description of the finding, for the humans reading the baseline.
+    pub description: String,
+}
+
+impl Finding {
+    /// Returns the finding of an entry point where the property is violated.
+    /// Returns `None` if the verdict is not a violation.
+    ///
+    /// The fingerprint covers the entry point, the blocking spans and the creation spans of the primitives
+    /// that the blocked threads hold or wait for. If the witness could not be replayed,
+    /// the fingerprint only covers the entry point, since the labels of the net are not stable:
+    /// every deadlock of the entry point without a trace has the same fingerprint.
+    #[must_use]
+    pub fn new(entry_point: &EntryPointReport) -> Option<Self> {
+        if entry_point.verdict != Verdict::Deadlock {
+            return None;
+        }
+        let mut components = BTreeSet::new();
+        let mut blocked = Vec::new();
+        if let Some(witness) = &entry_point.witness {
+            match &witness.trace {
+                Some(trace) => {
+                    for blocked_thread in &trace.blocked {
+                        for index in &blocked_thread.holds {
+                            components.insert(format!(
+                                "holds {}",
+                                stable_name(Resource::Mutex(*index), trace)
+                            ));
+                        }
+                        for (resource, transition, span) in &blocked_thread.waits_for {
+                            let location = span
+                                .as_ref()
+                                .map_or_else(|| transition.clone(), ToString::to_string);
+                            components.insert(format!(
+                                "{location} waits for {}",
+                                stable_name(*resource, trace)
+                            ));
+                            blocked.push(format!(
+                                "{} waits for {resource} at {location}",
+                                blocked_thread.thread
+                            ));
+                        }
+                    }
+                }
+                None => {
+                    components.insert("witness without trace".to_string());
+                }
+            }
+        }
+
+        let mut hash = fnv1a(entry_point.entry_point.as_bytes(), FNV_OFFSET_BASIS);
+        for component in &components {
+            // Separate the components so that they cannot run into each other.
+            hash = fnv1a(&[0], hash);
+            hash = fnv1a(component.as_bytes(), hash);
+        }
+        let mut description = format!("deadlock in `{}`", entry_point.entry_point);
+        if !blocked.is_empty() {
+            description = format!("{description}: {}", blocked.join(", "));
+        }
+        Some(Self {
+            fingerprint: format!("{hash:016x}"),
+            description,
+        })
+    }
+}
+
+/// Returns a name of the resource that does not depend on the order of the translation:
+/// its kind and the location where it was created, or its index if the location is not known.
+fn stable_name(resource: Resource, trace: &Trace) -> String {
+    let kind = match resource {
+        Resource::Mutex(_) => "mutex",
+        Resource::Condition(_) => "the condition of the mutex",
+        Resource::Condvar(_) => "a notification on the condvar",
+        Resource::Thread(_) => "the end of the thread",
+    };
+    trace.creation_span(resource).map_or_else(
+        || resource.to_string(),
+        |span| format!("{kind} created at {span}"),
+    )
+}
+
+/// The set of accepted fingerprints.
+#[derive(Debug, Default, Clone)]
+pub struct Baseline {
+    fingerprints: BTreeSet<String>,
+}
+
+impl Baseline {
+    /// Creates a new empty `Baseline`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the contents of a baseline file.
+    /// The first word of every line that is not empty and not a comment is a fingerprint.
+    #[must_use]
+    pub fn parse(contents: &str) -> Self {
+        let fingerprints = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_whitespace().next())
+            .map(ToString::to_string)
+            .collect();
+        Self { fingerprints }
+    }
+
+    /// Checks whether the finding was accepted.
+    #[must_use]
+    pub fn contains(&self, finding: &Finding) -> bool {
+        self.fingerprints.contains(&finding.fingerprint)
+    }
+
+    /// Writes a baseline that accepts exactly the given findings.
+    ///
+    /// # Errors
+    ///
+    /// If writing to the writer fails, then the function returns an error.
+    pub fn write<T: Write>(findings: &[Finding], writer: &mut T) -> Result<(), std::io::Error> {
+        writeln!(writer, "{HEADER}")?;
+        let findings: BTreeSet<&Finding> = findings.iter().collect();
+        for finding in findings {
+            writeln!(writer, "{} {}", finding.fingerprint, finding.description)?;
+        }
+        Ok(())
+    }
+}
+
+/// The offset basis of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+/// The prime of the 64-bit FNV-1a hash.
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Continues the 64-bit FNV-1a hash `hash` with the given bytes.
+/// The standard library hashers are not guaranteed to be stable across releases,
+/// which would invalidate the baseline files.
+fn fnv1a(bytes: &[u8], hash: u64) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
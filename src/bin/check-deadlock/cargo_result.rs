@@ -1,9 +1,15 @@
+use cargo_check_deadlock::AnalysisReport;
+
 /// Possible outcomes of running the `cargo check-deadlock` command.
 pub enum CargoResult {
     /// A successful translation containing the result of the deadlock analysis
-    DeadlockAnalysis(String),
+    DeadlockAnalysis(String, Outcome),
     /// A successful translation without deadlock analysis
     SimpleTranslation,
+    /// The result of the support check, i.e. the list of unsupported constructs found
+    SupportCheck(Vec<String>),
+    /// The analysis report, to be printed in JSON format
+    Report(AnalysisReport, Outcome),
     /// The source file was not found
     SourceFileNotFound(String),
     /// The output folder was not found
@@ -12,4 +18,43 @@ pub enum CargoResult {
     TranslationError(String),
     /// Failure when writing the output files
     OutputGenerationError(String),
+    /// The model checker could not check the property
+    ModelCheckerError(String),
+    /// The formula supplied by the user could not be read, parsed or resolved
+    InvalidProperty(String),
+}
+
+/// Outcome of the deadlock analysis that decides whether the command fails, e.g. to gate a CI build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// No deadlock was found or the deadlock found is accepted by the baseline
+    Passed,
+    /// A deadlock was found that is not accepted by the baseline
+    NewDeadlock,
+    /// A deadlock was found that is not accepted by the baseline, but it may be spurious,
+    /// or the model checker stopped without a result
+    Inconclusive,
+    /// The translation failed
+    TranslationFailed,
+    /// The support check found unsupported constructs
+    Unsupported,
+    /// The model checker could not check the property
+    ModelCheckerFailed,
+    /// The formula supplied by the user could not be read, parsed or resolved
+    InvalidProperty,
+}
+
+impl Outcome {
+    /// Returns the exit code of the command for this outcome.
+    pub const fn exit_code(self) -> i32 {
+        match self {
+            Self::Passed => 0,
+            Self::TranslationFailed => 3,
+            Self::Unsupported => 5,
+            Self::NewDeadlock => 6,
+            Self::Inconclusive => 7,
+            Self::ModelCheckerFailed => 8,
+            Self::InvalidProperty => 9,
+        }
+    }
 }
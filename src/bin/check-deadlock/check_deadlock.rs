@@ -1,10 +1,51 @@
-use clap::Parser;
-use log::info;
+use clap::{Parser, ValueEnum};
+use log::{info, warn};
 
-use crate::cargo_result::CargoResult;
-use crate::output_format::OutputFormat;
+use crate::cargo_result::{CargoResult, Outcome};
+use crate::output_format::{MessageFormat, OutputFormat, create_map_file, create_sarif_file};
 
-use cargo_check_deadlock::model_checker::lola;
+use cargo_check_deadlock::baseline::{Baseline, Finding};
+use cargo_check_deadlock::formula::{self, Formula};
+use cargo_check_deadlock::held_guard;
+use cargo_check_deadlock::lock_order;
+use cargo_check_deadlock::lost_signal;
+use cargo_check_deadlock::model_checker::lola::Lola;
+use cargo_check_deadlock::model_checker::native::{self, Native, livelock_cycle};
+use cargo_check_deadlock::model_checker::{ModelChecker, ModelCheckerError, ModelCheckerOutput};
+use cargo_check_deadlock::panic::{self, PanicCause};
+use cargo_check_deadlock::reduction::Reduction;
+use cargo_check_deadlock::refinement::{InstrumentedNet, Refinement};
+use cargo_check_deadlock::report::{
+    DeadlockClass, EntryPointReport, HeldGuard, Livelock, LockOrderCycle, LostSignal,
+    ModelCheckerRun, NetStatistics, Panic, Property, StructuralProof, UnjoinedThread, Verdict,
+    Witness,
+};
+use cargo_check_deadlock::structural;
+use cargo_check_deadlock::unjoined_thread;
+use cargo_check_deadlock::{
+    AnalysisReport, PetriNet, Reporter, Trace, Translation, TranslationError,
+};
+
+/// The model checkers available for the deadlock analysis.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum Checker {
+    /// The external model checker `LoLA`, called on the net in `LoLA` format
+    Lola,
+    /// The built-in explicit-state model checker, does not require any external program
+    Native,
+}
+
+impl std::fmt::Display for Checker {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Lola => write!(f, "LoLA"),
+            Self::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// The entry point analyzed, the translation always starts at the `main` function.
+const ENTRY_POINT: &str = "main";
 
 /// Convert a Rust source code file into a Petri net and export
 /// the resulting net in one of the supported formats.
@@ -32,6 +73,116 @@ pub struct Args {
     #[arg(long)]
     pnml: bool,
 
+    /// If set, outputs the Petri net in `LoLA` format.
+    /// The file is always generated when the model checker is `LoLA`, since it is its input.
+    #[arg(long)]
+    lola: bool,
+
+    /// If set, outputs the findings of the analysis in SARIF 2.1.0 format
+    /// to the file `<output-folder>/<filename>.sarif`.
+    #[arg(long)]
+    sarif: bool,
+
+    /// The model checker used for the deadlock analysis.
+    #[arg(long, value_enum, default_value_t = Checker::Lola)]
+    checker: Checker,
+
+    /// The path to the executable of the model checker `LoLA`.
+    /// If not specified, `lola` is searched in the `$PATH` and then in the `./assets/` folder.
+    #[arg(long, env = "LOLA_PATH")]
+    lola_path: Option<std::path::PathBuf>,
+
+    /// The time limit in seconds for the model checker `LoLA`.
+    /// If it expires, `LoLA` is killed and the result is inconclusive.
+    #[arg(long, value_name = "SECONDS")]
+    lola_timeout: Option<u64>,
+
+    /// The memory limit in MiB for the model checker `LoLA` (only on Unix).
+    /// If it is exceeded, the result is inconclusive.
+    #[arg(long, value_name = "MIB")]
+    lola_memory_limit: Option<u64>,
+
+    /// If set, the `native` model checker explores every interleaving
+    /// instead of applying the partial-order reduction with stubborn sets.
+    #[arg(long)]
+    no_reduction: bool,
+
+    /// If set, the `native` model checker explores the state space both with and without
+    /// the partial-order reduction and reports the number of states of each exploration.
+    #[arg(long)]
+    compare_reduction: bool,
+
+    /// If set, the net is reduced with structural rules that preserve deadlocks before the analysis.
+    /// Chains of transitions without synchronization are fused and redundant places are removed.
+    /// The witness path is expanded to the labels of the original net in the trace and the reports.
+    #[arg(long)]
+    reduce: bool,
+
+    /// If set, a deadlock whose witness path takes contradicting branches on a boolean local is reported as is.
+    /// By default, such a witness is discarded and the net is checked again
+    /// with the value of the boolean locals involved tracked in a copy of the net.
+    #[arg(long)]
+    no_branch_refinement: bool,
+
+    /// A formula in CTL or LTL to check instead of deadlocks, in the syntax of `LoLA`,
+    /// e.g. `EF (holds(thread 1, data) AND holds(main, other))`.
+    /// Mutexes, threads and locations in the source code can be referred to by name, see the README.
+    #[arg(long, conflicts_with_all = ["property_file", "reduce"])]
+    formula: Option<String>,
+
+    /// The path to a file that contains the formula to check instead of deadlocks.
+    /// Lines starting with `#` are comments.
+    #[arg(long, conflicts_with = "reduce")]
+    property_file: Option<std::path::PathBuf>,
+
+    /// If set, checks whether a panic can be reached instead of deadlocks:
+    /// a call to a function that panics, a failed assertion or an `unwrap` of an `Err` or `None` value.
+    #[arg(long, conflicts_with_all = ["formula", "property_file", "reduce"])]
+    check_panics: bool,
+
+    /// If set, checks whether a livelock can be reached instead of deadlocks:
+    /// a state from which the program loops forever and can neither end nor block, under a fairness assumption.
+    #[arg(long, conflicts_with_all = ["formula", "property_file", "check_panics", "reduce"])]
+    check_livelocks: bool,
+
+    /// If set, also checks whether a thread can return while it holds a mutex guard, e.g. one passed to `std::mem::forget`.
+    /// Every mutex is checked separately for every thread that locks it, which may take long on large nets.
+    #[arg(long, conflicts_with_all = ["formula", "property_file", "check_panics", "check_livelocks", "reduce"])]
+    check_held_guards: bool,
+
+    /// If set, checks whether the program can end while a spawned thread still runs instead of deadlocks,
+    /// e.g. a thread that is never joined and is blocked forever or in the middle of a critical section.
+    #[arg(
+        long,
+        conflicts_with_all = ["formula", "property_file", "check_panics", "check_livelocks", "check_held_guards", "reduce"]
+    )]
+    check_unjoined_threads: bool,
+
+    /// If set and a deadlock is found, enumerates every reachable deadlock with the `native` model checker
+    /// and reports each distinct class, i.e. the operations that the threads are blocked on, with its own witness.
+    /// The whole state space is explored, which may take long on large nets.
+    #[arg(
+        long,
+        conflicts_with_all = ["formula", "property_file", "check_panics", "check_livelocks", "check_unjoined_threads"]
+    )]
+    all_deadlocks: bool,
+
+    /// If set, builds the lock-order graph of the threads before running the model checker
+    /// and reports its cycles, i.e. mutexes that the threads lock in different orders.
+    /// The graph is built without exploring the state space. Together with `--skip-analysis`, it replaces the model checker.
+    #[arg(long)]
+    lock_order: bool,
+
+    /// If set, first tries to prove that the program is deadlock-free from the structure of the net,
+    /// with place invariants and traps. If the proof succeeds, the model checker does not run
+    /// and the certificate of the proof is written to a file named `filename.certificate.txt`.
+    /// The proof is also attempted when the model checker cannot decide whether a deadlock is reachable.
+    #[arg(
+        long,
+        conflicts_with_all = ["formula", "property_file", "check_panics", "check_livelocks", "check_unjoined_threads"]
+    )]
+    structural: bool,
+
     /// If set, the reachability analysis to find deadlocks is skipped.
     #[arg(long)]
     skip_analysis: bool,
@@ -41,9 +192,42 @@ pub struct Args {
     #[arg(long)]
     witness_path: bool,
 
+    /// If set, unsupported constructs are approximated instead of aborting the translation.
+    /// They are replaced by opaque transitions to every possible successor,
+    /// which drop the synchronization of the construct, e.g. the locks taken in a function that cannot be called.
+    /// Deadlocks may then be spurious or missed, so a result without a deadlock is inconclusive.
+    /// The result lists the location of each approximation.
+    #[arg(long)]
+    lenient: bool,
+
+    /// If set, only checks whether the translator supports the constructs reachable from the entry point.
+    /// Lists every unsupported construct with its location, then exits without translating.
+    #[arg(long)]
+    check_support: bool,
+
+    /// The path to a baseline file with the fingerprints of the accepted findings.
+    /// A deadlock whose fingerprint is in the baseline does not make the command fail.
+    /// Only deadlocks can be baselined, not lost signals, panics, held guards or other properties.
+    #[arg(long)]
+    baseline: Option<std::path::PathBuf>,
+
+    /// If set, regenerates the baseline file with the findings of this run instead of comparing them.
+    #[arg(long, requires = "baseline")]
+    update_baseline: bool,
+
+    /// The format of the result printed to the standard output.
+    /// With `json`, the analysis report is printed instead of the result message.
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
     /// Verbosity flag.
     #[clap(flatten)]
     verbose: clap_verbosity_flag::Verbosity,
+
+    /// Arguments passed through to `rustc` when compiling the source code file,
+    /// e.g. `-- --edition 2024 --cfg feature="x" --extern foo=libfoo.rlib`.
+    #[arg(last = true)]
+    rustc_flags: Vec<String>,
 }
 
 impl Args {
@@ -81,18 +265,108 @@ impl Args {
             return CargoResult::OutputFolderNotFound(err_str);
         }
 
+        let property = match self.property() {
+            Ok(property) => property,
+            Err(err_str) => return self.invalid_property_result(err_str, None),
+        };
+
         info!("Starting the translation...");
-        let petri_net = match cargo_check_deadlock::run(self.path.clone()) {
-            Ok(petri_net) => petri_net,
-            Err(err_str) => {
-                return CargoResult::TranslationError(err_str.to_string());
+        let options = cargo_check_deadlock::Options {
+            rustc_args: self.rustc_flags.clone(),
+            lenient: self.lenient,
+            reduce: self.reduce,
+        };
+        if self.check_support {
+            info!("Starting the support check...");
+            return match cargo_check_deadlock::check_support(self.path.clone(), &options) {
+                Ok(unsupported) => self.support_check_result(unsupported, &property),
+                Err(err) => self.translation_error_result(err, &property),
+            };
+        }
+
+        // The net is checked while the compiler session is alive to report the findings as diagnostics.
+        match cargo_check_deadlock::run_and_check(
+            self.path.clone(),
+            &options,
+            |translation, reporter| self.check_translation(translation, reporter, &property),
+        ) {
+            Ok((_, result)) => result,
+            Err(err) => self.translation_error_result(err, &property),
+        }
+    }
+
+    /// Returns the property to check: panics, livelocks or unjoined threads
+    /// if `--check-panics`, `--check-livelocks` or `--check-unjoined-threads` is set,
+    /// the formula given with `--formula` or `--property-file`, or deadlocks.
+    ///
+    /// # Errors
+    ///
+    /// If the property file cannot be read or the formula is not well-formed,
+    /// then the function returns an error message.
+    fn property(&self) -> Result<Property, String> {
+        if self.check_panics {
+            return Ok(Property::Panic);
+        }
+        if self.check_livelocks {
+            return Ok(Property::Livelock);
+        }
+        if self.check_unjoined_threads {
+            return Ok(Property::UnjoinedThread { blocked: false });
+        }
+        let text = match (&self.formula, &self.property_file) {
+            (Some(formula), _) => formula.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .map_err(|err| {
+                    format!(
+                        "Could not read the property file at {}: {err}",
+                        path.to_string_lossy()
+                    )
+                })?
+                .lines()
+                .filter(|line| !line.trim_start().starts_with('#'))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            (None, None) => return Ok(Property::Deadlock),
+        };
+        Formula::parse(&text)
+            .map(Property::Formula)
+            .map_err(|err| format!("Invalid formula: {err}"))
+    }
+
+    /// Generates the output files for the translation and runs the analysis of the property.
+    /// A deadlock is also reported as a `rustc` warning with spans in the source code.
+    fn check_translation(
+        &self,
+        translation: &Translation,
+        reporter: &Reporter,
+        property: &Property,
+    ) -> CargoResult {
+        // The names in a formula are resolved before generating the output files to fail early.
+        let (property, instrumented_net) = match property {
+            Property::Formula(formula) => {
+                match formula.resolve(&translation.net, &translation.net_map) {
+                    Ok((formula, net)) => (Property::Formula(formula), net),
+                    Err(err) => {
+                        return self.invalid_property_result(
+                            format!("Invalid formula: {err}"),
+                            Some(property),
+                        );
+                    }
+                }
             }
+            Property::Panic => (Property::Panic, Some(panic::instrument(&translation.net))),
+            Property::UnjoinedThread { .. } => (
+                property.clone(),
+                Some(unjoined_thread::instrument(&translation.net)),
+            ),
+            _ => (property.clone(), None),
         };
+        let net = instrumented_net.as_ref().unwrap_or(&translation.net);
 
         if self.dot {
             let format = OutputFormat::Dot;
             if let Err(err_str) =
-                format.create_output_file(&petri_net, &self.filename, &self.output_folder)
+                format.create_output_file(&translation.net, &self.filename, &self.output_folder)
             {
                 return CargoResult::OutputGenerationError(err_str.to_string());
             }
@@ -101,40 +375,954 @@ impl Args {
         if self.pnml {
             let format = OutputFormat::Pnml;
             if let Err(err_str) =
-                format.create_output_file(&petri_net, &self.filename, &self.output_folder)
+                format.create_output_file(&translation.net, &self.filename, &self.output_folder)
             {
                 return CargoResult::OutputGenerationError(err_str.to_string());
             }
         }
-        // Always generate the file in LoLA format for the deadlock analysis
-        let format = OutputFormat::Lola;
+        // With `LoLA`, the file is always generated. The model checker writes its input file itself when it runs.
+        let export_lola = match self.checker {
+            Checker::Lola => self.skip_analysis,
+            Checker::Native => self.lola,
+        };
+        if export_lola {
+            let format = OutputFormat::Lola;
+            if let Err(err_str) =
+                format.create_output_file(&translation.net, &self.filename, &self.output_folder)
+            {
+                return CargoResult::OutputGenerationError(err_str.to_string());
+            }
+        }
+
+        // Always generate the mapping to the source code to make sense of the net
         if let Err(err_str) =
-            format.create_output_file(&petri_net, &self.filename, &self.output_folder)
+            create_map_file(&translation.net_map, &self.filename, &self.output_folder)
+        {
+            return CargoResult::OutputGenerationError(err_str.to_string());
+        }
+
+        let mut entry_point = match self.analyze(translation, reporter, &property, net) {
+            Ok(entry_point) => entry_point,
+            Err(err) => {
+                return self.model_checker_error_result(format!("{err}"), &property);
+            }
+        };
+        entry_point.verdict = lenient_verdict(entry_point.verdict, &translation.approximations);
+        if self.sarif
+            && let Err(err_str) = create_sarif_file(
+                &self.report(entry_point.clone(), &property),
+                &self.filename,
+                &self.output_folder,
+            )
         {
             return CargoResult::OutputGenerationError(err_str.to_string());
         }
+        let (outcome, note) = match self.apply_baseline(&entry_point) {
+            Ok(result) => result,
+            Err(err_str) => return CargoResult::OutputGenerationError(err_str.to_string()),
+        };
+        match self.message_format {
+            MessageFormat::Json => {
+                CargoResult::Report(self.report(entry_point, &property), outcome)
+            }
+            MessageFormat::Human => {
+                let mut message = match &entry_point.verdict {
+                    Verdict::Deadlock => {
+                        result_message(true, &translation.approximations, self.checker)
+                    }
+                    Verdict::DeadlockFree => match &entry_point.structural_proof {
+                        Some(proof) => structural_message(
+                            proof,
+                            &translation.approximations,
+                            &self.certificate_path(),
+                        ),
+                        None => result_message(false, &translation.approximations, self.checker),
+                    },
+                    Verdict::Panic | Verdict::PanicFree => panic_message(
+                        entry_point.verdict == Verdict::Panic,
+                        &translation.approximations,
+                        self.checker,
+                    ),
+                    Verdict::Livelock | Verdict::LivelockFree => livelock_message(
+                        entry_point.verdict == Verdict::Livelock,
+                        &translation.approximations,
+                        self.checker,
+                    ),
+                    Verdict::UnjoinedThread | Verdict::ThreadsFinished => unjoined_thread_message(
+                        entry_point.verdict == Verdict::UnjoinedThread,
+                        &translation.approximations,
+                        self.checker,
+                    ),
+                    Verdict::Holds | Verdict::DoesNotHold => formula_message(
+                        &property,
+                        entry_point.verdict == Verdict::Holds,
+                        &translation.approximations,
+                        self.checker,
+                    ),
+                    Verdict::Inconclusive { reason } => format!(
+                        "The analysis is inconclusive according to the model checker `{}`: {reason}{}",
+                        self.checker,
+                        approximation_list(&translation.approximations)
+                    ),
+                    Verdict::NotChecked if self.lock_order => {
+                        lock_order_message(&entry_point.lock_order_cycles)
+                    }
+                    Verdict::NotChecked | Verdict::TranslationFailed => {
+                        return CargoResult::SimpleTranslation;
+                    }
+                    Verdict::ModelCheckerFailed { .. } | Verdict::InvalidProperty { .. } => {
+                        panic!(
+                            "BUG: The errors of the analysis should be returned before the result"
+                        )
+                    }
+                };
+                if let Some(states) = state_counts(entry_point.model_checker.as_ref()) {
+                    message.push('\n');
+                    message.push_str(&states);
+                }
+                if let Some(note) = note {
+                    message.push('\n');
+                    message.push_str(&note);
+                }
+                if let Some(refinement) = &entry_point.refinement {
+                    message.push('\n');
+                    message.push_str(&refinement.to_string());
+                }
+                for lost_signal in &entry_point.lost_signals {
+                    message.push('\n');
+                    message.push_str(&lost_signal.to_string());
+                }
+                for held_guard in &entry_point.held_guards {
+                    message.push('\n');
+                    message.push_str(&held_guard.to_string());
+                }
+                if let Some(panic) = &entry_point.panic {
+                    message.push('\n');
+                    message.push_str(&panic.to_string());
+                }
+                if let Some(livelock) = &entry_point.livelock {
+                    message.push('\n');
+                    message.push_str(&livelock.to_string());
+                }
+                for unjoined_thread in &entry_point.unjoined_threads {
+                    message.push('\n');
+                    message.push_str(&unjoined_thread.to_string());
+                }
+                if !entry_point.deadlock_classes.is_empty() {
+                    message.push('\n');
+                    message.push_str(&format!(
+                        "{} distinct deadlock(s) can be reached:",
+                        entry_point.deadlock_classes.len()
+                    ));
+                }
+                for class in &entry_point.deadlock_classes {
+                    message.push_str("\n  - ");
+                    message.push_str(&class.to_string());
+                }
+                for cycle in &entry_point.lock_order_cycles {
+                    message.push('\n');
+                    message.push_str(&cycle.to_string());
+                }
+                if self.witness_path
+                    && let Some(trace) = entry_point.witness.and_then(|witness| witness.trace)
+                {
+                    message.push('\n');
+                    // The final state of the witness of a panic or a formula is not necessarily blocked.
+                    match entry_point.verdict {
+                        Verdict::Deadlock => message.push_str(&trace.to_string()),
+                        Verdict::Panic => {
+                            message.push_str(&trace.steps_to_string("Trace to the panic"))
+                        }
+                        Verdict::Livelock => {
+                            message.push_str(&trace.steps_to_string("Trace to the livelock"))
+                        }
+                        Verdict::UnjoinedThread => message
+                            .push_str(&trace.steps_to_string("Trace to the end of the program")),
+                        _ => message.push_str(&trace.steps_to_string("Trace to the witness state")),
+                    }
+                }
+                CargoResult::DeadlockAnalysis(message, outcome)
+            }
+        }
+    }
 
+    /// Runs the selected model checker on the net unless the analysis is skipped.
+    /// The net is the one of the translation or a copy instrumented for the property.
+    /// Returns the report for the entry point with the verdict, the statistics of the net and the witness.
+    ///
+    /// # Errors
+    ///
+    /// If the model checker fails to check the property, then the function returns an error.
+    fn analyze(
+        &self,
+        translation: &Translation,
+        reporter: &Reporter,
+        property: &Property,
+        net: &PetriNet,
+    ) -> Result<EntryPointReport, ModelCheckerError> {
+        let mut entry_point = EntryPointReport::new(ENTRY_POINT.to_string(), Verdict::NotChecked);
+        entry_point.net = Some(NetStatistics::new(&translation.net));
+        entry_point
+            .approximations
+            .clone_from(&translation.approximations);
+        if self.lock_order {
+            info!("Building the lock-order graph...");
+            // The graph follows the paths of each thread, which the reduction fuses.
+            entry_point.lock_order_cycles =
+                lock_order::cycles(original_net(translation), &translation.net_map);
+        }
+        if self.structural && *property == Property::Deadlock {
+            entry_point.structural_proof = self.prove_structurally(net);
+            if entry_point.structural_proof.is_some() {
+                entry_point.verdict = Verdict::DeadlockFree;
+                return Ok(entry_point);
+            }
+        }
         if self.skip_analysis {
-            return CargoResult::SimpleTranslation;
+            return Ok(entry_point);
+        }
+
+        let model_checker = self.model_checker(self.checker, !self.no_reduction);
+        let start = std::time::Instant::now();
+        let mut output = model_checker.check(net, property.clone())?;
+        let mut run = ModelCheckerRun {
+            name: model_checker.name().to_string(),
+            runtime: start.elapsed(),
+            states: output.states,
+            states_without_reduction: None,
+        };
+        if self.compare_reduction && self.checker == Checker::Native {
+            // The state space is explored once more for comparison, after the runtime was measured.
+            let reduction = !self.no_reduction;
+            let other = self
+                .model_checker(Checker::Native, !reduction)
+                .check(net, property.clone())?;
+            if reduction {
+                run.states_without_reduction = other.states;
+            } else {
+                run.states_without_reduction = run.states;
+                run.states = other.states;
+            }
+        }
+        entry_point.model_checker = Some(run);
+        let mut refinement = (!self.no_branch_refinement)
+            .then(|| Refinement::new(&translation.branches, original_net(translation)));
+        let mut refined_net = None;
+        // After the refinement, the witness path refers to the original net even if the net was reduced.
+        let mut original_labels = false;
+        if output.verdict == Verdict::Deadlock
+            && let Some(refinement) = refinement.as_mut()
+        {
+            original_labels = true;
+            (output, refined_net) = self.refine_branches(translation, refinement, output)?;
+            // `LoLA` wrote the spurious witness path, it is replaced by the one after the refinement.
+            if refined_net.is_some() && self.witness_path && self.checker == Checker::Lola {
+                let path = self.output_folder.join("witness-path.txt");
+                let result = match &output.witness_path {
+                    Some(transitions) => std::fs::write(&path, transitions.join("\n") + "\n"),
+                    None => std::fs::remove_file(&path),
+                };
+                if let Err(err) = result {
+                    warn!("Could not update the witness path: {err}");
+                }
+            }
+        }
+        if output.verdict == Verdict::UnjoinedThread {
+            // A witness where the threads are blocked forever shows what they wait for,
+            // so it is preferred over the shortest one, where they often did not start yet.
+            let blocked = model_checker.check(net, Property::UnjoinedThread { blocked: true })?;
+            if blocked.witness_path.is_some() {
+                output.witness_path = blocked.witness_path;
+            }
+        }
+        entry_point.verdict = output.verdict.clone();
+        if let Some(transitions) = output.witness_path {
+            if self.witness_path && self.checker == Checker::Native {
+                let path = self.output_folder.join("witness-path.txt");
+                if let Err(err) = std::fs::write(&path, transitions.join("\n") + "\n") {
+                    warn!("Could not write the witness path: {err}");
+                }
+            }
+            // The witness path ends with the transition that starts the panic.
+            let panic_cause = transitions
+                .last()
+                .and_then(|label| PanicCause::from_label(label));
+            let witness = if original_labels {
+                original_witness(transitions, translation)
+            } else if matches!(property, Property::Panic) {
+                // The net is instrumented with unwind paths for the calls to `unwrap` without a cleanup block.
+                deadlock_witness(
+                    panic::restore_witness_path(transitions, &translation.net),
+                    translation,
+                )
+            } else if matches!(property, Property::Formula(_)) {
+                // The formula may be checked on a net instrumented with copies of the unlocks.
+                deadlock_witness(formula::restore_witness_path(transitions), translation)
+            } else {
+                deadlock_witness(transitions, translation)
+            };
+            if output.verdict == Verdict::Deadlock
+                && let Some(trace) = &witness.trace
+            {
+                reporter.report_deadlock(trace);
+            }
+            if output.verdict == Verdict::Panic {
+                entry_point.panic = panic_cause.and_then(|cause| Panic::new(cause, &witness));
+            }
+            // The model checker only reports the path to the state, the cycle is searched from there.
+            if output.verdict == Verdict::Livelock {
+                entry_point.livelock = livelock_cycle(net, &witness.transitions)
+                    .map(|cycle| Livelock::new(cycle, net, &translation.net_map));
+            }
+            if output.verdict == Verdict::UnjoinedThread {
+                entry_point.unjoined_threads =
+                    unjoined_thread::unfinished_threads(net, &witness.transitions)
+                        .into_iter()
+                        .filter_map(|thread| UnjoinedThread::new(thread, &witness))
+                        .collect();
+            }
+            entry_point.witness = Some(witness);
+        }
+        // A lost signal always ends in a deadlock, so the condvars only need to be checked after one was found.
+        if entry_point.verdict == Verdict::Deadlock {
+            entry_point.lost_signals = self.check_lost_signals(translation)?;
+        }
+        if self.all_deadlocks && entry_point.verdict == Verdict::Deadlock {
+            entry_point.deadlock_classes =
+                self.enumerate_deadlocks(translation, net, refinement.as_mut(), refined_net);
+        }
+        entry_point.refinement = refinement.as_ref().and_then(Refinement::report);
+        // A guard held when a thread returns only blocks the next thread that locks the mutex,
+        // so it is checked whether a deadlock was found or not.
+        if self.check_held_guards {
+            entry_point.held_guards = self.check_held_guards(translation)?;
+        }
+        Ok(entry_point)
+    }
+
+    /// Tries to prove that the net is deadlock-free from its structure.
+    /// If the proof succeeds, the certificate is written to a file named `filename.certificate.txt` in the output folder.
+    fn prove_structurally(&self, net: &PetriNet) -> Option<StructuralProof> {
+        info!("Trying to prove deadlock-freedom from the structure of the net...");
+        let proof = structural::prove_deadlock_freedom(net)?;
+        if let Err(err) = std::fs::write(self.certificate_path(), proof.to_string()) {
+            warn!("Could not write the certificate of the structural proof: {err}");
+        }
+        Some(proof)
+    }
+
+    /// Returns the path of the certificate of the structural proof in the output folder.
+    fn certificate_path(&self) -> std::path::PathBuf {
+        self.output_folder
+            .join(format!("{}.certificate.txt", self.filename))
+    }
+
+    /// Re-checks the net for deadlocks as long as the witness path takes contradicting branches on boolean locals.
+    /// Every round tracks the value of the locals involved in a new copy of the net instrumented for them.
+    /// If the net was reduced, the refinement runs on the original net, since the reduction fuses the branches
+    /// with the transitions around them.
+    /// Returns the output of the last check and the instrumented net of the last check, if any.
+    /// The witness path refers to the original net in any case.
+    ///
+    /// # Errors
+    ///
+    /// If the model checker fails to check the property, then the function returns an error.
+    fn refine_branches(
+        &self,
+        translation: &Translation,
+        refinement: &mut Refinement,
+        mut output: ModelCheckerOutput,
+    ) -> Result<(ModelCheckerOutput, Option<InstrumentedNet>), ModelCheckerError> {
+        output.witness_path = output
+            .witness_path
+            .map(|transitions| expand_witness_path(transitions, translation));
+        let mut refined_net = None;
+        while output.verdict == Verdict::Deadlock
+            && let Some(transitions) = &output.witness_path
+            && let Some(instrumented) = refinement.refine(std::slice::from_ref(transitions))
+        {
+            output =
+                self.check_instrumented(&instrumented.net, Property::Deadlock, "refinement")?;
+            output.witness_path = output
+                .witness_path
+                .map(|transitions| instrumented.restore_witness_path(transitions));
+            refined_net = Some(instrumented);
+        }
+        Ok((output, refined_net))
+    }
+
+    /// Enumerates every reachable deadlock with the `native` model checker, since `LoLA` stops at the first one,
+    /// and groups them by the operations that the threads are blocked on.
+    /// If the branches are refined, the deadlocks are enumerated on the instrumented net, if any,
+    /// and enumerated again as long as one of their witnesses is spurious.
+    fn enumerate_deadlocks(
+        &self,
+        translation: &Translation,
+        net: &PetriNet,
+        mut refinement: Option<&mut Refinement>,
+        mut refined_net: Option<InstrumentedNet>,
+    ) -> Vec<DeadlockClass> {
+        loop {
+            info!("Enumerating the reachable deadlocks...");
+            let (witness_paths, states) = native::enumerate_deadlocks(
+                refined_net
+                    .as_ref()
+                    .map_or(net, |refined_net| &refined_net.net),
+                !self.no_reduction,
+            );
+            info!(
+                "Found {} deadlock state(s) after visiting {states} states",
+                witness_paths.len()
+            );
+            let witness_paths: Vec<Vec<String>> = witness_paths
+                .into_iter()
+                .map(|transitions| match &refined_net {
+                    Some(refined_net) => refined_net.restore_witness_path(transitions),
+                    None => expand_witness_path(transitions, translation),
+                })
+                .collect();
+            if let Some(refinement) = refinement.as_deref_mut()
+                && let Some(instrumented) = refinement.refine(&witness_paths)
+            {
+                refined_net = Some(instrumented);
+                continue;
+            }
+            return DeadlockClass::group(
+                witness_paths
+                    .into_iter()
+                    .map(|transitions| original_witness(transitions, translation))
+                    .collect(),
+            );
+        }
+    }
+
+    /// Checks every condvar of the net for a signal that is lost before a thread waits on the condvar forever.
+    /// Every condvar is checked separately on a copy of the net instrumented for it.
+    ///
+    /// # Errors
+    ///
+    /// If the model checker fails to check the property, then the function returns an error.
+    fn check_lost_signals(
+        &self,
+        translation: &Translation,
+    ) -> Result<Vec<LostSignal>, ModelCheckerError> {
+        // The reduction only preserves deadlocks, so the lost signals are checked on the original net.
+        let original_net = original_net(translation);
+        let mut lost_signals = Vec::new();
+        for condvar in lost_signal::condvars(original_net) {
+            info!("Checking for lost signals on condvar {condvar}...");
+            let net = lost_signal::instrument(original_net, condvar);
+            let output = self.check_instrumented(
+                &net,
+                Property::LostSignal { condvar },
+                &format!("lost-signal-{condvar}"),
+            )?;
+            if let Verdict::Inconclusive { reason } = &output.verdict {
+                warn!("The check for lost signals on condvar {condvar} is inconclusive: {reason}");
+            }
+            if let Some(transitions) = output.witness_path {
+                let transitions = lost_signal::restore_witness_path(transitions, condvar);
+                let witness = original_witness(transitions, translation);
+                lost_signals.push(LostSignal::new(condvar, witness));
+            }
+        }
+        Ok(lost_signals)
+    }
+
+    /// Checks every mutex and every thread that locks it for a guard that is still held when the thread returns.
+    /// Every pair is checked separately on a copy of the net instrumented for it.
+    ///
+    /// # Errors
+    ///
+    /// If the model checker fails to check the property, then the function returns an error.
+    fn check_held_guards(
+        &self,
+        translation: &Translation,
+    ) -> Result<Vec<HeldGuard>, ModelCheckerError> {
+        let mut held_guards = Vec::new();
+        for (mutex, thread) in held_guard::lockers(&translation.net, &translation.net_map) {
+            info!("Checking whether the {thread} returns while holding mutex {mutex}...");
+            let net = held_guard::instrument(&translation.net, &translation.net_map, mutex, thread);
+            let output = self.check_instrumented(
+                &net,
+                Property::HeldGuard { mutex, thread },
+                &format!(
+                    "held-guard-{mutex}-{}",
+                    thread
+                        .0
+                        .map_or_else(|| "main".to_string(), |index| index.to_string())
+                ),
+            )?;
+            if let Verdict::Inconclusive { reason } = &output.verdict {
+                warn!(
+                    "The check for guards of mutex {mutex} held by the {thread} is inconclusive: {reason}"
+                );
+            }
+            if let Some(transitions) = output.witness_path {
+                let witness =
+                    deadlock_witness(formula::restore_witness_path(transitions), translation);
+                held_guards.push(HeldGuard::new(mutex, thread, witness));
+            }
+        }
+        Ok(held_guards)
+    }
+
+    /// Checks a property on a copy of the net instrumented for it with the model checker selected.
+    /// The `name` tells apart the files of the instrumented nets written for `LoLA`.
+    ///
+    /// # Errors
+    ///
+    /// If the model checker fails to check the property, then the function returns an error.
+    fn check_instrumented(
+        &self,
+        net: &PetriNet,
+        property: Property,
+        name: &str,
+    ) -> Result<ModelCheckerOutput, ModelCheckerError> {
+        match self.checker {
+            Checker::Lola => {
+                // The instrumented net must not overwrite the net exported to the output folder.
+                let net_filepath = std::env::temp_dir().join(format!(
+                    "{}-{name}-{}.lola",
+                    self.filename,
+                    std::process::id()
+                ));
+                let output = Lola::new(self.lola_path.clone(), net_filepath.clone(), None)
+                    .with_limits(
+                        self.lola_timeout.map(std::time::Duration::from_secs),
+                        self.lola_memory_limit,
+                    )
+                    .check(net, property);
+                // Best effort, a leftover file in the temporary folder is harmless.
+                let _ = std::fs::remove_file(&net_filepath);
+                output
+            }
+            Checker::Native => Native::new(!self.no_reduction).check(net, property),
+        }
+    }
+
+    /// Creates the backend for the given model checker with the options of the command.
+    /// The partial-order `reduction` only applies to the `native` model checker.
+    fn model_checker(&self, checker: Checker, reduction: bool) -> Box<dyn ModelChecker> {
+        match checker {
+            Checker::Lola => {
+                let mut net_filepath = self.output_folder.join(&self.filename);
+                net_filepath.set_extension(OutputFormat::Lola.to_string());
+                // `LoLA` writes the witness path itself, it is kept only if the user requested it.
+                let witness_filepath = self
+                    .witness_path
+                    .then(|| self.output_folder.join("witness-path.txt"));
+                Box::new(
+                    Lola::new(self.lola_path.clone(), net_filepath, witness_filepath).with_limits(
+                        self.lola_timeout.map(std::time::Duration::from_secs),
+                        self.lola_memory_limit,
+                    ),
+                )
+            }
+            Checker::Native => Box::new(Native::new(reduction)),
         }
+    }
 
-        let mut filepath = self.output_folder.clone();
-        filepath.push(&self.filename);
-        filepath.set_extension(OutputFormat::Lola.to_string());
+    /// Compares the finding of the entry point with the baseline, or regenerates the baseline if requested.
+    /// Returns the outcome that decides the exit code and a note about the finding for the result message.
+    ///
+    /// # Errors
+    ///
+    /// If the baseline file cannot be read or written, then the function returns an error.
+    /// A baseline file that does not exist yet is treated as empty.
+    fn apply_baseline(
+        &self,
+        entry_point: &EntryPointReport,
+    ) -> Result<(Outcome, Option<String>), std::io::Error> {
+        let finding = Finding::new(entry_point);
+        if self.update_baseline
+            && let Some(path) = &self.baseline
+        {
+            info!("Writing the baseline file {}...", path.to_string_lossy());
+            let mut file = std::fs::File::create(path)?;
+            Baseline::write(finding.as_slice(), &mut file)?;
+            let note = format!(
+                "Baseline with {} finding(s) written to {}",
+                finding.as_slice().len(),
+                path.to_string_lossy()
+            );
+            return Ok((Outcome::Passed, Some(note)));
+        }
 
-        let witness_path = if self.witness_path {
-            let mut path = self.output_folder.clone();
-            path.push("witness-path.txt");
-            Some(path)
+        let Some(finding) = finding else {
+            // Without a result, the program cannot be considered deadlock-free.
+            if matches!(entry_point.verdict, Verdict::Inconclusive { .. }) {
+                return Ok((Outcome::Inconclusive, None));
+            }
+            // A cycle in the lock-order graph is a potential deadlock that the model checker did not confirm or refute.
+            if entry_point.verdict == Verdict::NotChecked
+                && !entry_point.lock_order_cycles.is_empty()
+            {
+                return Ok((Outcome::Inconclusive, None));
+            }
+            return Ok((Outcome::Passed, None));
+        };
+        let note = format!("Fingerprint of the finding: {}", finding.fingerprint);
+        // A deadlock under approximations may be spurious, so it does not count as a confirmed deadlock.
+        let new_finding = if entry_point.approximations.is_empty() {
+            Outcome::NewDeadlock
         } else {
-            None
+            Outcome::Inconclusive
+        };
+        let Some(path) = &self.baseline else {
+            return Ok((new_finding, Some(note)));
         };
 
-        let message = if lola::check_deadlock(&filepath, witness_path.as_ref()) {
-            "Deadlock can be reached according to the model checker `LoLA`"
-        } else {
-            "The program is deadlock-free according to the model checker `LoLA`"
+        let baseline = match std::fs::read_to_string(path) {
+            Ok(contents) => Baseline::parse(&contents),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                warn!(
+                    "Baseline file at {} does not exist, no finding is accepted",
+                    path.to_string_lossy()
+                );
+                Baseline::new()
+            }
+            Err(err) => return Err(err),
         };
-        CargoResult::DeadlockAnalysis(message.to_string())
+        if baseline.contains(&finding) {
+            Ok((
+                Outcome::Passed,
+                Some(format!("{note} (accepted by the baseline)")),
+            ))
+        } else {
+            Ok((new_finding, Some(note)))
+        }
+    }
+
+    /// Wraps the report of the entry point in the report of the whole analysis.
+    fn report(&self, entry_point: EntryPointReport, property: &Property) -> AnalysisReport {
+        let mut report = AnalysisReport::new(
+            self.path.to_string_lossy().into_owned(),
+            Some(property.clone()),
+        );
+        report.entry_points.push(entry_point);
+        report
+    }
+
+    /// Converts the result of the support check to the result in the requested message format.
+    fn support_check_result(
+        &self,
+        unsupported: Vec<TranslationError>,
+        property: &Property,
+    ) -> CargoResult {
+        match self.message_format {
+            MessageFormat::Human => {
+                CargoResult::SupportCheck(unsupported.iter().map(ToString::to_string).collect())
+            }
+            MessageFormat::Json => {
+                let mut entry_point =
+                    EntryPointReport::new(ENTRY_POINT.to_string(), Verdict::NotChecked);
+                let outcome = if unsupported.is_empty() {
+                    Outcome::Passed
+                } else {
+                    Outcome::Unsupported
+                };
+                entry_point.unsupported = unsupported;
+                CargoResult::Report(self.report(entry_point, property), outcome)
+            }
+        }
+    }
+
+    /// Converts an error that stopped the translation to the result in the requested message format.
+    fn translation_error_result(&self, err: TranslationError, property: &Property) -> CargoResult {
+        match self.message_format {
+            MessageFormat::Human => {
+                CargoResult::TranslationError(format!("Translation failed: {err}"))
+            }
+            MessageFormat::Json => {
+                let mut entry_point =
+                    EntryPointReport::new(ENTRY_POINT.to_string(), Verdict::TranslationFailed);
+                entry_point.error = Some(err);
+                CargoResult::Report(
+                    self.report(entry_point, property),
+                    Outcome::TranslationFailed,
+                )
+            }
+        }
+    }
+
+    /// Converts an error of the model checker to the result in the requested message format.
+    fn model_checker_error_result(&self, reason: String, property: &Property) -> CargoResult {
+        match self.message_format {
+            MessageFormat::Human => {
+                CargoResult::ModelCheckerError(format!("Model checker failed: {reason}"))
+            }
+            MessageFormat::Json => {
+                let verdict = Verdict::ModelCheckerFailed { reason };
+                let entry_point = EntryPointReport::new(ENTRY_POINT.to_string(), verdict);
+                CargoResult::Report(
+                    self.report(entry_point, property),
+                    Outcome::ModelCheckerFailed,
+                )
+            }
+        }
+    }
+
+    /// Converts an invalid formula to the result in the requested message format.
+    /// The property is missing from the report if the formula could not be read or parsed.
+    fn invalid_property_result(&self, reason: String, property: Option<&Property>) -> CargoResult {
+        match self.message_format {
+            MessageFormat::Human => CargoResult::InvalidProperty(reason),
+            MessageFormat::Json => {
+                let verdict = Verdict::InvalidProperty { reason };
+                let mut report = AnalysisReport::new(
+                    self.path.to_string_lossy().into_owned(),
+                    property.cloned(),
+                );
+                report
+                    .entry_points
+                    .push(EntryPointReport::new(ENTRY_POINT.to_string(), verdict));
+                CargoResult::Report(report, Outcome::InvalidProperty)
+            }
+        }
+    }
+}
+
+/// Replays the witness path found by the model checker on the net
+/// to build a human-readable trace of the deadlock.
+/// If the net was reduced, the witness path is first expanded and replayed on the original net.
+/// The witness is returned without the trace if the replay fails.
+fn deadlock_witness(transitions: Vec<String>, translation: &Translation) -> Witness {
+    original_witness(expand_witness_path(transitions, translation), translation)
+}
+
+/// Builds the witness from a witness path that already refers to the original net.
+fn original_witness(transitions: Vec<String>, translation: &Translation) -> Witness {
+    let trace = Trace::new(
+        &transitions,
+        original_net(translation),
+        &translation.net_map,
+    )
+    .inspect_err(|err_str| warn!("Could not build the trace from the witness path: {err_str}"))
+    .ok();
+    Witness { transitions, trace }
+}
+
+/// Expands the witness path to the labels of the original net if the net was reduced.
+fn expand_witness_path(transitions: Vec<String>, translation: &Translation) -> Vec<String> {
+    match &translation.reduction {
+        Some(reduction) => reduction.expand(&transitions),
+        None => transitions,
+    }
+}
+
+/// Returns the net of the translation before the reduction, if any.
+fn original_net(translation: &Translation) -> &PetriNet {
+    translation
+        .reduction
+        .as_ref()
+        .map_or(&translation.net, Reduction::original_net)
+}
+
+/// Describes the number of states explored by the model checker, if it reports them.
+fn state_counts(run: Option<&ModelCheckerRun>) -> Option<String> {
+    let run = run?;
+    let states = run.states?;
+    Some(run.states_without_reduction.map_or_else(
+        || format!("States explored: {states}"),
+        |states_without_reduction| {
+            format!(
+                "States explored: {states} with partial-order reduction, {states_without_reduction} without"
+            )
+        },
+    ))
+}
+
+/// Replaces a verdict without a finding by an inconclusive one if the net contains approximations.
+/// An approximation drops the synchronization of the unsupported construct, so it may hide the finding.
+fn lenient_verdict(verdict: Verdict, approximations: &[TranslationError]) -> Verdict {
+    let missed = match verdict {
+        Verdict::DeadlockFree => "a deadlock",
+        Verdict::PanicFree => "a panic",
+        Verdict::LivelockFree => "a livelock",
+        Verdict::ThreadsFinished => "an unjoined thread",
+        _ => return verdict,
+    };
+    if approximations.is_empty() {
+        return verdict;
+    }
+    Verdict::Inconclusive {
+        reason: format!(
+            "the {} approximation(s) of unsupported constructs may hide {missed}",
+            approximations.len()
+        ),
+    }
+}
+
+/// Lists the location of every approximation, one per line.
+fn approximation_list(approximations: &[TranslationError]) -> String {
+    approximations
+        .iter()
+        .map(|approximation| format!("\n  - {approximation}"))
+        .collect()
+}
+
+/// Builds the message with the result of an analysis, e.g. `Deadlock can be reached`, followed by the `source` of the result.
+/// If the net contains approximations of unsupported constructs,
+/// the message qualifies the result with their number, adds the `caveat`, if any,
+/// and lists the location of every approximation.
+fn qualified_message(
+    verdict: &str,
+    caveat: Option<&str>,
+    approximations: &[TranslationError],
+    source: &str,
+) -> String {
+    let mut message = if approximations.is_empty() {
+        format!("{verdict} according to {source}")
+    } else {
+        let mut message = format!(
+            "{verdict} under {} approximation(s) according to {source}",
+            approximations.len()
+        );
+        if let Some(caveat) = caveat {
+            message.push_str(". ");
+            message.push_str(caveat);
+        }
+        message
+    };
+    message.push_str(&approximation_list(approximations));
+    message
+}
+
+/// Builds the message with the result of the deadlock analysis.
+fn result_message(deadlock: bool, approximations: &[TranslationError], checker: Checker) -> String {
+    let source = format!("the model checker `{checker}`");
+    if deadlock {
+        qualified_message(
+            "Deadlock can be reached",
+            Some("The deadlock may be spurious"),
+            approximations,
+            &source,
+        )
+    } else {
+        qualified_message(
+            "The program is deadlock-free",
+            None,
+            approximations,
+            &source,
+        )
+    }
+}
+
+/// Builds the message with the result of the structural proof of deadlock-freedom,
+/// with the number of invariants and traps used and the path to the certificate.
+fn structural_message(
+    proof: &StructuralProof,
+    approximations: &[TranslationError],
+    certificate: &std::path::Path,
+) -> String {
+    let mut message = qualified_message(
+        "The program is deadlock-free",
+        None,
+        approximations,
+        "the structural analysis",
+    );
+    message.push('\n');
+    message.push_str(&format!(
+        "{}. Certificate written to {}",
+        proof.summary(),
+        certificate.to_string_lossy()
+    ));
+    message
+}
+
+/// Builds the message with the result of checking a formula supplied by the user.
+fn formula_message(
+    property: &Property,
+    holds: bool,
+    approximations: &[TranslationError],
+    checker: Checker,
+) -> String {
+    let text = match property {
+        Property::Formula(formula) => formula.text.as_str(),
+        _ => "",
+    };
+    let result = if holds { "holds" } else { "does not hold" };
+    qualified_message(
+        &format!("The formula `{text}` {result}"),
+        Some("The result may differ in the program"),
+        approximations,
+        &format!("the model checker `{checker}`"),
+    )
+}
+
+/// Builds the message with the result of the panic analysis.
+fn panic_message(panic: bool, approximations: &[TranslationError], checker: Checker) -> String {
+    let source = format!("the model checker `{checker}`");
+    if panic {
+        qualified_message(
+            "A panic can be reached",
+            Some("The panic may be spurious"),
+            approximations,
+            &source,
+        )
+    } else {
+        qualified_message("The program cannot panic", None, approximations, &source)
+    }
+}
+
+/// Builds the message with the result of the livelock analysis.
+fn livelock_message(
+    livelock: bool,
+    approximations: &[TranslationError],
+    checker: Checker,
+) -> String {
+    let source = format!("the model checker `{checker}`");
+    if livelock {
+        qualified_message(
+            "A livelock can be reached",
+            Some("The livelock may be spurious"),
+            approximations,
+            &source,
+        )
+    } else {
+        qualified_message(
+            "The program is livelock-free",
+            None,
+            approximations,
+            &source,
+        )
+    }
+}
+
+/// Builds the message with the result of the analysis of unjoined threads.
+fn unjoined_thread_message(
+    unjoined: bool,
+    approximations: &[TranslationError],
+    checker: Checker,
+) -> String {
+    let source = format!("the model checker `{checker}`");
+    if unjoined {
+        qualified_message(
+            "The program can end while a spawned thread still runs",
+            Some("The result may be spurious"),
+            approximations,
+            &source,
+        )
+    } else {
+        qualified_message(
+            "Every spawned thread ends before the program",
+            None,
+            approximations,
+            &source,
+        )
+    }
+}
+
+/// Builds the message with the result of the lock-order graph when the model checker did not run.
+/// A cycle is only a potential deadlock, since the graph over-approximates the program.
+fn lock_order_message(cycles: &[LockOrderCycle]) -> String {
+    if cycles.is_empty() {
+        "The lock-order graph has no cycle between mutexes locked by different threads".to_string()
+    } else {
+        format!(
+            "The lock-order graph has {} cycle(s). A deadlock may be reachable, run the model checker to confirm it",
+            cycles.len()
+        )
     }
 }
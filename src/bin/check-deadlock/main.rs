@@ -12,9 +12,11 @@ mod output_format;
 use clap::Parser;
 
 use cargo_result::CargoResult::{
-    DeadlockAnalysis, OutputFolderNotFound, OutputGenerationError, SimpleTranslation,
-    SourceFileNotFound, TranslationError,
+    DeadlockAnalysis, InvalidProperty, ModelCheckerError, OutputFolderNotFound,
+    OutputGenerationError, Report, SimpleTranslation, SourceFileNotFound, SupportCheck,
+    TranslationError,
 };
+use cargo_result::Outcome;
 
 fn main() {
     let args = cli::Command::parse();
@@ -36,9 +38,40 @@ fn main() {
             eprintln!("{err_str}");
             std::process::exit(4);
         }
-        DeadlockAnalysis(message) => {
+        ModelCheckerError(err_str) => {
+            eprintln!("{err_str}");
+            std::process::exit(8);
+        }
+        InvalidProperty(err_str) => {
+            eprintln!("{err_str}");
+            std::process::exit(9);
+        }
+        DeadlockAnalysis(message, outcome) => {
             println!("Result: {message}");
+            if outcome != Outcome::Passed {
+                std::process::exit(outcome.exit_code());
+            }
         }
         SimpleTranslation => {}
+        SupportCheck(unsupported) => {
+            if unsupported.is_empty() {
+                println!("All the constructs reachable from the entry point are supported");
+                return;
+            }
+            println!("Found {} unsupported construct(s):", unsupported.len());
+            for construct in unsupported {
+                println!("  - {construct}");
+            }
+            std::process::exit(5);
+        }
+        Report(report, outcome) => {
+            if let Err(err) = report.to_json(&mut std::io::stdout()) {
+                eprintln!("Could not print the report: {err}");
+                std::process::exit(4);
+            }
+            if outcome != Outcome::Passed {
+                std::process::exit(outcome.exit_code());
+            }
+        }
     }
 }
@@ -1,7 +1,13 @@
 use clap::ValueEnum;
 use log::info;
 
-use cargo_check_deadlock::PetriNet;
+use cargo_check_deadlock::json;
+use cargo_check_deadlock::report::{
+    DeadlockClass, EntryPointReport, HeldGuard, LockOrderCycle, LostSignal, Property, Verdict,
+};
+use cargo_check_deadlock::source_span::SourceSpan;
+use cargo_check_deadlock::trace::{ThreadId, Trace};
+use cargo_check_deadlock::{AnalysisReport, NetMap, PetriNet};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 /// Possible file formats for the Petri net
@@ -41,6 +47,548 @@ impl OutputFormat {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Default)]
+/// Possible formats for the result printed to the standard output
+pub enum MessageFormat {
+    /// A sentence with the result, followed by the trace if requested
+    #[default]
+    Human,
+    /// The analysis report in JSON format
+    Json,
+}
+
+/// Writes the mapping of the Petri net back to the source code
+/// to a JSON file named `filename.map.json` in the given output folder.
+///
+/// # Errors
+///
+/// If the file cannot be created, then the function returns an error.
+/// If the mapping cannot be written to the file, then the function returns an error.
+pub fn create_map_file(
+    net_map: &NetMap,
+    filename: &str,
+    output_folder: &std::path::Path,
+) -> Result<(), std::io::Error> {
+    let mut filepath = output_folder.to_path_buf();
+    filepath.push(format!("{filename}.map.json"));
+
+    info!("Creating output file {}...", filepath.to_string_lossy());
+    let mut file = std::fs::File::create(filepath)?;
+    net_map.to_json(&mut file)
+}
+
+/// Writes the findings of the analysis report in SARIF 2.1.0 format
+/// to a file named `filename.sarif` in the given output folder.
+///
+/// # Errors
+///
+/// If the file cannot be created, then the function returns an error.
+/// If the report cannot be written to the file, then the function returns an error.
+pub fn create_sarif_file(
+    report: &AnalysisReport,
+    filename: &str,
+    output_folder: &std::path::Path,
+) -> Result<(), std::io::Error> {
+    let mut filepath = output_folder.to_path_buf();
+    filepath.push(format!("{filename}.sarif"));
+
+    info!("Creating output file {}...", filepath.to_string_lossy());
+    let mut file = std::fs::File::create(filepath)?;
+    write_sarif(report, &mut file)
+}
+
+/// Writes the findings of the analysis report in SARIF 2.1.0 format.
+/// See the specification: <https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html>
+///
+/// Every violation of the property is a result whose locations are the calls where the threads block.
+/// The witness path is included as a code flow with one thread flow per thread.
+/// Every lost signal is a result of its own rule, located at the notify call and the wait.
+/// Every cycle in the lock-order graph is a warning of its own rule, located at the lock calls.
+///
+/// # Errors
+///
+/// If writing to the writer fails, then the function returns an error.
+pub fn write_sarif<T: std::io::Write>(
+    report: &AnalysisReport,
+    writer: &mut T,
+) -> Result<(), std::io::Error> {
+    writeln!(writer, "{{")?;
+    writeln!(
+        writer,
+        "  \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\","
+    )?;
+    writeln!(writer, "  \"version\": \"2.1.0\",")?;
+    writeln!(writer, "  \"runs\": [")?;
+    writeln!(writer, "    {{")?;
+    writeln!(writer, "      \"tool\": {{")?;
+    writeln!(writer, "        \"driver\": {{")?;
+    writeln!(
+        writer,
+        "          \"name\": {},",
+        json::string(env!("CARGO_PKG_NAME"))
+    )?;
+    writeln!(
+        writer,
+        "          \"version\": {},",
+        json::string(env!("CARGO_PKG_VERSION"))
+    )?;
+    writeln!(
+        writer,
+        "          \"informationUri\": {},",
+        json::string(env!("CARGO_PKG_REPOSITORY"))
+    )?;
+    let mut rules: Vec<Property> = report.property.iter().cloned().collect();
+    let lost_signals: Vec<&LostSignal> = report
+        .entry_points
+        .iter()
+        .flat_map(|entry_point| &entry_point.lost_signals)
+        .collect();
+    if let Some(lost_signal) = lost_signals.first() {
+        rules.push(Property::LostSignal {
+            condvar: lost_signal.condvar,
+        });
+    }
+    let held_guards: Vec<&HeldGuard> = report
+        .entry_points
+        .iter()
+        .flat_map(|entry_point| &entry_point.held_guards)
+        .collect();
+    if let Some(held_guard) = held_guards.first() {
+        rules.push(Property::HeldGuard {
+            mutex: held_guard.mutex,
+            thread: held_guard.thread,
+        });
+    }
+    let lock_order_cycles: Vec<&LockOrderCycle> = report
+        .entry_points
+        .iter()
+        .flat_map(|entry_point| &entry_point.lock_order_cycles)
+        .collect();
+    let mut rules: Vec<(String, &str)> = rules
+        .iter()
+        .map(|property| (property.to_string(), rule_description(property)))
+        .collect();
+    // The lock-order graph is not a property of the net, so its rule has no corresponding `Property`.
+    if !lock_order_cycles.is_empty() {
+        rules.push((LOCK_ORDER_RULE.to_string(), LOCK_ORDER_DESCRIPTION));
+    }
+    writeln!(
+        writer,
+        "          \"rules\": {}",
+        json::array(rules.into_iter().map(|(id, description)| format!(
+            "{{\"id\": {}, \"shortDescription\": {{\"text\": {}}}}}",
+            json::string(&id),
+            json::string(description)
+        )))
+    )?;
+    writeln!(writer, "        }}")?;
+    writeln!(writer, "      }},")?;
+    let results: Vec<String> = report
+        .entry_points
+        .iter()
+        .flat_map(|entry_point| {
+            if entry_point.deadlock_classes.is_empty() {
+                report
+                    .property
+                    .as_ref()
+                    .and_then(|property| sarif_result(property, entry_point))
+                    .into_iter()
+                    .collect()
+            } else {
+                // Every class of deadlocks is a result of its own, with its own witness.
+                entry_point
+                    .deadlock_classes
+                    .iter()
+                    .map(deadlock_class_result)
+                    .collect::<Vec<_>>()
+            }
+        })
+        .chain(lost_signals.into_iter().map(lost_signal_result))
+        .chain(held_guards.into_iter().map(held_guard_result))
+        .chain(lock_order_cycles.into_iter().map(lock_order_result))
+        .collect();
+    if results.is_empty() {
+        writeln!(writer, "      \"results\": []")?;
+    } else {
+        writeln!(writer, "      \"results\": [")?;
+        writeln!(writer, "{}", results.join(",\n"))?;
+        writeln!(writer, "      ]")?;
+    }
+    writeln!(writer, "    }}")?;
+    writeln!(writer, "  ]")?;
+    writeln!(writer, "}}")
+}
+
+/// The identifier of the rule for the cycles in the lock-order graph.
+const LOCK_ORDER_RULE: &str = "lock_order";
+
+/// The short description of the rule for the cycles in the lock-order graph.
+const LOCK_ORDER_DESCRIPTION: &str =
+    "Threads lock the same mutexes in different orders, which may lead to a deadlock";
+
+/// Returns the short description of the rule that corresponds to a property.
+const fn rule_description(property: &Property) -> &'static str {
+    match property {
+        Property::Deadlock => {
+            "A state can be reached where every thread is blocked before the program ends"
+        }
+        Property::Panic => {
+            "A function that panics is called, an assertion fails or an `Err` or `None` value is unwrapped"
+        }
+        Property::Livelock => {
+            "A state can be reached from which the program loops forever without terminating"
+        }
+        Property::UnjoinedThread { .. } => {
+            "The program can end while a spawned thread still runs, e.g. because it is never joined"
+        }
+        Property::Formula(_) => "The formula supplied by the user",
+        Property::LostSignal { .. } => {
+            "A signal on a condvar is lost while no thread waits and a thread later waits on the condvar forever"
+        }
+        Property::HeldGuard { .. } => {
+            "A thread returns while it holds a mutex guard, so the mutex stays locked forever"
+        }
+    }
+}
+
+/// Formats the result for a lost signal. The locations are the notify call whose signal is lost
+/// and the wait that never returns.
+fn lost_signal_result(lost_signal: &LostSignal) -> String {
+    let locations = [&lost_signal.notify_span, &lost_signal.wait_span]
+        .into_iter()
+        .flatten()
+        .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span)));
+    let fields = [
+        format!(
+            "          \"ruleId\": {}",
+            json::string(
+                &Property::LostSignal {
+                    condvar: lost_signal.condvar
+                }
+                .to_string()
+            )
+        ),
+        "          \"level\": \"error\"".to_string(),
+        format!(
+            "          \"message\": {{\"text\": {}}}",
+            json::string(&lost_signal.to_string())
+        ),
+        format!("          \"locations\": {}", json::array(locations)),
+    ];
+    format!("        {{\n{}\n        }}", fields.join(",\n"))
+}
+
+/// Formats the result for a guard held at the end of a thread, located at the lock call that acquired it.
+fn held_guard_result(held_guard: &HeldGuard) -> String {
+    let locations = held_guard
+        .lock_span
+        .iter()
+        .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span)))
+        .collect();
+    result_fields(
+        &Property::HeldGuard {
+            mutex: held_guard.mutex,
+            thread: held_guard.thread,
+        },
+        &held_guard.to_string(),
+        locations,
+        held_guard
+            .witness
+            .trace
+            .as_ref()
+            .map(|trace| (trace, false)),
+    )
+}
+
+/// Formats the result for a class of deadlocks, located at the calls where the threads block.
+fn deadlock_class_result(class: &DeadlockClass) -> String {
+    let locations = class
+        .blocked
+        .iter()
+        .flat_map(|(_, waits_for)| waits_for)
+        .filter_map(|(_, span)| span.as_ref())
+        .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span)))
+        .collect();
+    result_fields(
+        &Property::Deadlock,
+        &class.to_string(),
+        locations,
+        class.witness.trace.as_ref().map(|trace| (trace, true)),
+    )
+}
+
+/// Formats the result for a cycle in the lock-order graph, located at the lock calls of every edge.
+/// The result is a warning, since the cycle is a potential deadlock that the model checker may refute.
+fn lock_order_result(cycle: &LockOrderCycle) -> String {
+    let locations = cycle
+        .edges
+        .iter()
+        .flat_map(|edge| [&edge.held_span, &edge.acquired_span])
+        .flatten()
+        .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span)));
+    let fields = [
+        format!("          \"ruleId\": {}", json::string(LOCK_ORDER_RULE)),
+        "          \"level\": \"warning\"".to_string(),
+        format!(
+            "          \"message\": {{\"text\": {}}}",
+            json::string(&cycle.to_string())
+        ),
+        format!("          \"locations\": {}", json::array(locations)),
+    ];
+    format!("        {{\n{}\n        }}", fields.join(",\n"))
+}
+
+/// Formats the result for an entry point that violates the property, one line per location.
+/// A deadlock is located at the calls where the threads block, a panic at the call that panics,
+/// a livelock at the code of the basic blocks in the cycle and an unjoined thread at its spawn and its blocked calls.
+/// Returns `None` if the property holds or was not checked.
+fn sarif_result(property: &Property, entry_point: &EntryPointReport) -> Option<String> {
+    let trace = entry_point
+        .witness
+        .as_ref()
+        .and_then(|witness| witness.trace.as_ref());
+    // The threads are only blocked at the end of the trace of a deadlock.
+    match entry_point.verdict {
+        Verdict::Deadlock => {}
+        Verdict::Panic => {
+            let panic = entry_point.panic.as_ref();
+            let message = panic.map_or_else(
+                || format!("A panic can be reached from `{}`", entry_point.entry_point),
+                ToString::to_string,
+            );
+            let locations = panic
+                .and_then(|panic| panic.span.as_ref())
+                .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span)));
+            return Some(result_fields(
+                property,
+                &message,
+                locations.into_iter().collect(),
+                trace.map(|trace| (trace, false)),
+            ));
+        }
+        Verdict::Livelock => {
+            let livelock = entry_point.livelock.as_ref();
+            let message = livelock.map_or_else(
+                || {
+                    format!(
+                        "A livelock can be reached from `{}`",
+                        entry_point.entry_point
+                    )
+                },
+                ToString::to_string,
+            );
+            let locations = livelock
+                .into_iter()
+                .flat_map(|livelock| &livelock.blocks)
+                .filter_map(|block| block.span.as_ref())
+                .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span)))
+                .collect();
+            return Some(result_fields(
+                property,
+                &message,
+                locations,
+                trace.map(|trace| (trace, false)),
+            ));
+        }
+        Verdict::UnjoinedThread => {
+            let message = if entry_point.unjoined_threads.is_empty() {
+                format!(
+                    "The program can end while a spawned thread still runs from `{}`",
+                    entry_point.entry_point
+                )
+            } else {
+                entry_point
+                    .unjoined_threads
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let locations = entry_point
+                .unjoined_threads
+                .iter()
+                .flat_map(|unjoined_thread| {
+                    std::iter::once(&unjoined_thread.spawn_span)
+                        .chain(unjoined_thread.waits_for.iter().map(|(_, span)| span))
+                })
+                .flatten()
+                .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span)))
+                .collect();
+            return Some(result_fields(
+                property,
+                &message,
+                locations,
+                trace.map(|trace| (trace, false)),
+            ));
+        }
+        _ => return None,
+    }
+
+    let mut message = format!("Deadlock can be reached from `{}`", entry_point.entry_point);
+    let mut locations = Vec::new();
+    if let Some(trace) = trace {
+        let blocked: Vec<String> = trace
+            .blocked
+            .iter()
+            .flat_map(|blocked_thread| {
+                blocked_thread.waits_for.iter().map(|(resource, _, _)| {
+                    format!("{} waits for {resource}", blocked_thread.thread)
+                })
+            })
+            .collect();
+        if !blocked.is_empty() {
+            message = format!("{message}: {}", blocked.join(", "));
+        }
+        locations.extend(
+            trace
+                .blocked
+                .iter()
+                .flat_map(|blocked_thread| &blocked_thread.waits_for)
+                .filter_map(|(_, _, span)| span.as_ref())
+                .map(|span| format!("{{\"physicalLocation\": {}}}", physical_location(span))),
+        );
+    }
+    Some(result_fields(
+        property,
+        &message,
+        locations,
+        trace.map(|trace| (trace, true)),
+    ))
+}
+
+/// Formats the fields of a result with the message and the locations.
+/// If the witness was replayed, the result contains a code flow with one thread flow per thread,
+/// ending with the blocked calls if requested.
+fn result_fields(
+    property: &Property,
+    message: &str,
+    locations: Vec<String>,
+    trace: Option<(&Trace, bool)>,
+) -> String {
+    let mut fields = vec![
+        format!(
+            "          \"ruleId\": {}",
+            json::string(&property.to_string())
+        ),
+        "          \"level\": \"error\"".to_string(),
+        format!(
+            "          \"message\": {{\"text\": {}}}",
+            json::string(message)
+        ),
+        format!("          \"locations\": {}", json::array(locations)),
+    ];
+    if let Some((trace, blocked)) = trace {
+        let thread_flows: Vec<String> = thread_flows(trace, blocked)
+            .into_iter()
+            .map(|(thread, locations)| {
+                format!(
+                    "            {{\"id\": {}, \"locations\": [\n{}\n            ]}}",
+                    json::string(&thread.to_string()),
+                    locations.join(",\n")
+                )
+            })
+            .collect();
+        fields.push(format!(
+            "          \"codeFlows\": [{{\"threadFlows\": [\n{}\n          ]}}]",
+            thread_flows.join(",\n")
+        ));
+    }
+    format!("        {{\n{}\n        }}", fields.join(",\n"))
+}
+
+/// Splits the trace into one list of thread flow locations per thread, in order of appearance.
+/// Every step keeps its position in the whole trace as execution order.
+/// If `blocked` is set, the blocked calls are appended at the end of each thread flow.
+fn thread_flows(trace: &Trace, blocked: bool) -> Vec<(ThreadId, Vec<String>)> {
+    let mut flows: Vec<(ThreadId, Vec<String>)> = Vec::new();
+
+    for (order, step) in trace.steps.iter().enumerate() {
+        let text = if step.actions.is_empty() {
+            step.transition.clone()
+        } else {
+            let actions: Vec<String> = step.actions.iter().map(ToString::to_string).collect();
+            actions.join(", ")
+        };
+        let index = flow_of(step.thread, &mut flows);
+        flows[index].1.push(thread_flow_location(
+            step.span.as_ref(),
+            &text,
+            order + 1,
+            !step.actions.is_empty(),
+        ));
+    }
+    if !blocked {
+        return flows;
+    }
+    let mut order = trace.steps.len();
+    for blocked_thread in &trace.blocked {
+        for (resource, _, span) in &blocked_thread.waits_for {
+            order += 1;
+            let index = flow_of(blocked_thread.thread, &mut flows);
+            flows[index].1.push(thread_flow_location(
+                span.as_ref(),
+                &format!("blocks waiting for {resource}"),
+                order,
+                true,
+            ));
+        }
+    }
+    flows
+}
+
+/// Returns the index of the thread flow of the given thread, adding it if it does not exist yet.
+fn flow_of(thread: ThreadId, flows: &mut Vec<(ThreadId, Vec<String>)>) -> usize {
+    flows
+        .iter()
+        .position(|(flow_thread, _)| *flow_thread == thread)
+        .unwrap_or_else(|| {
+            flows.push((thread, Vec::new()));
+            flows.len() - 1
+        })
+}
+
+/// Formats a location of a thread flow as a JSON object in a single line.
+/// The steps that touch synchronization primitives are marked as essential.
+fn thread_flow_location(
+    span: Option<&SourceSpan>,
+    text: &str,
+    execution_order: usize,
+    essential: bool,
+) -> String {
+    let location = span.map_or_else(
+        || format!("{{\"message\": {{\"text\": {}}}}}", json::string(text)),
+        |span| {
+            format!(
+                "{{\"message\": {{\"text\": {}}}, \"physicalLocation\": {}}}",
+                json::string(text),
+                physical_location(span)
+            )
+        },
+    );
+    format!(
+        "              {{\"location\": {location}, \"executionOrder\": {execution_order}, \"importance\": {}}}",
+        if essential {
+            "\"essential\""
+        } else {
+            "\"unimportant\""
+        }
+    )
+}
+
+/// Formats a span as a SARIF physical location.
+/// The URI of the artifact is the path of the file as shown in the `rustc` diagnostics.
+fn physical_location(span: &SourceSpan) -> String {
+    let uri = span.file.strip_prefix("./").unwrap_or(&span.file);
+    format!(
+        "{{\"artifactLocation\": {{\"uri\": {}}}, \"region\": {{\"startLine\": {}, \"startColumn\": {}, \"endLine\": {}, \"endColumn\": {}}}}}",
+        json::string(uri),
+        span.start_line,
+        span.start_column,
+        span.end_line,
+        span.end_column
+    )
+}
+
 impl std::fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
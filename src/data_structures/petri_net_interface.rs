@@ -2,6 +2,8 @@
 //! around the methods provided by the library `netcrab`.
 //! This ensures a proper error handling when adding arcs to the net.
 
+use std::collections::HashMap;
+
 pub use netcrab::petri_net::{PetriNet, PlaceRef, TransitionRef};
 
 /// Adds an arc from a place to a transition with multiplicity one.
@@ -63,3 +65,41 @@ pub fn connect_places(
     add_arc_transition_place(net, &transition, end_place);
     transition
 }
+
+/// Copies the places with their initial marking and the transitions with their arcs to a new net,
+/// e.g. to add places that observe the behavior of the net without modifying the original.
+/// Returns the new net and the references to its places and transitions by label.
+///
+/// # Panics
+///
+/// If the places or the arcs could not be created, then the function panics.
+pub fn copy_net(
+    net: &PetriNet,
+) -> (
+    PetriNet,
+    HashMap<String, PlaceRef>,
+    HashMap<String, TransitionRef>,
+) {
+    let mut copy = PetriNet::new();
+    let mut place_refs = HashMap::new();
+    for (place_ref, place) in net.places_iter() {
+        let copy_ref = copy.add_place(place_ref.label());
+        if place.marking() > 0 {
+            copy.add_token(&copy_ref, place.marking())
+                .expect("BUG: Adding the initial tokens to an empty place should not fail");
+        }
+        place_refs.insert(place_ref.label().clone(), copy_ref);
+    }
+    let mut transition_refs = HashMap::new();
+    for (transition_ref, transition) in net.transitions_iter() {
+        let copy_ref = copy.add_transition(transition_ref.label());
+        for place_ref in transition.get_preset() {
+            add_arc_place_transition(&mut copy, &place_refs[place_ref.label()], &copy_ref);
+        }
+        for place_ref in transition.get_postset() {
+            add_arc_transition_place(&mut copy, &copy_ref, &place_refs[place_ref.label()]);
+        }
+        transition_refs.insert(transition_ref.label().clone(), copy_ref);
+    }
+    (copy, place_refs, transition_refs)
+}
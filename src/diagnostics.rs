@@ -0,0 +1,114 @@
+//! Reporting of the findings as `rustc` diagnostics.
+//!
+//! The findings are emitted through the diagnostic context of the compiler (`DiagCtxt`),
+//! so they are rendered like any other compiler warning, inline in editors and in the `cargo` output.
+//! This is only possible while the compiler session is alive.
+//! The `Reporter` is therefore handed to the caller of `run_and_check` together with the translation.
+//!
+//! A deadlock is reported with a primary span at every blocking call
+//! and labelled secondary spans at the lock acquisitions and the thread spawns involved.
+
+use std::collections::BTreeSet;
+
+use crate::source_span::SourceSpan;
+use crate::trace::{Resource, SyncAction, Trace};
+
+/// Emits the findings as diagnostics of the compiler session.
+pub struct Reporter<'tcx> {
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+}
+
+impl<'tcx> Reporter<'tcx> {
+    /// Creates a new `Reporter` for the given compiler session.
+    pub(crate) const fn new(tcx: rustc_middle::ty::TyCtxt<'tcx>) -> Self {
+        Self { tcx }
+    }
+
+    /// Reports a deadlock as a warning.
+    ///
+    /// - The primary spans point to the calls where the threads block.
+    /// - The secondary spans point to the acquisitions of the mutexes that are held in the blocked state
+    ///   or waited for, and to the spawns of the threads involved.
+    pub fn report_deadlock(&self, trace: &Trace) {
+        let mut primary_labels = Vec::new();
+        let mut mutexes = BTreeSet::new();
+        let mut threads = BTreeSet::new();
+        for blocked_thread in &trace.blocked {
+            mutexes.extend(blocked_thread.holds.iter().copied());
+            threads.extend(blocked_thread.thread.0);
+            for (resource, _, span) in &blocked_thread.waits_for {
+                match resource {
+                    Resource::Mutex(index) => {
+                        mutexes.insert(*index);
+                    }
+                    Resource::Thread(index) => {
+                        threads.insert(*index);
+                    }
+                    Resource::Condition(_) | Resource::Condvar(_) => {}
+                }
+                primary_labels.push((
+                    span.as_ref(),
+                    format!(
+                        "{} blocks here waiting for {resource}",
+                        blocked_thread.thread
+                    ),
+                ));
+            }
+        }
+
+        let mut secondary_labels = Vec::new();
+        for index in &mutexes {
+            if let Some(step) = last_lock(trace, *index) {
+                secondary_labels.push((
+                    step.span.as_ref(),
+                    format!("mutex {index} is locked here by {}", step.thread),
+                ));
+            }
+        }
+        for step in &trace.steps {
+            for action in &step.actions {
+                if let SyncAction::Spawn(index) = action
+                    && threads.contains(index)
+                {
+                    secondary_labels.push((
+                        step.span.as_ref(),
+                        format!("thread {index} is spawned here"),
+                    ));
+                }
+            }
+        }
+
+        let primary_spans: Vec<rustc_span::Span> = primary_labels
+            .iter()
+            .filter_map(|(span, _)| self.to_span(*span))
+            .collect();
+        let mut diag = self
+            .tcx
+            .dcx()
+            .struct_span_warn(primary_spans, "deadlock can be reached");
+        for (span, label) in primary_labels.into_iter().chain(secondary_labels) {
+            if let Some(span) = self.to_span(span) {
+                diag.span_label(span, label);
+            }
+        }
+        diag.emit();
+    }
+
+    /// Converts a location back to a span of the compiler session, if possible.
+    fn to_span(&self, span: Option<&SourceSpan>) -> Option<rustc_span::Span> {
+        span?.to_span(self.tcx)
+    }
+}
+
+/// Returns the step where the mutex was locked for the last time,
+/// provided that it was not unlocked afterwards.
+fn last_lock(trace: &Trace, index: usize) -> Option<&crate::trace::TraceStep> {
+    let step = trace.steps.iter().rev().find(|step| {
+        step.actions.iter().any(
+            |action| matches!(action, SyncAction::Lock(i) | SyncAction::Unlock(i) if *i == index),
+        )
+    })?;
+    step.actions
+        .contains(&SyncAction::Lock(index))
+        .then_some(step)
+}
@@ -0,0 +1,117 @@
+//! Errors that can occur while translating the source code to a Petri net.
+//!
+//! Unsupported MIR constructs do not abort the process.
+//! Instead the translator stops and returns a `TranslationError`
+//! that records what went wrong, in which function and where in the source code.
+//!
+//! Violations of internal invariants of the translator are still reported as panics
+//! with a message starting with "BUG:", since they indicate a programming error.
+
+use crate::source_span::SourceSpan;
+
+/// The kind of error that stopped the translation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranslationErrorKind {
+    /// The arguments passed to `rustc` do not request a compilation, e.g. `--help` or `--version`.
+    InvalidRustcArguments,
+    /// `rustc` reported errors while compiling the source code.
+    CompilationFailed,
+    /// The source code does not contain a `main` function.
+    NoEntryPoint,
+    /// A terminator of a basic block that the translator does not support, e.g. `InlineAsm`.
+    UnsupportedTerminator(String),
+    /// The called function could not be resolved to a function definition, e.g. a function pointer.
+    UnresolvedCall(String),
+    /// A place was linked to a synchronization variable that conflicts with the value already linked to it.
+    MemoryLinkConflict(String),
+    /// A synchronization variable was used but it is not tracked in the memory of the function,
+    /// e.g. a mutex stored inside a `Vec`.
+    UntrackedSyncVariable(String),
+    /// A use of a supported synchronization primitive that the translator does not model yet.
+    UnsupportedFeature(String),
+}
+
+impl std::fmt::Display for TranslationErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidRustcArguments => {
+                write!(
+                    f,
+                    "the arguments passed to `rustc` do not request a compilation"
+                )
+            }
+            Self::CompilationFailed => write!(f, "`rustc` failed to compile the source code"),
+            Self::NoEntryPoint => write!(f, "no main function found in the source code"),
+            Self::UnsupportedTerminator(terminator) => {
+                write!(f, "unsupported terminator `{terminator}`")
+            }
+            Self::UnresolvedCall(description) => {
+                write!(f, "unresolved function call: {description}")
+            }
+            Self::MemoryLinkConflict(description) => {
+                write!(f, "conflicting sync variable in memory: {description}")
+            }
+            Self::UntrackedSyncVariable(description) => {
+                write!(f, "untracked sync variable: {description}")
+            }
+            Self::UnsupportedFeature(description) => {
+                write!(f, "unsupported feature: {description}")
+            }
+        }
+    }
+}
+
+/// An error that stopped the translation of the source code.
+///
+/// It contains the kind of error, the function being translated and the source span where it occurred.
+/// The function and the span are not available for errors that occur outside of a function body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslationError {
+    /// What went wrong.
+    pub kind: TranslationErrorKind,
+    /// The name of the function being translated when the error occurred.
+    pub function: Option<String>,
+    /// The location in the source code that caused the error.
+    pub span: Option<SourceSpan>,
+}
+
+impl TranslationError {
+    /// Creates a new error that is not associated to any function or source span.
+    #[must_use]
+    pub const fn new(kind: TranslationErrorKind) -> Self {
+        Self {
+            kind,
+            function: None,
+            span: None,
+        }
+    }
+
+    /// Creates a new error located in the given function at the given source span.
+    #[must_use]
+    pub const fn located(
+        kind: TranslationErrorKind,
+        function: String,
+        span: Option<SourceSpan>,
+    ) -> Self {
+        Self {
+            kind,
+            function: Some(function),
+            span,
+        }
+    }
+}
+
+impl std::fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(function) = &self.function {
+            write!(f, " in function `{function}`")?;
+        }
+        if let Some(span) = &self.span {
+            write!(f, " at {span}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TranslationError {}
@@ -0,0 +1,932 @@
+//! User-supplied temporal properties of the Petri net.
+//!
+//! A formula is written in the syntax of `LoLA` for CTL and LTL, e.g. `EF (MUTEX_0 = 0)` or `A G F MUTEX_0 = 1`.
+//! Besides comparisons of the tokens in places of the net, `FIREABLE(t)`, `DEADLOCK`, `TRUE` and `FALSE`,
+//! the atomic propositions may refer to the program by symbolic names:
+//! - `locked(M)`: the mutex `M` is locked.
+//! - `holds(T, M)`: the thread `T` holds the mutex `M`.
+//! - `at(L)` or `at(T, L)`: some thread, or the thread `T`, is about to execute the code at the location `L`.
+//!
+//! A mutex is named by the variable it is assigned to where it is created, e.g. `data`, or as `mutex 0`.
+//! A thread is named `main`, by the variable of its join handle, e.g. `handle`, or as `thread 1`.
+//! A location is a line number, optionally preceded by the file, e.g. `12` or `src/main.rs:12`.
+//!
+//! The names are resolved to places of the net using the mapping of the net back to the source code.
+//! The variable names are read from the `let` statement at the location where the mutex or the thread is created.
+//! The net does not record which thread holds a mutex, so `holds` is checked on an instrumented copy of the net
+//! with one place per mutex and thread, marked while the thread holds the mutex, see `instrument_ownership`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, PlaceRef, add_arc_place_transition, add_arc_transition_place, copy_net,
+};
+use crate::naming::condvar::transition_labels;
+use crate::naming::mutex::{
+    held_by_label, not_held_label, place_label, release_copy_label, release_copy_original,
+};
+use crate::naming::thread::started_thread;
+use crate::net_map::NetMap;
+use crate::trace::{SourceFiles, ThreadId, control_place_thread};
+
+/// The symbolic atomic propositions and their number of arguments.
+const SYMBOLS: [(&str, &[usize]); 3] = [("locked", &[1]), ("holds", &[2]), ("at", &[1, 2])];
+
+/// A comparison of the number of tokens in places with a constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// The symbols of the comparisons, the longer ones first to parse them greedily.
+    const SYMBOLS: [(&'static str, Self); 6] = [
+        ("<=", Self::LessOrEqual),
+        (">=", Self::GreaterOrEqual),
+        ("!=", Self::NotEqual),
+        ("=", Self::Equal),
+        ("<", Self::Less),
+        (">", Self::Greater),
+    ];
+
+    /// Compares the number of tokens with the constant.
+    #[must_use]
+    pub const fn holds(self, tokens: u64, value: u64) -> bool {
+        match self {
+            Self::Equal => tokens == value,
+            Self::NotEqual => tokens != value,
+            Self::Less => tokens < value,
+            Self::LessOrEqual => tokens <= value,
+            Self::Greater => tokens > value,
+            Self::GreaterOrEqual => tokens >= value,
+        }
+    }
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = Self::SYMBOLS
+            .iter()
+            .find(|(_, comparison)| comparison == self)
+            .map_or("=", |(symbol, _)| symbol);
+        write!(f, "{symbol}")
+    }
+}
+
+/// A temporal operator of CTL or LTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemporalOperator {
+    /// `EF`: the operand holds in some reachable state.
+    ExistsFinally,
+    /// `AG`: the operand holds in every reachable state.
+    AllGlobally,
+    /// `AF`: on every path, the operand eventually holds.
+    AllFinally,
+    /// `EG`: on some path, the operand always holds.
+    ExistsGlobally,
+    /// `EX`: the operand holds in some successor.
+    ExistsNext,
+    /// `AX`: the operand holds in every successor.
+    AllNext,
+    /// `A`: the path formula holds on every path.
+    All,
+    /// `E`: the path formula holds on some path.
+    Exists,
+    /// `F`: the operand eventually holds on the path.
+    Finally,
+    /// `G`: the operand always holds on the path.
+    Globally,
+    /// `X`: the operand holds in the next state of the path.
+    Next,
+}
+
+impl TemporalOperator {
+    /// The keywords of the operators in the syntax of `LoLA`.
+    const KEYWORDS: [(&'static str, Self); 11] = [
+        ("EF", Self::ExistsFinally),
+        ("AG", Self::AllGlobally),
+        ("AF", Self::AllFinally),
+        ("EG", Self::ExistsGlobally),
+        ("EX", Self::ExistsNext),
+        ("AX", Self::AllNext),
+        ("A", Self::All),
+        ("E", Self::Exists),
+        ("F", Self::Finally),
+        ("G", Self::Globally),
+        ("X", Self::Next),
+    ];
+}
+
+impl std::fmt::Display for TemporalOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = Self::KEYWORDS
+            .iter()
+            .find(|(_, operator)| operator == self)
+            .map_or("", |(keyword, _)| keyword);
+        write!(f, "{keyword}")
+    }
+}
+
+/// A formula of CTL or LTL over the places and transitions of the net.
+/// It is displayed in the syntax of `LoLA`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    /// `TRUE` or `FALSE`.
+    Constant(bool),
+    /// No transition is enabled.
+    Deadlock,
+    /// The transition with the given label is enabled.
+    Fireable(String),
+    /// The sum of the tokens in the places compared with a constant.
+    Tokens {
+        places: Vec<String>,
+        comparison: Comparison,
+        value: u64,
+    },
+    /// A symbolic atomic proposition that is not resolved yet, e.g. `locked(data)`.
+    Symbol {
+        name: String,
+        arguments: Vec<String>,
+    },
+    Not(Box<Self>),
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Implies(Box<Self>, Box<Self>),
+    /// The left operand holds until the right operand holds.
+    Until(Box<Self>, Box<Self>),
+    Temporal(TemporalOperator, Box<Self>),
+}
+
+impl Expression {
+    /// Checks whether the expression contains temporal operators.
+    #[must_use]
+    pub fn is_temporal(&self) -> bool {
+        match self {
+            Self::Constant(_)
+            | Self::Deadlock
+            | Self::Fireable(_)
+            | Self::Tokens { .. }
+            | Self::Symbol { .. } => false,
+            Self::Not(operand) => operand.is_temporal(),
+            Self::And(left, right) | Self::Or(left, right) | Self::Implies(left, right) => {
+                left.is_temporal() || right.is_temporal()
+            }
+            Self::Until(_, _) | Self::Temporal(_, _) => true,
+        }
+    }
+
+    /// Replaces `FIREABLE(t)` by the disjunction of `FIREABLE` for `t` and for its copies, if it has any.
+    fn with_copies(self, copies: &HashMap<String, Vec<String>>) -> Self {
+        let with_copies = |operand: Box<Self>| Box::new(operand.with_copies(copies));
+        match self {
+            Self::Fireable(transition) => copies.get(&transition).into_iter().flatten().fold(
+                Self::Fireable(transition.clone()),
+                |disjunction, copy| {
+                    Self::Or(
+                        Box::new(disjunction),
+                        Box::new(Self::Fireable(copy.clone())),
+                    )
+                },
+            ),
+            Self::Constant(_) | Self::Deadlock | Self::Tokens { .. } | Self::Symbol { .. } => self,
+            Self::Not(operand) => Self::Not(with_copies(operand)),
+            Self::And(left, right) => Self::And(with_copies(left), with_copies(right)),
+            Self::Or(left, right) => Self::Or(with_copies(left), with_copies(right)),
+            Self::Implies(left, right) => Self::Implies(with_copies(left), with_copies(right)),
+            Self::Until(left, right) => Self::Until(with_copies(left), with_copies(right)),
+            Self::Temporal(operator, operand) => Self::Temporal(operator, with_copies(operand)),
+        }
+    }
+
+    /// Replaces the symbolic atomic propositions by comparisons of tokens in places.
+    fn resolve(&self, names: &mut Names) -> Result<Self, String> {
+        let resolve = |operand: &Self, names: &mut Names| operand.resolve(names).map(Box::new);
+        Ok(match self {
+            Self::Constant(_) | Self::Deadlock => self.clone(),
+            Self::Fireable(transition) => {
+                if !names.transitions.contains(transition) {
+                    return Err(format!(
+                        "the transition `{transition}` does not exist in the net"
+                    ));
+                }
+                self.clone()
+            }
+            Self::Tokens { places, .. } => {
+                if let Some(place) = places.iter().find(|place| !names.places.contains(*place)) {
+                    return Err(format!("the place `{place}` does not exist in the net"));
+                }
+                self.clone()
+            }
+            Self::Symbol { name, arguments } => names.resolve_symbol(name, arguments)?,
+            Self::Not(operand) => Self::Not(resolve(operand, names)?),
+            Self::And(left, right) => Self::And(resolve(left, names)?, resolve(right, names)?),
+            Self::Or(left, right) => Self::Or(resolve(left, names)?, resolve(right, names)?),
+            Self::Implies(left, right) => {
+                Self::Implies(resolve(left, names)?, resolve(right, names)?)
+            }
+            Self::Until(left, right) => Self::Until(resolve(left, names)?, resolve(right, names)?),
+            Self::Temporal(operator, operand) => {
+                Self::Temporal(*operator, resolve(operand, names)?)
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant(true) => write!(f, "TRUE"),
+            Self::Constant(false) => write!(f, "FALSE"),
+            Self::Deadlock => write!(f, "DEADLOCK"),
+            Self::Fireable(transition) => write!(f, "FIREABLE({transition})"),
+            Self::Tokens {
+                places,
+                comparison,
+                value,
+            } => write!(f, "{} {comparison} {value}", places.join(" + ")),
+            Self::Symbol { name, arguments } => write!(f, "{name}({})", arguments.join(", ")),
+            Self::Not(operand) => write!(f, "NOT ({operand})"),
+            Self::And(left, right) => write!(f, "({left} AND {right})"),
+            Self::Or(left, right) => write!(f, "({left} OR {right})"),
+            Self::Implies(left, right) => write!(f, "({left} -> {right})"),
+            Self::Until(left, right) => write!(f, "({left} U {right})"),
+            Self::Temporal(operator, operand) => write!(f, "{operator} ({operand})"),
+        }
+    }
+}
+
+/// A formula supplied by the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formula {
+    /// The formula as written by the user.
+    pub text: String,
+    /// The parsed formula, with the symbolic names replaced by places once it is resolved.
+    pub expression: Expression,
+}
+
+impl Formula {
+    /// Parses a formula in the syntax described in the module documentation.
+    ///
+    /// # Errors
+    ///
+    /// If the formula is not well-formed, then an error describing the problem is returned.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser { text, position: 0 };
+        let expression = parser.implication()?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("the end of the formula"));
+        }
+        Ok(Self {
+            text: text.trim().to_string(),
+            expression,
+        })
+    }
+
+    /// Resolves the symbolic names of the formula to places of the net.
+    /// Returns the resolved formula and, if it refers to the threads that hold mutexes,
+    /// the copy of the net instrumented to record them, which must be checked instead.
+    /// In that case, `FIREABLE(t)` also holds when a copy of `t` added by the instrumentation is enabled,
+    /// and the witness path must be restored with `restore_witness_path`.
+    ///
+    /// # Errors
+    ///
+    /// If a name does not refer to exactly one mutex, thread or location, then an error describing the problem is returned.
+    pub fn resolve(
+        &self,
+        net: &PetriNet,
+        net_map: &NetMap,
+    ) -> Result<(Self, Option<PetriNet>), String> {
+        let mut names = Names::new(net, net_map);
+        let expression = self.expression.resolve(&mut names)?;
+        let formula = Self {
+            text: self.text.clone(),
+            expression,
+        };
+        if names.ownership.is_empty() {
+            return Ok((formula, None));
+        }
+        let (net, copies) = instrument_ownership(net, net_map, &names.ownership);
+        let formula = Self {
+            text: formula.text,
+            expression: formula.expression.with_copies(&copies),
+        };
+        Ok((formula, Some(net)))
+    }
+
+    /// Returns the state formula and whether it must hold in some reachable state (`EF`)
+    /// or in every reachable state (`AG`), if the formula has one of these two forms.
+    #[must_use]
+    pub fn as_reachability(&self) -> Option<(bool, &Expression)> {
+        let (exists, operand) = match &self.expression {
+            Expression::Temporal(TemporalOperator::ExistsFinally, operand) => (true, operand),
+            Expression::Temporal(TemporalOperator::AllGlobally, operand) => (false, operand),
+            Expression::Temporal(TemporalOperator::Exists, path) => match path.as_ref() {
+                Expression::Temporal(TemporalOperator::Finally, operand) => (true, operand),
+                _ => return None,
+            },
+            Expression::Temporal(TemporalOperator::All, path) => match path.as_ref() {
+                Expression::Temporal(TemporalOperator::Globally, operand) => (false, operand),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        (!operand.is_temporal()).then_some((exists, operand.as_ref()))
+    }
+}
+
+/// A recursive-descent parser for formulas.
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    /// Returns an error describing what was expected at the current position.
+    fn error(&self, expected: &str) -> String {
+        format!(
+            "expected {expected} at position {} of the formula",
+            self.position + 1
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes the symbol if the text continues with it.
+    fn eat(&mut self, symbol: &str) -> bool {
+        self.skip_whitespace();
+        if self.text[self.position..].starts_with(symbol) {
+            self.position += symbol.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{symbol}`")))
+        }
+    }
+
+    /// Returns the word of letters, digits and underscores that starts at the current position, if any.
+    fn peek_word(&mut self) -> Option<&str> {
+        self.skip_whitespace();
+        let rest = &self.text[self.position..];
+        let length = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        (length > 0).then(|| &rest[..length])
+    }
+
+    fn word(&mut self) -> Option<String> {
+        let word = self.peek_word()?.to_string();
+        self.position += word.len();
+        Some(word)
+    }
+
+    /// Consumes the keyword if the next word is exactly the keyword.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_word() == Some(keyword) {
+            self.position += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `implication := disjunction ("->" implication)?`
+    fn implication(&mut self) -> Result<Expression, String> {
+        let left = self.disjunction()?;
+        if self.eat("->") {
+            let right = self.implication()?;
+            return Ok(Expression::Implies(Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    /// `disjunction := conjunction ("OR" conjunction)*`
+    fn disjunction(&mut self) -> Result<Expression, String> {
+        let mut left = self.conjunction()?;
+        while self.eat_keyword("OR") {
+            left = Expression::Or(Box::new(left), Box::new(self.conjunction()?));
+        }
+        Ok(left)
+    }
+
+    /// `conjunction := unary ("AND" unary)*`
+    fn conjunction(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        while self.eat_keyword("AND") {
+            left = Expression::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    /// `unary := "NOT" unary | temporal_operator unary | "(" implication ("U" implication)? ")" | atom`
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expression::Not(Box::new(self.unary()?)));
+        }
+        let word = self.peek_word().map(ToString::to_string);
+        if let Some((keyword, operator)) = TemporalOperator::KEYWORDS
+            .iter()
+            .find(|(keyword, _)| word.as_deref() == Some(*keyword))
+        {
+            self.position += keyword.len();
+            return Ok(Expression::Temporal(*operator, Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let mut expression = self.implication()?;
+            if self.eat_keyword("U") {
+                let right = self.implication()?;
+                expression = Expression::Until(Box::new(expression), Box::new(right));
+            }
+            self.expect(")")?;
+            return Ok(expression);
+        }
+        self.atom()
+    }
+
+    /// `atom := "TRUE" | "FALSE" | "DEADLOCK" | "FIREABLE" "(" label ")" | symbol "(" arguments ")"
+    ///        | label ("+" label)* comparison number`
+    fn atom(&mut self) -> Result<Expression, String> {
+        let start = self.position;
+        let word = self.word().ok_or_else(|| self.error("a formula"))?;
+        match word.as_str() {
+            "TRUE" => return Ok(Expression::Constant(true)),
+            "FALSE" => return Ok(Expression::Constant(false)),
+            "DEADLOCK" => return Ok(Expression::Deadlock),
+            "FIREABLE" => {
+                self.expect("(")?;
+                let transition = self.word().ok_or_else(|| self.error("a transition"))?;
+                self.expect(")")?;
+                return Ok(Expression::Fireable(transition));
+            }
+            _ => {}
+        }
+        if let Some((name, counts)) = SYMBOLS.iter().find(|(name, _)| *name == word) {
+            self.expect("(")?;
+            let rest = &self.text[self.position..];
+            let length = rest.find(')').ok_or_else(|| self.error("`)`"))?;
+            let arguments: Vec<String> = rest[..length]
+                .split(',')
+                .map(|argument| argument.trim().to_string())
+                .collect();
+            if !counts.contains(&arguments.len()) || arguments.iter().any(String::is_empty) {
+                self.position = start;
+                return Err(self.error(&format!(
+                    "{} argument(s) for `{name}`",
+                    counts
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                )));
+            }
+            self.position += length + 1;
+            return Ok(Expression::Symbol {
+                name: (*name).to_string(),
+                arguments,
+            });
+        }
+
+        let mut places = vec![word];
+        while self.eat("+") {
+            places.push(self.word().ok_or_else(|| self.error("a place"))?);
+        }
+        self.skip_whitespace();
+        let (symbol, comparison) = Comparison::SYMBOLS
+            .iter()
+            .find(|(symbol, _)| self.text[self.position..].starts_with(symbol))
+            .ok_or_else(|| self.error("a comparison"))?;
+        self.position += symbol.len();
+        let value = self
+            .word()
+            .and_then(|word| word.parse().ok())
+            .ok_or_else(|| self.error("a number"))?;
+        Ok(Expression::Tokens {
+            places,
+            comparison: *comparison,
+            value,
+        })
+    }
+}
+
+/// The names of the mutexes, threads and locations of the program, to resolve symbolic atomic propositions.
+struct Names<'a> {
+    net_map: &'a NetMap,
+    /// The labels of the places of the net.
+    places: BTreeSet<String>,
+    /// The labels of the transitions of the net.
+    transitions: BTreeSet<String>,
+    /// The index of every mutex and the variable it is assigned to, if known.
+    mutexes: Vec<(usize, Option<String>)>,
+    /// The index of every spawned thread and the variable of its join handle, if known.
+    threads: Vec<(usize, Option<String>)>,
+    /// For every transition, the labels of the places in its preset.
+    presets: Vec<(String, Vec<String>)>,
+    /// The mutexes and threads that appear in `holds`.
+    ownership: BTreeSet<(usize, ThreadId)>,
+}
+
+impl<'a> Names<'a> {
+    fn new(net: &PetriNet, net_map: &'a NetMap) -> Self {
+        let mut source_files = SourceFiles::default();
+        let mut variable = |label: &str| {
+            let span = net_map.place_origin(label)?.span.as_ref()?;
+            binding_name(&source_files.line(span)?)
+        };
+        let places: BTreeSet<String> = net
+            .places_iter()
+            .map(|(place_ref, _)| place_ref.label().clone())
+            .collect();
+        let mut mutexes = Vec::new();
+        let mut threads = Vec::new();
+        for label in &places {
+            if let Some(index) = label
+                .strip_prefix("MUTEX_")
+                .and_then(|index| index.parse().ok())
+            {
+                mutexes.push((index, variable(label)));
+            }
+            if let Some(index) = started_thread(label) {
+                threads.push((index, variable(label)));
+            }
+        }
+        let presets = net
+            .transitions_iter()
+            .map(|(transition_ref, transition)| {
+                let preset = transition
+                    .get_preset()
+                    .iter()
+                    .map(|place_ref| place_ref.label().clone())
+                    .collect();
+                (transition_ref.label().clone(), preset)
+            })
+            .collect();
+        Self {
+            net_map,
+            places,
+            transitions: net
+                .transitions_iter()
+                .map(|(transition_ref, _)| transition_ref.label().clone())
+                .collect(),
+            mutexes,
+            threads,
+            presets,
+            ownership: BTreeSet::new(),
+        }
+    }
+
+    /// Resolves a symbolic atomic proposition to a comparison of tokens in places.
+    fn resolve_symbol(&mut self, name: &str, arguments: &[String]) -> Result<Expression, String> {
+        let tokens = |places: Vec<String>, comparison: Comparison, value: u64| Expression::Tokens {
+            places,
+            comparison,
+            value,
+        };
+        match (name, arguments) {
+            ("locked", [mutex]) => {
+                let mutex = self.mutex(mutex)?;
+                Ok(tokens(vec![place_label(mutex)], Comparison::Equal, 0))
+            }
+            ("holds", [thread, mutex]) => {
+                let thread = self.thread(thread)?;
+                let mutex = self.mutex(mutex)?;
+                self.ownership.insert((mutex, thread));
+                Ok(tokens(
+                    vec![held_by_label(mutex, thread.0)],
+                    Comparison::Greater,
+                    0,
+                ))
+            }
+            ("at", [location]) => Ok(tokens(
+                self.location(None, location)?,
+                Comparison::Greater,
+                0,
+            )),
+            ("at", [thread, location]) => {
+                let thread = self.thread(thread)?;
+                Ok(tokens(
+                    self.location(Some(thread), location)?,
+                    Comparison::Greater,
+                    0,
+                ))
+            }
+            _ => Err(format!("unknown atomic proposition `{name}`")),
+        }
+    }
+
+    /// Resolves the name of a mutex to its index.
+    fn mutex(&self, name: &str) -> Result<usize, String> {
+        resolve_name("mutex", name, &self.mutexes)
+    }
+
+    /// Resolves the name of a thread.
+    fn thread(&self, name: &str) -> Result<ThreadId, String> {
+        if name == "main" {
+            return Ok(ThreadId(None));
+        }
+        resolve_name("thread", name, &self.threads).map(|index| ThreadId(Some(index)))
+    }
+
+    /// Resolves a location to the places where a thread, or the given thread, is about to execute the code at it,
+    /// i.e. the control places in the preset of the transitions that originate from the location.
+    fn location(&self, thread: Option<ThreadId>, location: &str) -> Result<Vec<String>, String> {
+        let (file, line) = location
+            .rsplit_once(':')
+            .map_or((None, location), |(file, line)| (Some(file), line));
+        let line: usize = line
+            .trim()
+            .parse()
+            .map_err(|_| format!("the location `{location}` is not of the form `[file:]line`"))?;
+        let file = file.map(|file| file.trim().trim_start_matches("./"));
+        let mut places = BTreeSet::new();
+        for (transition, preset) in &self.presets {
+            let Some(span) = self
+                .net_map
+                .transition_origin(transition)
+                .and_then(|origin| origin.span.as_ref())
+            else {
+                continue;
+            };
+            if span.start_line != line || file.is_some_and(|file| !span.file.ends_with(file)) {
+                continue;
+            }
+            places.extend(preset.iter().filter(|place| {
+                control_place_thread(place, self.net_map)
+                    .is_some_and(|place_thread| thread.is_none_or(|thread| thread == place_thread))
+            }));
+        }
+        if places.is_empty() {
+            return Err(format!(
+                "no code of {} at the location `{location}` was found in the net",
+                thread.map_or_else(|| "any thread".to_string(), |thread| thread.to_string())
+            ));
+        }
+        Ok(places.into_iter().cloned().collect())
+    }
+}
+
+/// Resolves a name of the form `{kind} {index}` or the name of a variable to the index of the element.
+fn resolve_name(
+    kind: &str,
+    name: &str,
+    elements: &[(usize, Option<String>)],
+) -> Result<usize, String> {
+    if let Some(index) = name
+        .strip_prefix(kind)
+        .and_then(|index| index.trim().parse::<usize>().ok())
+    {
+        if elements.iter().any(|(element, _)| *element == index) {
+            return Ok(index);
+        }
+        return Err(format!("the {kind} {index} does not exist in the net"));
+    }
+    let matches: Vec<usize> = elements
+        .iter()
+        .filter(|(_, variable)| variable.as_deref() == Some(name))
+        .map(|(index, _)| *index)
+        .collect();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => {
+            let known: Vec<String> = elements
+                .iter()
+                .map(|(index, variable)| match variable {
+                    Some(variable) => format!("`{variable}` ({kind} {index})"),
+                    None => format!("{kind} {index}"),
+                })
+                .collect();
+            Err(format!(
+                "unknown {kind} `{name}`, the known ones are: {}",
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            ))
+        }
+        _ => Err(format!(
+            "the name `{name}` refers to more than one {kind}, use `{kind} <index>` instead"
+        )),
+    }
+}
+
+/// Returns the variable bound by a `let` statement at the start of the line, if any.
+fn binding_name(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("let ")?.trim_start();
+    let rest = rest.strip_prefix("mut ").unwrap_or(rest).trim_start();
+    let length = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    (length > 0).then(|| rest[..length].to_string())
+}
+
+/// Copies the net and adds a place for every mutex and thread in `ownership`
+/// that is marked while the thread holds the mutex, and a place `MUTEX_{i}_NOT_HELD` for every mutex
+/// that is marked while none of these threads holds it.
+/// Every transition of the thread that locks the mutex moves the token from the latter place to the former.
+///
+/// A guard may be dropped by another thread than the one that locked the mutex, e.g. after it was moved to it,
+/// so every transition that unlocks the mutex releases it, whichever thread holds it:
+/// the original transition fires while none of the threads holds the mutex
+/// and a copy of it fires for every thread while this thread holds the mutex.
+/// Exactly one of them is enabled whenever the original transition is enabled in the original net,
+/// so the instrumentation does not restrict the behavior of the net.
+/// Returns the instrumented net and the labels of the copies of every transition that has copies.
+pub(crate) fn instrument_ownership(
+    net: &PetriNet,
+    net_map: &NetMap,
+    ownership: &BTreeSet<(usize, ThreadId)>,
+) -> (PetriNet, HashMap<String, Vec<String>>) {
+    // The thread of a transition is the one of the control place in its preset.
+    let mut threads: HashMap<String, ThreadId> = net
+        .transitions_iter()
+        .filter_map(|(transition_ref, transition)| {
+            let thread = transition
+                .get_preset()
+                .iter()
+                .find_map(|place_ref| control_place_thread(place_ref.label(), net_map))?;
+            Some((transition_ref.label().clone(), thread))
+        })
+        .collect();
+    // The thread that receives a notification and locks the mutex again is the one that waits.
+    for index in crate::lost_signal::condvars(net) {
+        let (wait_start, _, notify_received) = transition_labels(index);
+        if let Some(thread) = threads.get(&wait_start).copied() {
+            threads.insert(notify_received, thread);
+        }
+    }
+
+    let mut holders: BTreeMap<usize, Vec<ThreadId>> = BTreeMap::new();
+    for (mutex, thread) in ownership {
+        holders.entry(*mutex).or_default().push(*thread);
+    }
+    let (mut copy, place_refs, transition_refs) = copy_net(net);
+    let mut copies: HashMap<String, Vec<String>> = HashMap::new();
+    for (mutex, threads_of_mutex) in holders {
+        let not_held = copy.add_place(&not_held_label(mutex));
+        copy.add_token(&not_held, 1)
+            .expect("BUG: Adding the initial token to an empty place should not fail");
+        let held: HashMap<ThreadId, PlaceRef> = threads_of_mutex
+            .iter()
+            .map(|thread| (*thread, copy.add_place(&held_by_label(mutex, thread.0))))
+            .collect();
+        let mutex_label = place_label(mutex);
+        for (transition_ref, transition) in net.transitions_iter() {
+            let label = transition_ref.label();
+            let locks = transition
+                .get_preset()
+                .iter()
+                .any(|place_ref| *place_ref.label() == mutex_label);
+            let unlocks = transition
+                .get_postset()
+                .iter()
+                .any(|place_ref| *place_ref.label() == mutex_label);
+            let copy_ref = &transition_refs[label];
+            if locks && !unlocks {
+                if let Some(held) = threads.get(label).and_then(|thread| held.get(thread)) {
+                    add_arc_place_transition(&mut copy, &not_held, copy_ref);
+                    add_arc_transition_place(&mut copy, copy_ref, held);
+                }
+            } else if unlocks && !locks {
+                add_arc_place_transition(&mut copy, &not_held, copy_ref);
+                add_arc_transition_place(&mut copy, copy_ref, &not_held);
+                for thread in &threads_of_mutex {
+                    let release_label = release_copy_label(label, mutex, thread.0);
+                    let release = copy.add_transition(&release_label);
+                    for place_ref in transition.get_preset() {
+                        add_arc_place_transition(
+                            &mut copy,
+                            &place_refs[place_ref.label()],
+                            &release,
+                        );
+                    }
+                    for place_ref in transition.get_postset() {
+                        add_arc_transition_place(
+                            &mut copy,
+                            &release,
+                            &place_refs[place_ref.label()],
+                        );
+                    }
+                    add_arc_place_transition(&mut copy, &held[thread], &release);
+                    add_arc_transition_place(&mut copy, &release, &not_held);
+                    copies.entry(label.clone()).or_default().push(release_label);
+                }
+            }
+        }
+    }
+    (copy, copies)
+}
+
+/// Replaces the copies of the transitions that unlock a mutex in a witness path of a net instrumented
+/// with `instrument_ownership` by the original transitions, so that the path can be replayed on the original net.
+#[must_use]
+pub fn restore_witness_path(witness_path: Vec<String>) -> Vec<String> {
+    witness_path
+        .into_iter()
+        .map(|label| match release_copy_original(&label) {
+            Some(original) => original.to_string(),
+            None => label,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod formula_tests {
+    use super::*;
+    use crate::model_checker::native::{check_deadlock, check_formula};
+    use crate::test_nets::net_with_guard_handed_over;
+
+    #[test]
+    fn formula_is_displayed_in_lola_syntax() {
+        let formula = Formula::parse("EF (MUTEX_0 + MUTEX_1 = 0 AND NOT FIREABLE(t) -> DEADLOCK)")
+            .expect("The formula should be valid");
+        assert_eq!(
+            formula.expression.to_string(),
+            "EF (((MUTEX_0 + MUTEX_1 = 0 AND NOT (FIREABLE(t))) -> DEADLOCK))"
+        );
+        assert!(formula.as_reachability().is_some());
+    }
+
+    #[test]
+    fn symbolic_names_and_temporal_operators_are_parsed() {
+        let formula = Formula::parse("A G (holds(thread 2, data) -> F NOT locked(data))")
+            .expect("The formula should be valid");
+        assert_eq!(
+            formula.expression.to_string(),
+            "A (G ((holds(thread 2, data) -> F (NOT (locked(data))))))"
+        );
+        assert!(formula.as_reachability().is_none());
+
+        let formula = Formula::parse("E (at(main, src/main.rs:12) U locked(mutex 0))")
+            .expect("The formula should be valid");
+        assert_eq!(
+            formula.expression.to_string(),
+            "E ((at(main, src/main.rs:12) U locked(mutex 0)))"
+        );
+    }
+
+    #[test]
+    fn malformed_formulas_are_rejected() {
+        assert_eq!(
+            Formula::parse("EF (MUTEX_0 = )"),
+            Err("expected a number at position 15 of the formula".to_string())
+        );
+        assert_eq!(
+            Formula::parse("EF holds(data)"),
+            Err("expected 2 argument(s) for `holds` at position 4 of the formula".to_string())
+        );
+        assert!(Formula::parse("EF (MUTEX_0 = 0").is_err());
+        assert!(Formula::parse("EF MUTEX_0 = 0 extra").is_err());
+    }
+
+    #[test]
+    fn variable_is_read_from_the_let_statement() {
+        assert_eq!(
+            binding_name("let mut data = Mutex::new(0);"),
+            Some("data".to_string())
+        );
+        assert_eq!(binding_name("let (tx, rx) = channel();"), None);
+        assert_eq!(binding_name("Mutex::new(0);"), None);
+    }
+
+    #[test]
+    fn guard_dropped_by_another_thread_releases_the_mutex() {
+        let net = net_with_guard_handed_over();
+        let ownership = BTreeSet::from([(0, ThreadId(None)), (0, ThreadId(Some(1)))]);
+        let (instrumented, copies) = instrument_ownership(&net, &NetMap::new(), &ownership);
+
+        // The main thread can drop the guard of thread 1, so the instrumented net cannot block more than the original.
+        assert!(check_deadlock(&instrumented, false).witness_path.is_none());
+        assert_eq!(copies["UNLOCK"].len(), 2);
+
+        let formula = Formula::parse(&format!(
+            "EF (PROGRAM_END = 1 AND {} = 0 AND {} = 1)",
+            held_by_label(0, Some(1)),
+            not_held_label(0)
+        ))
+        .expect("The formula should be valid");
+        let (holds, exploration) =
+            check_formula(&instrumented, &formula).expect("The formula should be supported");
+        assert!(holds);
+        let witness_path = restore_witness_path(
+            exploration
+                .witness_path
+                .expect("The end of the program should be reachable"),
+        );
+        assert_eq!(witness_path, ["LOCK", "UNLOCK"]);
+    }
+}
@@ -0,0 +1,113 @@
+//! Module that prepares the Petri net to check for mutex guards still held when a thread or the program returns.
+//!
+//! A guard that is never dropped, e.g. because it was passed to `std::mem::forget`
+//! or stored in a value that outlives the thread, keeps the mutex locked forever.
+//! The program does not necessarily deadlock, but the next thread that locks the mutex blocks.
+//!
+//! The net does not record which thread holds a mutex, so the property is checked on an instrumented copy:
+//! - The place `MUTEX_{i}_HELD_BY_{thread}` is marked while the thread holds the mutex, as for `holds` in formulas.
+//!   A guard may be dropped by another thread, e.g. after it was moved to it, which releases the mutex as well.
+//! - The place `CONDVAR_{j}_WAITING` is marked while a thread waits on the condvar.
+//!   The net lets a notification wake up the waiting thread even if it does not wait,
+//!   which would lock the mutex again for a thread that already released it or ended.
+//!   In the copy, a thread only wakes up if it waits.
+//! - The place `{thread}_RETURNED` is marked when the thread returns, i.e. when its return statement
+//!   marks `PROGRAM_END` for the main thread or `THREAD_{k}_END` for a spawned thread.
+//!   A thread that ends with a `panic!` is not considered, since the net does not model the drop flags
+//!   that decide which guards are dropped while unwinding.
+//!
+//! The property `Property::HeldGuard` is then that the place `{thread}_RETURNED` is marked while the thread holds the mutex.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, TransitionRef, add_arc_place_transition, add_arc_transition_place,
+};
+use crate::formula::instrument_ownership;
+use crate::lost_signal::condvars;
+use crate::naming::condvar::{transition_labels, waiting_place_label};
+use crate::naming::function::is_return_transition_label;
+use crate::naming::thread::{returned_place_label, thread_end_place_label};
+use crate::net_map::NetMap;
+use crate::trace::{ThreadId, control_place_thread};
+
+/// Returns the pairs of a mutex and a thread that locks it, in increasing order.
+/// Only these pairs can end with a guard still held.
+#[must_use]
+pub fn lockers(net: &PetriNet, net_map: &NetMap) -> Vec<(usize, ThreadId)> {
+    let places: BTreeSet<String> = net
+        .places_iter()
+        .map(|(place_ref, _)| place_ref.label().clone())
+        .collect();
+    let mut lockers = BTreeSet::new();
+    for (_, transition) in net.transitions_iter() {
+        let Some(thread) = transition
+            .get_preset()
+            .iter()
+            .find_map(|place_ref| control_place_thread(place_ref.label(), net_map))
+        else {
+            continue;
+        };
+        for place_ref in transition.get_preset() {
+            if let Some(mutex) = place_ref
+                .label()
+                .strip_prefix("MUTEX_")
+                .and_then(|index| index.parse().ok())
+                && !transition.get_postset().contains(place_ref)
+            {
+                lockers.insert((mutex, thread));
+            }
+        }
+    }
+    lockers
+        .into_iter()
+        .filter(|(_, thread)| places.contains(&thread_end_place_label(thread.0)))
+        .collect()
+}
+
+/// Copies the net and adds the place that is marked while the given thread holds the mutex with the given index,
+/// the places that are marked while a thread waits on each condvar and the place marked when the thread returns.
+/// The transitions that unlock the mutex get a copy that fires while the thread holds it, see `instrument_ownership`,
+/// so the witness path must be restored with `formula::restore_witness_path` before replaying it on the original net.
+/// Unlike the other places, the places of the condvars restrict the net on purpose,
+/// to the notifications received by a thread that waits.
+#[must_use]
+pub fn instrument(net: &PetriNet, net_map: &NetMap, mutex: usize, thread: ThreadId) -> PetriNet {
+    let (mut copy, copies) = instrument_ownership(net, net_map, &BTreeSet::from([(mutex, thread)]));
+    let transition_refs: HashMap<String, TransitionRef> = copy
+        .transitions_iter()
+        .map(|(transition_ref, _)| (transition_ref.label().clone(), transition_ref.clone()))
+        .collect();
+    // The arcs added to a transition are added to its copies as well.
+    let with_copies = |label: &String| -> Vec<TransitionRef> {
+        std::iter::once(label)
+            .chain(copies.get(label).into_iter().flatten())
+            .map(|label| transition_refs[label].clone())
+            .collect()
+    };
+    for condvar in condvars(net) {
+        let (wait_start, _, notify_received) = transition_labels(condvar);
+        let waiting = copy.add_place(&waiting_place_label(condvar));
+        for transition_ref in with_copies(&wait_start) {
+            add_arc_transition_place(&mut copy, &transition_ref, &waiting);
+        }
+        for transition_ref in with_copies(&notify_received) {
+            add_arc_place_transition(&mut copy, &waiting, &transition_ref);
+        }
+    }
+    let end_label = thread_end_place_label(thread.0);
+    let returned = copy.add_place(&returned_place_label(thread.0));
+    for (transition_ref, transition) in net.transitions_iter() {
+        if is_return_transition_label(transition_ref.label())
+            && transition
+                .get_postset()
+                .iter()
+                .any(|place_ref| *place_ref.label() == end_label)
+        {
+            for transition_ref in with_copies(transition_ref.label()) {
+                add_arc_transition_place(&mut copy, &transition_ref, &returned);
+            }
+        }
+    }
+    copy
+}
@@ -0,0 +1,350 @@
+//! Helpers to write and read JSON by hand.
+//!
+//! The output files and reports only need a handful of JSON constructs,
+//! which does not justify an additional dependency for serialization.
+//! Every helper returns the JSON text of a single value.
+//!
+//! The JSON output of the model checkers is read with a small parser into a `Value`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use crate::source_span::SourceSpan;
+
+/// Formats a string as a JSON string literal, escaping the characters that require it.
+#[must_use]
+pub fn string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_control() => write!(result, "\\u{:04x}", u32::from(c))
+                .expect("BUG: Writing to a string should not fail"),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Formats an optional value as a JSON value, `null` if it is absent.
+/// The value must already be valid JSON when displayed, e.g. a number.
+#[must_use]
+pub fn optional<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "null".to_string(), |value| value.to_string())
+}
+
+/// Formats a sequence of JSON values as a JSON array in a single line.
+#[must_use]
+pub fn array(values: impl IntoIterator<Item = String>) -> String {
+    let values: Vec<String> = values.into_iter().collect();
+    format!("[{}]", values.join(", "))
+}
+
+/// Formats a span as a JSON object in a single line, `null` if it is absent.
+#[must_use]
+pub fn span(span: Option<&SourceSpan>) -> String {
+    span.map_or_else(
+        || "null".to_string(),
+        |span| {
+            format!(
+                "{{\"file\": {}, \"start_line\": {}, \"start_column\": {}, \"end_line\": {}, \"end_column\": {}}}",
+                string(&span.file),
+                span.start_line,
+                span.start_column,
+                span.end_line,
+                span.end_column
+            )
+        },
+    )
+}
+
+/// A JSON value parsed from a text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// The literal `null`.
+    Null,
+    /// The literals `true` and `false`.
+    Bool(bool),
+    /// A number, integers are exact up to 2^53.
+    Number(f64),
+    /// A string with the escape sequences resolved.
+    String(String),
+    /// An array of values.
+    Array(Vec<Self>),
+    /// An object, the members are sorted by key.
+    Object(BTreeMap<String, Self>),
+}
+
+impl Value {
+    /// Returns the value of the member with the given key, if this is an object that contains it.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(members) => members.get(key),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if this is a boolean.
+    #[must_use]
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the string, if this is a string.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as an unsigned integer, if this is a non-negative integer.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Self::Number(value) if *value >= 0.0 && value.fract() == 0.0 => Some(*value as usize),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a text that contains a single JSON value, surrounded by optional whitespace.
+///
+/// # Errors
+///
+/// If the text is not valid JSON, then an error describing the position of the problem is returned.
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        position: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < parser.chars.len() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+/// A recursive descent parser over the characters of a JSON text.
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("{message} at character {}", self.position)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(char::is_ascii_whitespace)
+        {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes the expected character after optional whitespace.
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.chars.get(self.position) == Some(&expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{expected}`")))
+        }
+    }
+
+    /// Consumes the expected keyword, e.g. `true`.
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, String> {
+        for expected in keyword.chars() {
+            if self.chars.get(self.position) != Some(&expected) {
+                return Err(self.error(&format!("expected `{keyword}`")));
+            }
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.chars.get(self.position) {
+            Some('n') => self.keyword("null", Value::Null),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('"') => self.string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("expected a JSON value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse()
+            .map(Value::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            let Some(c) = self.chars.get(self.position).copied() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.position += 1;
+            match c {
+                '"' => return Ok(result),
+                '\\' => {
+                    let Some(escaped) = self.chars.get(self.position).copied() else {
+                        return Err(self.error("unterminated escape sequence"));
+                    };
+                    self.position += 1;
+                    match escaped {
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'u' => {
+                            let digits: String = self
+                                .chars
+                                .get(self.position..self.position + 4)
+                                .ok_or_else(|| self.error("incomplete unicode escape"))?
+                                .iter()
+                                .collect();
+                            self.position += 4;
+                            let character = u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .unwrap_or(char::REPLACEMENT_CHARACTER);
+                            result.push(character);
+                        }
+                        other => result.push(other),
+                    }
+                }
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.chars.get(self.position) == Some(&']') {
+            self.position += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.get(self.position) {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut members = BTreeMap::new();
+        self.skip_whitespace();
+        if self.chars.get(self.position) == Some(&'}') {
+            self.position += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.insert(key, self.value()?);
+            self.skip_whitespace();
+            match self.chars.get(self.position) {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(Value::Object(members));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_output_of_lola() {
+        let value = parse(
+            r#"{"analysis": {"result": true, "stats": {"edges": 2, "states": 3}}, "call": {"error": null, "parameters": ["net.lola", "--json"]}}"#,
+        )
+        .expect("The JSON text should be valid");
+
+        let analysis = value.get("analysis").expect("The member should exist");
+        assert_eq!(analysis.get("result").and_then(Value::as_bool), Some(true));
+        assert_eq!(
+            analysis
+                .get("stats")
+                .and_then(|stats| stats.get("states"))
+                .and_then(Value::as_usize),
+            Some(3)
+        );
+        assert_eq!(
+            value.get("call").and_then(|call| call.get("error")),
+            Some(&Value::Null)
+        );
+    }
+
+    #[test]
+    fn parses_escaped_strings() {
+        let value = parse(r#""line\n\"quoted\" \u00e9""#).expect("The JSON text should be valid");
+
+        assert_eq!(value.as_str(), Some("line\n\"quoted\" \u{e9}"));
+    }
+
+    #[test]
+    fn reports_invalid_json() {
+        assert!(parse(r#"{"result": tru}"#).is_err());
+        assert!(parse("[1, 2").is_err());
+        assert!(parse("{} {}").is_err());
+    }
+
+    #[test]
+    fn written_strings_are_parsed_back() {
+        let text = "tab\t, backslash \\ and control \u{1}";
+
+        assert_eq!(parse(&string(text)), Ok(Value::String(text.to_string())));
+    }
+}
@@ -28,29 +28,105 @@ extern crate rustc_middle;
 extern crate rustc_session;
 extern crate rustc_span;
 
+pub mod baseline;
 mod data_structures;
+pub mod diagnostics;
+pub mod error;
+pub mod formula;
+pub mod held_guard;
+pub mod json;
+pub mod lock_order;
+pub mod lost_signal;
 pub mod model_checker;
 mod naming;
+pub mod net_map;
+pub mod panic;
+pub mod reduction;
+pub mod refinement;
+pub mod report;
+pub mod source_span;
+pub mod structural;
+#[cfg(test)]
+mod test_nets;
+pub mod trace;
 mod translator;
+pub mod unjoined_thread;
 mod utils;
 
 pub use data_structures::petri_net_interface::PetriNet;
+pub use diagnostics::Reporter;
+pub use error::{TranslationError, TranslationErrorKind};
+pub use net_map::NetMap;
+pub use report::AnalysisReport;
+pub use trace::Trace;
 
 use std::collections::HashMap;
 
+/// Options that control how the source code is compiled and translated.
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Additional command-line arguments passed through to `rustc`,
+    /// e.g. `--edition 2024`, `--cfg feature="x"` or `--extern foo=libfoo.rlib`.
+    /// They are parsed with the option parser of `rustc` itself.
+    pub rustc_args: Vec<String>,
+    /// If set, unsupported constructs (e.g. inline assembly or calls through function pointers)
+    /// are replaced by opaque transitions to every possible successor instead of stopping the translation.
+    /// Each replacement is recorded as an approximation in the result.
+    pub lenient: bool,
+    /// If set, the net is reduced with structural rules that preserve deadlocks after the translation.
+    /// The transitions connected to synchronization primitives are kept.
+    pub reduce: bool,
+}
+
+/// The result of a successful translation.
+pub struct Translation {
+    /// The Petri net model of the program.
+    pub net: PetriNet,
+    /// The unsupported constructs that were approximated in lenient mode.
+    /// Each one is described by the error that would have stopped the translation otherwise.
+    pub approximations: Vec<TranslationError>,
+    /// The origin of every place and transition of the net in the source code.
+    pub net_map: NetMap,
+    /// The mapping of the reduced net back to the original net, if the net was reduced.
+    pub reduction: Option<reduction::Reduction>,
+    /// The branches on boolean locals and the assignments to them, to refine spurious witnesses.
+    pub branches: refinement::BranchConditions,
+}
+
 /// The Config struct is documented here:
 /// <https://doc.rust-lang.org/stable/nightly-rustc/rustc_interface/interface/struct.Config.html>
 ///
 /// It includes command-line options as well as internal rustc options.
-/// The relevant parts in this case is only the input file.
+/// The input file is always the source code file given by the user.
+/// The remaining options (edition, cfgs, externs, etc.) are parsed from `rustc_args`
+/// in the same way as the `rustc` driver does it.
 ///
 /// See the rustc driver examples for other possible example configurations:
 /// <https://rustc-dev-guide.rust-lang.org/rustc-driver.html>
-fn prepare_rustc_config(source_code_filepath: std::path::PathBuf) -> rustc_interface::Config {
-    rustc_interface::Config {
-        opts: rustc_session::config::Options::default(),
-        crate_cfg: Vec::new(),
-        crate_check_cfg: Vec::new(),
+///
+/// Returns `None` if the arguments do not request a compilation, e.g. `--help` or `--version`.
+/// Invalid arguments are reported by `rustc` itself, which then exits the process.
+fn prepare_rustc_config(
+    source_code_filepath: std::path::PathBuf,
+    rustc_args: &[String],
+) -> Option<rustc_interface::Config> {
+    let mut early_dcx =
+        rustc_session::EarlyDiagCtxt::new(rustc_session::config::ErrorOutputType::default());
+    // The source code file is passed as the free argument, just like in a regular call to `rustc`.
+    let mut args = rustc_args.to_vec();
+    args.push(source_code_filepath.to_string_lossy().into_owned());
+
+    let rustc_driver::HandledOptions::Normal(matches) =
+        rustc_driver::handle_options(&early_dcx, &args)
+    else {
+        return None;
+    };
+    let opts = rustc_session::config::build_session_options(&mut early_dcx, &matches);
+
+    Some(rustc_interface::Config {
+        opts,
+        crate_cfg: matches.opt_strs("cfg"),
+        crate_check_cfg: matches.opt_strs("check-cfg"),
         input: rustc_session::config::Input::File(source_code_filepath),
         output_dir: None,
         output_file: None,
@@ -64,35 +140,115 @@ fn prepare_rustc_config(source_code_filepath: std::path::PathBuf) -> rustc_inter
         make_codegen_backend: None,
         using_internal_features: &rustc_driver::USING_INTERNAL_FEATURES,
         extra_symbols: Vec::new(),
-    }
+    })
 }
 
 /// Entry point for the translation of the Rust code to a Petri net.
 ///
-/// Adapted from the [example in the rustc repo](https://github.com/rust-lang/rustc-dev-guide/blob/master/examples/rustc-interface-example.rs)
+/// # Errors
+///
+/// If the `rustc` arguments in the options do not request a compilation, then an error is returned.
+/// If `rustc` fails to compile the source code, then an error is returned.
+/// If the translation encounters an unsupported construct and the lenient mode is not enabled,
+/// then an error describing it is returned.
+///
+/// # Panics
+///
+/// If the translation failed due to a bug, then the function panics.
+pub fn run(
+    source_code_filepath: std::path::PathBuf,
+    options: &Options,
+) -> Result<Translation, TranslationError> {
+    run_and_check(source_code_filepath, options, |_, _| ()).map(|(translation, ())| translation)
+}
+
+/// Entry point for the translation of the Rust code to a Petri net,
+/// followed by a check of the net while the compiler session is still alive.
+///
+/// The closure `check` receives the translation and a `Reporter` that emits
+/// the findings as `rustc` diagnostics with spans in the source code.
+/// Its result is returned together with the translation.
 ///
 /// # Errors
 ///
-/// If the `sysroot` cannot be found, then an error is returned.
-/// If the translation fails, then an error with the corresponding description is returned.
+/// If the `rustc` arguments in the options do not request a compilation, then an error is returned.
+/// If `rustc` fails to compile the source code, then an error is returned.
+/// If the translation encounters an unsupported construct and the lenient mode is not enabled,
+/// then an error describing it is returned.
 ///
 /// # Panics
 ///
 /// If the translation failed due to a bug, then the function panics.
-pub fn run(source_code_filepath: std::path::PathBuf) -> Result<PetriNet, &'static str> {
-    let config = prepare_rustc_config(source_code_filepath);
-    let mut translation_result: Result<PetriNet, &'static str> = Err("Translation did not run");
-
-    rustc_interface::run_compiler(config, |compiler| {
-        // Parse the program and print the syntax tree.
-        let krate = rustc_interface::passes::parse(&compiler.sess);
-        // Analyze the program and inspect the types of definitions.
-        rustc_interface::create_and_enter_global_ctxt(compiler, krate, |tcx| {
-            let mut translator = translator::Translator::new(tcx);
-            translator.run();
-            translation_result = Ok(translator.get_result());
+pub fn run_and_check<T: Send>(
+    source_code_filepath: std::path::PathBuf,
+    options: &Options,
+    check: impl FnOnce(&Translation, &Reporter) -> T + Send,
+) -> Result<(Translation, T), TranslationError> {
+    compile_and_analyze(source_code_filepath, options, |tcx| {
+        let mut translator = translator::Translator::new(tcx, options.lenient, options.reduce);
+        translator.run()?;
+        let translation = translator.get_result();
+        let result = check(&translation, &Reporter::new(tcx));
+        Ok((translation, result))
+    })
+}
+
+/// Entry point for the pre-flight support check.
+/// Walks the MIR reachable from the entry point without translating it
+/// and returns every construct that the translator does not support, with its location.
+///
+/// # Errors
+///
+/// If the `rustc` arguments in the options do not request a compilation, then an error is returned.
+/// If `rustc` fails to compile the source code, then an error is returned.
+/// If the source code does not contain a main function, then an error is returned.
+pub fn check_support(
+    source_code_filepath: std::path::PathBuf,
+    options: &Options,
+) -> Result<Vec<TranslationError>, TranslationError> {
+    compile_and_analyze(source_code_filepath, options, translator::check_support)
+}
+
+/// Compiles the source code up to the analysis passes and calls `analyze` with the global typing context.
+///
+/// Adapted from the [example in the rustc repo](https://github.com/rust-lang/rustc-dev-guide/blob/master/examples/rustc-interface-example.rs)
+///
+/// # Errors
+///
+/// If the `rustc` arguments in the options do not request a compilation, then an error is returned.
+/// If `rustc` fails to compile the source code, then an error is returned.
+/// Otherwise the result of `analyze` is returned.
+fn compile_and_analyze<T: Send>(
+    source_code_filepath: std::path::PathBuf,
+    options: &Options,
+    analyze: impl FnOnce(rustc_middle::ty::TyCtxt) -> Result<T, TranslationError> + Send,
+) -> Result<T, TranslationError> {
+    let Some(config) = prepare_rustc_config(source_code_filepath, &options.rustc_args) else {
+        return Err(TranslationError::new(
+            TranslationErrorKind::InvalidRustcArguments,
+        ));
+    };
+    // If the compilation aborts before the analysis runs, this is the error reported.
+    let mut result = Err(TranslationError::new(
+        TranslationErrorKind::CompilationFailed,
+    ));
+
+    // Compilation errors abort the compiler with a fatal error, catch it to report it as an error.
+    // The diagnostics were already emitted by `rustc` at this point.
+    let _ = rustc_driver::catch_fatal_errors(|| {
+        rustc_interface::run_compiler(config, |compiler| {
+            // Parse the program and print the syntax tree.
+            let krate = rustc_interface::passes::parse(&compiler.sess);
+            // Analyze the program and inspect the types of definitions.
+            rustc_interface::create_and_enter_global_ctxt(compiler, krate, |tcx| {
+                // Run the analysis passes (type checking, borrow checking, etc.)
+                // and stop if they report errors, the MIR of an invalid program must not be translated.
+                tcx.ensure_ok().analysis(());
+                tcx.dcx().abort_if_errors();
+                result = analyze(tcx);
+            });
         });
     });
 
-    translation_result
+    result
 }
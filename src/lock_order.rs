@@ -0,0 +1,266 @@
+//! Module that builds the lock-order graph of the program without exploring the state space.
+//!
+//! The control flow of every thread is followed in the Petri net on its own, ignoring the other threads,
+//! while recording the mutexes that the thread holds and the transition that locked each of them.
+//! Whenever a thread locks a mutex while it holds another, the graph gets an edge
+//! from the mutex held to the mutex locked, with the locations of both lock calls.
+//! A cycle whose edges come from different threads is a potential deadlock:
+//! every thread may hold the mutex that the previous one waits for.
+//!
+//! The pass is fast since the threads are not interleaved, but it over-approximates the program.
+//! The conditions of the branches are not modeled, and neither is the order in which the threads are spawned and joined,
+//! so a cycle may be spurious. The model checker decides whether the deadlock can be reached.
+//! A cycle is discarded if every thread in it holds the same mutex while locking, i.e. a gate lock
+//! that keeps the threads from interleaving their acquisitions.
+//! Only mutexes are part of the graph, a deadlock on a condvar or on a join is not found.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::data_structures::petri_net_interface::{PetriNet, PlaceRef};
+use crate::lost_signal::condvars;
+use crate::naming::PROGRAM_START;
+use crate::naming::condvar::transition_labels;
+use crate::naming::thread::started_thread;
+use crate::net_map::NetMap;
+use crate::report::{LockOrderCycle, LockOrderEdge};
+use crate::trace::{SyncAction, ThreadId, control_place_thread, sync_actions};
+
+/// The mutexes held by a thread, with the label of the transition that locked each of them.
+type Held = BTreeMap<usize, String>;
+
+/// An edge of the lock-order graph, with the labels of the transitions that lock the mutexes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge {
+    held: usize,
+    acquired: usize,
+    thread: ThreadId,
+    held_transition: String,
+    acquired_transition: String,
+    /// Every mutex that the thread holds when it locks `acquired`, including `held`.
+    guards: BTreeSet<usize>,
+}
+
+/// The preset and the postset of a transition.
+type Arcs<'net> = (&'net BTreeSet<PlaceRef>, &'net BTreeSet<PlaceRef>);
+
+/// Returns the cycles of the lock-order graph of the net whose edges come from different threads
+/// and do not share a gate lock, ordered by their smallest mutex.
+/// Every elementary cycle of mutexes is reported once, with the first edges found for it.
+#[must_use]
+pub fn cycles(net: &PetriNet, net_map: &NetMap) -> Vec<LockOrderCycle> {
+    let mut graph: BTreeMap<usize, BTreeMap<usize, Vec<Edge>>> = BTreeMap::new();
+    for edge in edges(net, net_map) {
+        graph
+            .entry(edge.held)
+            .or_default()
+            .entry(edge.acquired)
+            .or_default()
+            .push(edge);
+    }
+
+    let mut cycles = Vec::new();
+    for &start in graph.keys() {
+        let mut path = vec![start];
+        elementary_cycles(&graph, &mut path, &mut |mutexes| {
+            if let Some(edges) = choose_edges(&graph, mutexes) {
+                cycles.push(LockOrderCycle {
+                    edges: edges
+                        .into_iter()
+                        .map(|edge| lock_order_edge(edge, net_map))
+                        .collect(),
+                });
+            }
+        });
+    }
+    cycles
+}
+
+/// Follows the control flow of every thread on its own and returns the edges of the lock-order graph.
+fn edges(net: &PetriNet, net_map: &NetMap) -> BTreeSet<Edge> {
+    let transitions: HashMap<&String, Arcs> = net
+        .transitions_iter()
+        .map(|(transition_ref, transition)| {
+            (
+                transition_ref.label(),
+                (transition.get_preset(), transition.get_postset()),
+            )
+        })
+        .collect();
+    // The transitions that leave each control place.
+    let mut outgoing: HashMap<&String, Vec<&String>> = HashMap::new();
+    for (label, (preset, _)) in &transitions {
+        for place_ref in *preset {
+            if control_place_thread(place_ref.label(), net_map).is_some() {
+                outgoing.entry(place_ref.label()).or_default().push(label);
+            }
+        }
+    }
+    // The thread that waits on a condvar continues after the notification, which locks the mutex again.
+    let wake_ups: HashMap<String, String> = condvars(net)
+        .into_iter()
+        .map(|index| {
+            let (wait_start, _, notify_received) = transition_labels(index);
+            (wait_start, notify_received)
+        })
+        .collect();
+
+    let mut edges = BTreeSet::new();
+    for (place_ref, _) in net.places_iter() {
+        let label = place_ref.label();
+        let thread = if label == PROGRAM_START {
+            ThreadId(None)
+        } else if let Some(index) = started_thread(label) {
+            ThreadId(Some(index))
+        } else {
+            continue;
+        };
+
+        let mut visited: HashSet<(&String, Held)> = HashSet::new();
+        let mut stack = vec![(label, Held::new())];
+        while let Some((place, held)) = stack.pop() {
+            if !visited.insert((place, held.clone())) {
+                continue;
+            }
+            for transition in outgoing.get(place).into_iter().flatten() {
+                let mut label = *transition;
+                let mut held = fire(label, label, transitions[label], thread, &held, &mut edges);
+                if let Some(notify_received) = wake_ups.get(label)
+                    && let Some((notify_received, arcs)) =
+                        transitions.get_key_value(notify_received)
+                {
+                    // The mutex is locked again at the call to `wait`.
+                    held = fire(notify_received, label, *arcs, thread, &held, &mut edges);
+                    label = notify_received;
+                }
+                // Some places have no origin and are attributed to the main thread,
+                // so the thread continues in every control place except the start of the threads it spawns.
+                let (_, postset) = transitions[label];
+                for place_ref in postset {
+                    if control_place_thread(place_ref.label(), net_map).is_some()
+                        && started_thread(place_ref.label()).is_none()
+                    {
+                        stack.push((place_ref.label(), held.clone()));
+                    }
+                }
+            }
+        }
+    }
+    edges
+}
+
+/// Returns the mutexes held after the thread fires the transition,
+/// and adds an edge for every mutex held when the transition locks another.
+/// The lock is recorded at the transition `site`, whose origin is the lock call in the source code.
+fn fire(
+    label: &str,
+    site: &str,
+    (preset, postset): Arcs,
+    thread: ThreadId,
+    held: &Held,
+    edges: &mut BTreeSet<Edge>,
+) -> Held {
+    let actions = sync_actions(label, preset, postset);
+    let mut held = held.clone();
+    for action in &actions {
+        if let SyncAction::Unlock(index) = action {
+            held.remove(index);
+        }
+    }
+    for action in &actions {
+        let SyncAction::Lock(acquired) = *action else {
+            continue;
+        };
+        // Locking a mutex that the thread already holds is a deadlock of its own, not a matter of order.
+        if held.contains_key(&acquired) {
+            continue;
+        }
+        for (held_mutex, held_transition) in &held {
+            edges.insert(Edge {
+                held: *held_mutex,
+                acquired,
+                thread,
+                held_transition: held_transition.clone(),
+                acquired_transition: site.to_string(),
+                guards: held.keys().copied().collect(),
+            });
+        }
+        held.insert(acquired, site.to_string());
+    }
+    held
+}
+
+/// Calls `found` with every elementary cycle of the graph that starts with the first mutex of `path`,
+/// where the start is the smallest mutex, so that every cycle is found once.
+fn elementary_cycles(
+    graph: &BTreeMap<usize, BTreeMap<usize, Vec<Edge>>>,
+    path: &mut Vec<usize>,
+    found: &mut impl FnMut(&[usize]),
+) {
+    let start = path[0];
+    let last = path[path.len() - 1];
+    for &next in graph.get(&last).into_iter().flat_map(BTreeMap::keys) {
+        if next == start {
+            found(path);
+        } else if next > start && !path.contains(&next) {
+            path.push(next);
+            elementary_cycles(graph, path, found);
+            path.pop();
+        }
+    }
+}
+
+/// Chooses one edge for every pair of consecutive mutexes in the cycle, such that every edge comes from another thread
+/// and no mutex guards every edge. Returns `None` if there is no such choice, i.e. the cycle cannot deadlock.
+fn choose_edges<'graph>(
+    graph: &'graph BTreeMap<usize, BTreeMap<usize, Vec<Edge>>>,
+    mutexes: &[usize],
+) -> Option<Vec<&'graph Edge>> {
+    let candidates: Vec<&Vec<Edge>> = mutexes
+        .iter()
+        .zip(mutexes.iter().cycle().skip(1))
+        .map(|(held, acquired)| &graph[held][acquired])
+        .collect();
+    let mut chosen = Vec::new();
+    choose_next_edge(&candidates, &mut chosen).then_some(chosen)
+}
+
+/// Extends the edges chosen so far with an edge of the next pair, backtracking if no edge fits.
+fn choose_next_edge<'graph>(
+    candidates: &[&'graph Vec<Edge>],
+    chosen: &mut Vec<&'graph Edge>,
+) -> bool {
+    let Some(edges) = candidates.get(chosen.len()) else {
+        let mut guards = chosen[0].guards.clone();
+        for edge in &chosen[1..] {
+            guards.retain(|guard| edge.guards.contains(guard));
+        }
+        return guards.is_empty();
+    };
+    for edge in edges.iter() {
+        if chosen.iter().any(|other| other.thread == edge.thread) {
+            continue;
+        }
+        chosen.push(edge);
+        if choose_next_edge(candidates, chosen) {
+            return true;
+        }
+        chosen.pop();
+    }
+    false
+}
+
+/// Converts an edge of the graph to the one in the report, with the locations of the lock calls.
+fn lock_order_edge(edge: &Edge, net_map: &NetMap) -> LockOrderEdge {
+    let span = |label: &str| {
+        net_map
+            .transition_origin(label)
+            .and_then(|origin| origin.span.clone())
+    };
+    LockOrderEdge {
+        thread: edge.thread,
+        held: edge.held,
+        held_span: span(&edge.held_transition),
+        acquired: edge.acquired,
+        acquired_span: span(&edge.acquired_transition),
+    }
+}
@@ -0,0 +1,93 @@
+//! Module that prepares the Petri net to check for lost signals on condition variables.
+//!
+//! A call to `notify_one` or `notify_all` while no thread waits on the condvar
+//! fires the transition `CONDVAR_{i}_LOST_SIGNAL`, which discards the notification.
+//! This is only a bug if a thread later waits on the same condvar and is never notified again.
+//!
+//! The net does not record whether a signal was lost, so the property is checked on an instrumented copy:
+//! - The place `CONDVAR_{i}_SIGNAL_NOT_LOST` starts with one token, which the first firing
+//!   of the lost signal transition moves to the place `CONDVAR_{i}_SIGNAL_LOST`.
+//! - The later firings use a copy of the transition, `CONDVAR_{i}_LOST_SIGNAL_REPEATED`,
+//!   that only tests the place `CONDVAR_{i}_SIGNAL_LOST`.
+//!
+//! The copy keeps the net bounded and the behavior of the program unchanged.
+//! The property `Property::LostSignal` is then a deadlock where a signal was lost and a thread waits on the condvar.
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, add_arc_place_transition, add_arc_transition_place, copy_net,
+};
+use crate::naming::condvar::{
+    lost_signal_repeated_label, signal_lost_place_labels, transition_labels,
+};
+
+/// Returns the indices of the condvars in the net, in increasing order.
+#[must_use]
+pub fn condvars(net: &PetriNet) -> Vec<usize> {
+    let mut indices: Vec<usize> = net
+        .transitions_iter()
+        .filter_map(|(transition_ref, _)| {
+            transition_ref
+                .label()
+                .strip_prefix("CONDVAR_")?
+                .strip_suffix("_LOST_SIGNAL")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    indices.sort_unstable();
+    indices
+}
+
+/// Copies the net and adds the places that record whether a signal on the condvar with the given index was lost.
+///
+/// # Panics
+///
+/// If the condvar does not exist in the net, then the function panics.
+#[must_use]
+pub fn instrument(net: &PetriNet, index: usize) -> PetriNet {
+    let (_, lost_signal_label, _) = transition_labels(index);
+    let (_, original) = net
+        .transitions_iter()
+        .find(|(transition_ref, _)| *transition_ref.label() == lost_signal_label)
+        .unwrap_or_else(|| panic!("BUG: The condvar {index} should exist in the net"));
+    let (mut copy, place_refs, transition_refs) = copy_net(net);
+    let (not_lost_label, lost_label) = signal_lost_place_labels(index);
+    let not_lost = copy.add_place(&not_lost_label);
+    copy.add_token(&not_lost, 1)
+        .expect("BUG: Adding the initial token to `signal_not_lost` should not cause an overflow");
+    let lost = copy.add_place(&lost_label);
+
+    // The first signal lost moves the token to `signal_lost`.
+    let lost_signal = &transition_refs[&lost_signal_label];
+    add_arc_place_transition(&mut copy, &not_lost, lost_signal);
+    add_arc_transition_place(&mut copy, lost_signal, &lost);
+    // The later ones only test that a signal was already lost.
+    let repeated = copy.add_transition(&lost_signal_repeated_label(index));
+    for place_ref in original.get_preset() {
+        add_arc_place_transition(&mut copy, &place_refs[place_ref.label()], &repeated);
+    }
+    for place_ref in original.get_postset() {
+        add_arc_transition_place(&mut copy, &repeated, &place_refs[place_ref.label()]);
+    }
+    add_arc_place_transition(&mut copy, &lost, &repeated);
+    add_arc_transition_place(&mut copy, &repeated, &lost);
+    copy
+}
+
+/// Replaces the copies of the lost signal transition in a witness path of the instrumented net
+/// by the original transition, so that the path can be replayed on the original net.
+#[must_use]
+pub fn restore_witness_path(witness_path: Vec<String>, index: usize) -> Vec<String> {
+    let (_, lost_signal_label, _) = transition_labels(index);
+    let repeated_label = lost_signal_repeated_label(index);
+    witness_path
+        .into_iter()
+        .map(|label| {
+            if label == repeated_label {
+                lost_signal_label.clone()
+            } else {
+                label
+            }
+        })
+        .collect()
+}
@@ -1,3 +1,79 @@
 //! Submodule for the supported model checkers.
+//!
+//! Every model checker implements the trait `ModelChecker`:
+//! it receives the Petri net and the property to verify
+//! and returns the verdict, the witness path and the statistics of the run.
+//! This allows to select the backend at runtime and to add new ones without changing the callers.
 
 pub mod lola;
+pub mod native;
+
+use crate::data_structures::petri_net_interface::PetriNet;
+use crate::report::{Property, Verdict};
+
+/// A backend that verifies a property of a Petri net.
+pub trait ModelChecker {
+    /// Returns the name of the model checker shown in the results, e.g. `LoLA`.
+    fn name(&self) -> &'static str;
+
+    /// Checks whether the net satisfies the property.
+    ///
+    /// # Errors
+    ///
+    /// If the model checker cannot be run or its result cannot be interpreted,
+    /// then an error describing the problem is returned.
+    fn check(
+        &self,
+        net: &PetriNet,
+        property: Property,
+    ) -> Result<ModelCheckerOutput, ModelCheckerError>;
+}
+
+/// The result of a successful run of a model checker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelCheckerOutput {
+    /// Whether the property is violated, i.e. `Verdict::Deadlock` or `Verdict::DeadlockFree`,
+    /// or for a formula supplied by the user, whether it holds.
+    pub verdict: Verdict,
+    /// The labels of the transitions on a path to a state that violates the property,
+    /// in the order in which they fire, if the property is violated and the model checker reports it.
+    /// For a formula, the path leads to a state that proves or refutes it, if the model checker reports one.
+    pub witness_path: Option<Vec<String>>,
+    /// The number of states explored, if the model checker reports it.
+    pub states: Option<usize>,
+}
+
+/// The reasons why a model checker could not check the property.
+#[derive(Debug)]
+pub enum ModelCheckerError {
+    /// The executable of the model checker was not found at the given path.
+    ExecutableNotFound(String),
+    /// Reading or writing a file or running the executable failed.
+    Io(std::io::Error),
+    /// The output of the model checker does not contain a result.
+    UnknownOutput(String),
+    /// The model checker cannot check this kind of property.
+    UnsupportedProperty(String),
+}
+
+impl std::fmt::Display for ModelCheckerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExecutableNotFound(path) => write!(
+                f,
+                "the executable `{path}` was not found, please check that the model checker is installed"
+            ),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::UnknownOutput(output) => {
+                write!(f, "unknown output of the model checker: {output}")
+            }
+            Self::UnsupportedProperty(reason) => write!(f, "unsupported property: {reason}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ModelCheckerError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
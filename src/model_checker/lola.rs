@@ -1,70 +1,446 @@
 //! Submodule for running the `LoLA` model checker.
+//!
+//! The net is written in `LoLA` format to a file, which is the input of the executable `lola`.
+//! The property is passed as a CTL* formula. `LoLA` writes the result in JSON format
+//! and the witness path to other files.
+//!
+//! On large nets, `LoLA` may run for hours or exhaust the memory of the machine.
+//! A time limit and a memory limit can be set: the process is killed when the time limit expires
+//! and its address space is limited to the given size (only on Unix).
+//! In both cases, the verdict is `Verdict::Inconclusive` with the reason.
 
-use log::info;
-use std::process::Command;
+use log::{info, warn};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
 
-/// Checks for deadlock using the `LoLA` model checker.
-/// Returns `true` if deadlock can be reached, otherwise returns `false`.
-///
-/// The CTL* formula used is `EF (DEADLOCK AND (PROGRAM_END = 0 AND PROGRAM_PANIC = 0))`.
-/// This excludes the `PROGRAM_PANIC` and `PROGRAM_END` from being considered as deadlock states.
-///
-/// # Panics
+use super::{ModelChecker, ModelCheckerError, ModelCheckerOutput};
+use crate::data_structures::petri_net_interface::PetriNet;
+use crate::json;
+use crate::naming::condvar::{place_labels, signal_lost_place_labels};
+use crate::naming::mutex::held_by_label;
+use crate::naming::thread::returned_place_label;
+use crate::report::{Property, Verdict};
+
+/// The CTL* formula checked by `LoLA` to find deadlocks.
+/// Deadlock states that correspond to a normal program end or a `panic!` are excluded.
+pub const DEADLOCK_FORMULA: &str = "EF (DEADLOCK AND (PROGRAM_END = 0 AND PROGRAM_PANIC = 0))";
+
+/// The CTL* formula checked by `LoLA` to find panics.
+/// The place `PANIC_STARTED` is marked by the transitions that start a panic, see the module `panic`.
+pub const PANIC_FORMULA: &str = "EF (PANIC_STARTED > 0)";
+
+/// The CTL* formula checked by `LoLA` to find livelocks.
+/// A state is reachable from which every reachable state is neither a deadlock nor the end of the program.
+pub const LIVELOCK_FORMULA: &str = "EF AG (NOT DEADLOCK AND PROGRAM_END = 0 AND PROGRAM_PANIC = 0)";
+
+/// Returns the CTL* formula checked by `LoLA` to find threads that did not end when the program ends.
+/// The place `THREADS_RUNNING` counts the spawned threads that did not end, see the module `unjoined_thread`.
+/// If `blocked` is set, the state must also be a deadlock, i.e. the threads are blocked forever.
+#[must_use]
+pub fn unjoined_thread_formula(blocked: bool) -> String {
+    if blocked {
+        "EF (DEADLOCK AND PROGRAM_END > 0 AND THREADS_RUNNING > 0)".to_string()
+    } else {
+        "EF (PROGRAM_END > 0 AND THREADS_RUNNING > 0)".to_string()
+    }
+}
+
+/// Returns the CTL* formula checked by `LoLA` to find lost signals on the condvar with the given index.
 ///
-/// If the command `lola` is not found, then the function panics.
-/// If the command `lola` produces an extraneous output, then the function panics.
+/// It is a deadlock as in `DEADLOCK_FORMULA` where a signal on the condvar was lost
+/// and a thread waits on it, i.e. the token of the place `CONDVAR_{i}_WAIT_ENABLED` was taken.
 #[must_use]
-pub fn check_deadlock(
-    net_filepath: &std::path::PathBuf,
-    witness_path: Option<&std::path::PathBuf>,
-) -> bool {
-    let mut cmd = initialize_command("lola", net_filepath, witness_path);
-    let mut backup_cmd = initialize_command("./assets/lola", net_filepath, witness_path);
-
-    let output = match cmd.output() {
-        Ok(output) => output,
-        Err(err) => {
+pub fn lost_signal_formula(condvar: usize) -> String {
+    let (wait_enabled, _) = place_labels(condvar);
+    let (_, signal_lost) = signal_lost_place_labels(condvar);
+    format!(
+        "EF (DEADLOCK AND (PROGRAM_END = 0 AND PROGRAM_PANIC = 0 AND {signal_lost} = 1 AND {wait_enabled} = 0))"
+    )
+}
+
+/// Returns the CTL* formula checked by `LoLA` to find a guard of the mutex with the given index
+/// still held when the given thread returns, i.e. the place `{thread}_RETURNED` is marked while the thread holds the mutex.
+/// The main thread has no index.
+#[must_use]
+pub fn held_guard_formula(mutex: usize, thread: Option<usize>) -> String {
+    format!(
+        "EF ({} > 0 AND {} > 0)",
+        returned_place_label(thread),
+        held_by_label(mutex, thread)
+    )
+}
+
+/// The executables tried in order if no path is given:
+/// the one in the `$PATH` and the precompiled one in the `./assets/` folder of the repository.
+const DEFAULT_EXECUTABLES: [&str; 2] = ["lola", "./assets/lola"];
+
+/// The interval between two checks of whether the process finished when a time limit is set.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The messages that `LoLA` or the dynamic loader print when an allocation fails.
+const OUT_OF_MEMORY_MESSAGES: [&str; 3] = [
+    "memory allocation failed",
+    "Cannot allocate memory",
+    "failed to map segment",
+];
+
+/// The `LoLA` model checker, called as an external program.
+pub struct Lola {
+    /// The path to the executable, or `None` to try the default locations.
+    executable: Option<PathBuf>,
+    /// The path where the net is written in `LoLA` format as the input of the executable.
+    net_filepath: PathBuf,
+    /// The path where the witness path is kept, or `None` to use a temporary file.
+    witness_filepath: Option<PathBuf>,
+    /// The time after which the process is killed, if any.
+    timeout: Option<Duration>,
+    /// The maximum size of the address space of the process in mebibytes, if any.
+    memory_limit: Option<u64>,
+}
+
+/// The result of running the executable to completion or until it was killed.
+struct Finished {
+    /// The exit status of the process.
+    status: ExitStatus,
+    /// Everything that the process wrote to `stderr`.
+    stderr: String,
+    /// Whether the process was killed because the time limit expired.
+    timed_out: bool,
+}
+
+impl Lola {
+    /// Creates a new `Lola` backend without time or memory limits.
+    /// The net is written to `net_filepath` before every check.
+    /// If `executable` is not given, `lola` is searched in the `$PATH` and then in the `./assets/` folder.
+    /// If `witness_filepath` is not given, the witness path is written to a temporary file that is deleted afterwards.
+    #[must_use]
+    pub const fn new(
+        executable: Option<PathBuf>,
+        net_filepath: PathBuf,
+        witness_filepath: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            executable,
+            net_filepath,
+            witness_filepath,
+            timeout: None,
+            memory_limit: None,
+        }
+    }
+
+    /// Sets the time after which the process is killed and the memory limit in mebibytes.
+    /// `None` means no limit.
+    #[must_use]
+    pub const fn with_limits(
+        mut self,
+        timeout: Option<Duration>,
+        memory_limit: Option<u64>,
+    ) -> Self {
+        self.timeout = timeout;
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    /// Runs the executable on the net file with the given arguments and waits for it within the time limit.
+    /// Tries the default locations in order if no executable was given.
+    fn run(&self, args: &[String]) -> Result<Finished, ModelCheckerError> {
+        let not_found = |err: std::io::Error, path: &Path| {
             if err.kind() == std::io::ErrorKind::NotFound {
-                info!(
-                    "`lola` was not found in the $PATH. Please check that the program is installed and added to the $PATH"
-                );
+                ModelCheckerError::ExecutableNotFound(path.to_string_lossy().into_owned())
             } else {
-                panic!("There was an unknown error while executing `lola`: {err}");
+                ModelCheckerError::Io(err)
             }
-            backup_cmd
-                .output()
-                .expect("`lola` was not found in the $PATH nor in the `./assets/` folder")
+        };
+        if let Some(executable) = &self.executable {
+            let child = self
+                .initialize_command(executable, args)
+                .spawn()
+                .map_err(|err| not_found(err, executable))?;
+            return Ok(wait(child, self.timeout)?);
         }
-    };
 
-    // For some reason `LoLA` only generates output to `stderr`.
-    // Parse the answer to the reachability analysis and panic otherwise.
-    let stderr_string =
-        String::from_utf8(output.stderr).expect("Failed to convert the `lola` stderr to UTF-8");
-    if stderr_string.contains("result: yes") {
-        return true;
+        let mut last_error = None;
+        for executable in DEFAULT_EXECUTABLES.map(Path::new) {
+            match self.initialize_command(executable, args).spawn() {
+                Ok(child) => return Ok(wait(child, self.timeout)?),
+                Err(err) => {
+                    info!(
+                        "`{}` could not be executed: {err}. Trying the next location...",
+                        executable.to_string_lossy()
+                    );
+                    last_error = Some(not_found(err, executable));
+                }
+            }
+        }
+        Err(last_error.expect("BUG: At least one default executable should have been tried"))
+    }
+
+    /// Initialize the command for calling the model checker with the right arguments and limits.
+    fn initialize_command(&self, program_filepath: &Path, args: &[String]) -> Command {
+        let mut cmd = Command::new(program_filepath);
+        cmd.arg(&self.net_filepath)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        if let Some(memory_limit) = self.memory_limit {
+            limit_memory(&mut cmd, memory_limit);
+        }
+        cmd
+    }
+
+    /// Interprets the run of `LoLA` using the JSON output, if it was written.
+    /// Returns an inconclusive verdict if the process was killed, ran out of memory or did not report a result.
+    fn verdict(
+        &self,
+        property: &Property,
+        finished: &Finished,
+        output: Option<&json::Value>,
+    ) -> (Verdict, Option<usize>) {
+        if finished.timed_out {
+            let seconds = self.timeout.map_or(0, |timeout| timeout.as_secs());
+            let reason = format!("the time limit of {seconds} s was exceeded");
+            return (Verdict::Inconclusive { reason }, None);
+        }
+        let analysis = output.and_then(|output| output.get("analysis"));
+        let states = analysis
+            .and_then(|analysis| analysis.get("stats"))
+            .and_then(|stats| stats.get("states"))
+            .and_then(json::Value::as_usize);
+        if let Some(result) = analysis
+            .and_then(|analysis| analysis.get("result"))
+            .and_then(json::Value::as_bool)
+        {
+            return (property.verdict(result), states);
+        }
+
+        let error = output
+            .and_then(|output| output.get("call"))
+            .and_then(|call| call.get("error"))
+            .and_then(json::Value::as_str);
+        let signal = terminating_signal(finished.status);
+        // A process that runs out of memory either reports it or crashes on the failed allocation.
+        // Only the limit set by the option explains a crash, the time limit did not expire at this point.
+        let out_of_memory = OUT_OF_MEMORY_MESSAGES.iter().any(|message| {
+            finished.stderr.contains(message) || error.is_some_and(|error| error.contains(message))
+        }) || (self.memory_limit.is_some()
+            && signal.is_some_and(is_allocation_failure_signal));
+        let last_message = finished
+            .stderr
+            .lines()
+            .rev()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("no output");
+        let reason = if let Some(memory_limit) = self.memory_limit
+            && out_of_memory
+        {
+            format!("the memory limit of {memory_limit} MiB was exceeded")
+        } else if let Some(error) = error {
+            format!("`LoLA` failed with the error: {error}")
+        } else if let Some(signal) = signal {
+            format!("`LoLA` was terminated by the signal {signal}, last message: {last_message}")
+        } else {
+            format!(
+                "`LoLA` stopped without a result ({}), last message: {last_message}",
+                finished.status
+            )
+        };
+        (Verdict::Inconclusive { reason }, states)
     }
-    if stderr_string.contains("result: no") {
-        return false;
+}
+
+impl ModelChecker for Lola {
+    fn name(&self) -> &'static str {
+        "LoLA"
+    }
+
+    /// Checks the property using the `LoLA` model checker.
+    ///
+    /// For deadlocks, the CTL* formula used is `EF (DEADLOCK AND (PROGRAM_END = 0 AND PROGRAM_PANIC = 0))`.
+    /// This excludes the `PROGRAM_PANIC` and `PROGRAM_END` from being considered as deadlock states.
+    /// For lost signals, the formula is the one returned by `lost_signal_formula`.
+    fn check(
+        &self,
+        net: &PetriNet,
+        property: Property,
+    ) -> Result<ModelCheckerOutput, ModelCheckerError> {
+        info!(
+            "Creating the input file {} for LoLA...",
+            self.net_filepath.to_string_lossy()
+        );
+        let mut file = std::fs::File::create(&self.net_filepath)?;
+        net.to_lola(&mut file)?;
+
+        let temporary_file = |suffix: &str| {
+            let stem = self
+                .net_filepath
+                .file_stem()
+                .map_or_else(|| "net".into(), |stem| stem.to_string_lossy());
+            std::env::temp_dir().join(format!("{stem}-{suffix}-{}", std::process::id()))
+        };
+        let witness_filepath = self
+            .witness_filepath
+            .clone()
+            .unwrap_or_else(|| temporary_file("witness-path.txt"));
+        let json_filepath = temporary_file("lola.json");
+        // A witness path left over from a previous run must not be taken for the result of this one.
+        if witness_filepath.exists() {
+            std::fs::remove_file(&witness_filepath)?;
+        }
+        let args = [
+            format!("--formula={}", property.formula()),
+            format!("--path={}", witness_filepath.to_string_lossy()),
+            format!("--json={}", json_filepath.to_string_lossy()),
+        ];
+        let finished = self.run(&args)?;
+
+        let output = std::fs::read_to_string(&json_filepath)
+            .ok()
+            .and_then(|contents| {
+                json::parse(&contents)
+                    .inspect_err(|err| warn!("Could not parse the JSON output of LoLA: {err}"))
+                    .ok()
+            });
+        let (verdict, states) = self.verdict(&property, &finished, output.as_ref());
+        // For a formula, `LoLA` writes a path only if one proves or refutes it.
+        let witness_path = match verdict {
+            Verdict::Deadlock | Verdict::Panic | Verdict::Livelock | Verdict::UnjoinedThread => {
+                std::fs::read_to_string(&witness_filepath)
+                    .inspect_err(|err| warn!("Could not read the witness path: {err}"))
+                    .ok()
+                    .map(|contents| parse_witness_path(&contents))
+            }
+            Verdict::Holds | Verdict::DoesNotHold => std::fs::read_to_string(&witness_filepath)
+                .ok()
+                .map(|contents| parse_witness_path(&contents)),
+            _ => None,
+        };
+        // Best effort, a leftover file in the temporary folder is harmless.
+        if self.witness_filepath.is_none() && witness_filepath.exists() {
+            let _ = std::fs::remove_file(&witness_filepath);
+        }
+        if json_filepath.exists() {
+            let _ = std::fs::remove_file(&json_filepath);
+        }
+        Ok(ModelCheckerOutput {
+            verdict,
+            witness_path,
+            states,
+        })
     }
-    panic!("Unknown output in command `lola`: {stderr_string}");
 }
 
-/// Initialize the command for calling the model checker with the right arguments
-fn initialize_command(
-    program_filepath: &str,
-    net_filepath: &std::path::PathBuf,
-    witness_path: Option<&std::path::PathBuf>,
-) -> Command {
-    let mut cmd = Command::new(program_filepath);
+/// Waits for the child process to finish. If the timeout expires first, the process is killed.
+fn wait(mut child: Child, timeout: Option<Duration>) -> Result<Finished, std::io::Error> {
+    // The output is read in another thread so that the process does not block on a full pipe.
+    let mut stderr = child
+        .stderr
+        .take()
+        .expect("BUG: The standard error of the process should be piped");
+    let reader = std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = stderr.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    });
 
-    cmd.arg(net_filepath)
-        .arg("--formula=EF (DEADLOCK AND (PROGRAM_END = 0 AND PROGRAM_PANIC = 0))");
+    let start = Instant::now();
+    let mut timed_out = false;
+    let status = match timeout {
+        None => child.wait()?,
+        Some(timeout) => loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if start.elapsed() >= timeout {
+                info!("The time limit expired, killing LoLA...");
+                // The process may have finished in the meantime, then there is nothing to kill.
+                let _ = child.kill();
+                timed_out = true;
+                break child.wait()?;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        },
+    };
+    // A process started by the killed one may keep the pipe open, so the output is not awaited.
+    let stderr = if timed_out {
+        String::new()
+    } else {
+        reader.join().unwrap_or_default()
+    };
+    Ok(Finished {
+        status,
+        stderr,
+        timed_out,
+    })
+}
 
-    if let Some(path) = witness_path {
-        let path = path.to_string_lossy();
-        cmd.arg(format!("--path={path}"));
+/// Limits the size of the address space of the process started by the command.
+#[cfg(unix)]
+fn limit_memory(cmd: &mut Command, megabytes: u64) {
+    use std::os::unix::process::CommandExt;
+
+    let bytes = megabytes.saturating_mul(1024 * 1024);
+    let limit = libc::rlimit {
+        rlim_cur: bytes,
+        rlim_max: bytes,
+    };
+    // SAFETY: The closure runs in the child process after `fork`.
+    // It only calls `setrlimit`, which is async-signal-safe, and does not allocate.
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_AS, &raw const limit) == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        });
     }
+}
 
-    cmd
+/// Limits the size of the address space of the process started by the command.
+#[cfg(not(unix))]
+fn limit_memory(_cmd: &mut Command, _megabytes: u64) {
+    warn!("The memory limit is only supported on Unix systems, it is ignored");
+}
+
+/// Returns the number of the signal that terminated the process, if it was terminated by a signal.
+#[cfg(unix)]
+fn terminating_signal(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+
+    status.signal()
+}
+
+/// Returns the number of the signal that terminated the process, if it was terminated by a signal.
+#[cfg(not(unix))]
+const fn terminating_signal(_status: ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Checks whether the signal is the one of a process that fails to allocate under a memory limit:
+/// `SIGKILL` if the kernel killed it, `SIGSEGV` if it crashed on the failed allocation.
+#[cfg(unix)]
+const fn is_allocation_failure_signal(signal: i32) -> bool {
+    signal == libc::SIGKILL || signal == libc::SIGSEGV
+}
+
+/// Checks whether the signal is the one of a process that fails to allocate under a memory limit.
+#[cfg(not(unix))]
+const fn is_allocation_failure_signal(_signal: i32) -> bool {
+    false
+}
+
+/// Parses the witness path written by `LoLA` with the option `--path`.
+/// Returns the labels of the transitions in the order in which they fire.
+#[must_use]
+pub fn parse_witness_path(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(ToString::to_string)
+        .collect()
 }
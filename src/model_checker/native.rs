@@ -0,0 +1,956 @@
+//! Submodule for the built-in explicit-state model checker.
+//!
+//! It explores the reachability graph of the Petri net in breadth-first order
+//! directly on the `PetriNet`, without calling an external program.
+//! The markings visited so far are kept in a hashed state set.
+//! Since the search is breadth-first, the witness path found is a shortest one.
+//!
+//! The property checked is the same as with `LoLA`: a deadlock is a marking where no transition is enabled
+//! and the places `PROGRAM_END` and `PROGRAM_PANIC` are empty.
+//!
+//! Optionally, the exploration uses a partial-order reduction based on stubborn sets (Valmari).
+//! In every marking, only the enabled transitions of a stubborn set are fired instead of all enabled transitions.
+//! This preserves every reachable deadlock while skipping most interleavings of independent transitions.
+//! The nets of multi-threaded programs benefit greatly: most transitions only move the token of one thread
+//! between the places of its basic blocks, so their stubborn sets are small.
+//! With the reduction, the witness path found is not necessarily a shortest one.
+//!
+//! A livelock is a reachable marking from which the program can neither end nor block.
+//! It is found by exploring the whole state space and searching backwards from the terminal markings.
+//!
+//! The reachability of a panic, of a guard held when a thread returns or of a thread still running at the end
+//! of the program is checked without the partial-order reduction, since it only preserves deadlocks.
+//!
+//! Formulas supplied by the user are supported if they have the form `EF φ` or `AG φ`
+//! without nested temporal operators. They are checked without the partial-order reduction.
+//!
+//! To enumerate every reachable deadlock, the whole state space is explored and every dead marking
+//! is collected with its witness path, instead of stopping at the first one.
+//!
+//! The exploration does not terminate if the reachability graph is infinite, e.g. for an unbounded net.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, RandomState};
+
+use log::info;
+
+use super::{ModelChecker, ModelCheckerError, ModelCheckerOutput};
+use crate::data_structures::petri_net_interface::PetriNet;
+use crate::formula::{Comparison, Expression, Formula};
+use crate::naming::condvar::{place_labels, signal_lost_place_labels};
+use crate::naming::mutex::held_by_label;
+use crate::naming::thread::returned_place_label;
+use crate::naming::{PANIC_STARTED, PROGRAM_END, PROGRAM_PANIC, THREADS_RUNNING};
+use crate::report::Property;
+
+/// A marking of the net, i.e. the number of tokens in each place, in the order of `PetriNet::places_iter`.
+type Marking = Box<[u32]>;
+
+/// The result of the exploration of the state space.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exploration {
+    /// The labels of the transitions on a path to a deadlock, in the order in which they fire,
+    /// or `None` if no deadlock can be reached.
+    pub witness_path: Option<Vec<String>>,
+    /// The number of distinct markings visited.
+    pub states: usize,
+}
+
+/// Checks for deadlock using the built-in model checker.
+/// If `reduction` is set, the partial-order reduction with stubborn sets is applied.
+/// Without it, the witness path found is a shortest one.
+///
+/// # Panics
+///
+/// If a place holds more than `u32::MAX` tokens, then the function panics.
+#[must_use]
+pub fn check_deadlock(net: &PetriNet, reduction: bool) -> Exploration {
+    let net = CompactNet::new(net);
+    explore(&net, reduction, |marking, enabled| {
+        enabled.is_empty() && net.is_deadlock(marking)
+    })
+}
+
+/// Enumerates every reachable deadlock using the built-in model checker, exploring the whole state space.
+/// Returns the witness paths to the distinct deadlock markings in the order in which they are found,
+/// and the number of states visited.
+/// Without `reduction`, the order is breadth-first and every witness path is a shortest one to its marking.
+/// The partial-order reduction preserves every deadlock, but the witness paths are not necessarily the shortest.
+///
+/// # Panics
+///
+/// If a place holds more than `u32::MAX` tokens, then the function panics.
+#[must_use]
+pub fn enumerate_deadlocks(net: &PetriNet, reduction: bool) -> (Vec<Vec<String>>, usize) {
+    let net = CompactNet::new(net);
+    explore_targets(
+        &net,
+        reduction,
+        |marking, enabled| enabled.is_empty() && net.is_deadlock(marking),
+        true,
+    )
+}
+
+/// Checks for a lost signal on the condvar with the given index using the built-in model checker.
+///
+/// A lost signal is a deadlock where a signal on the condvar was lost and a thread waits on it.
+/// The net must be instrumented with `lost_signal::instrument`.
+/// The deadlocks are preserved by the partial-order reduction, so `reduction` can be set as well.
+///
+/// # Panics
+///
+/// If the net is not instrumented for the condvar or a place holds more than `u32::MAX` tokens,
+/// then the function panics.
+#[must_use]
+pub fn check_lost_signal(net: &PetriNet, condvar: usize, reduction: bool) -> Exploration {
+    let net = CompactNet::new(net);
+    let (wait_enabled, _) = place_labels(condvar);
+    let (_, signal_lost) = signal_lost_place_labels(condvar);
+    let wait_enabled = net.place_index(&wait_enabled);
+    let signal_lost = net.place_index(&signal_lost);
+    explore(&net, reduction, |marking, enabled| {
+        enabled.is_empty()
+            && net.is_deadlock(marking)
+            && marking[signal_lost] > 0
+            && marking[wait_enabled] == 0
+    })
+}
+
+/// Checks whether a panic can start using the built-in model checker.
+/// The net must be instrumented with `panic::instrument`.
+/// The partial-order reduction is not applied, since it only preserves deadlocks.
+///
+/// # Panics
+///
+/// If the net is not instrumented or a place holds more than `u32::MAX` tokens, then the function panics.
+#[must_use]
+pub fn check_panic(net: &PetriNet) -> Exploration {
+    let net = CompactNet::new(net);
+    let panic_started = net.place_index(PANIC_STARTED);
+    explore(&net, false, |marking, _| marking[panic_started] > 0)
+}
+
+/// Checks whether the program can end while a spawned thread did not end using the built-in model checker.
+/// If `blocked` is set, no transition may be enabled in the state, i.e. the threads are blocked forever.
+/// The net must be instrumented with `unjoined_thread::instrument`.
+/// The partial-order reduction is not applied, since it only preserves deadlocks.
+///
+/// # Panics
+///
+/// If the net is not instrumented or a place holds more than `u32::MAX` tokens, then the function panics.
+#[must_use]
+pub fn check_unjoined_thread(net: &PetriNet, blocked: bool) -> Exploration {
+    let net = CompactNet::new(net);
+    let program_end = net.place_index(PROGRAM_END);
+    let running = net.place_index(THREADS_RUNNING);
+    explore(&net, false, |marking, enabled| {
+        marking[program_end] > 0 && marking[running] > 0 && (!blocked || enabled.is_empty())
+    })
+}
+
+/// Checks whether the thread with the given index can return while it holds the mutex with the given index
+/// using the built-in model checker. The main thread has no index.
+/// The net must be instrumented with `held_guard::instrument`.
+/// The partial-order reduction is not applied, since it only preserves deadlocks.
+///
+/// # Panics
+///
+/// If the net is not instrumented for the mutex and the thread or a place holds more than `u32::MAX` tokens,
+/// then the function panics.
+#[must_use]
+pub fn check_held_guard(net: &PetriNet, mutex: usize, thread: Option<usize>) -> Exploration {
+    let net = CompactNet::new(net);
+    let end = net.place_index(&returned_place_label(thread));
+    let held = net.place_index(&held_by_label(mutex, thread));
+    explore(&net, false, |marking, _| {
+        marking[end] > 0 && marking[held] > 0
+    })
+}
+
+/// Checks for a livelock using the built-in model checker, i.e. a reachable marking from which
+/// no marking where the program ended or no transition is enabled can be reached.
+/// Under a fairness assumption, every loop with an exit is eventually left,
+/// so the program does not terminate if and only if such a marking is reachable.
+/// This includes a thread that loops forever while another thread is blocked waiting for it.
+/// The whole state space is explored, since the partial-order reduction only preserves deadlocks.
+/// Returns the exploration, whose witness path leads to the first such marking in breadth-first order.
+///
+/// # Panics
+///
+/// If a place holds more than `u32::MAX` tokens, then the function panics.
+#[must_use]
+pub fn check_livelock(net: &PetriNet) -> Exploration {
+    let net = CompactNet::new(net);
+    // The markings visited so far, numbered in breadth-first order.
+    let mut markings = MarkingTable::new(net.initial_marking.clone());
+    let mut parents: Vec<Option<(usize, usize)>> = vec![None];
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut terminal = Vec::new();
+
+    let mut index = 0;
+    while index < markings.len() {
+        let marking = markings.get(index).clone();
+        let enabled: Vec<usize> = net.enabled_transitions(&marking).collect();
+        // A marking is terminal if the program ended or no transition is enabled.
+        terminal.push(enabled.is_empty() || !net.is_deadlock(&marking));
+        for transition in enabled {
+            let (successor_index, new) = markings.insert(net.fire(&marking, transition));
+            if new {
+                parents.push(Some((index, transition)));
+                predecessors.push(Vec::new());
+            }
+            predecessors[successor_index].push(index);
+        }
+        index += 1;
+    }
+
+    // The markings from which a terminal marking can be reached, found backwards.
+    let mut can_terminate = terminal.clone();
+    let mut stack: Vec<usize> = (0..markings.len())
+        .filter(|index| terminal[*index])
+        .collect();
+    while let Some(index) = stack.pop() {
+        for predecessor in &predecessors[index] {
+            if !can_terminate[*predecessor] {
+                can_terminate[*predecessor] = true;
+                stack.push(*predecessor);
+            }
+        }
+    }
+    let witness_path = can_terminate
+        .iter()
+        .position(|can_terminate| !can_terminate)
+        .map(|index| net.witness_path(&parents, index));
+    info!(
+        "{} after visiting {} states",
+        if witness_path.is_some() {
+            "Found a livelock"
+        } else {
+            "No livelock found"
+        },
+        markings.len()
+    );
+    Exploration {
+        witness_path,
+        states: markings.len(),
+    }
+}
+
+/// The markings visited by an exploration that needs them all afterwards, numbered in the order of insertion.
+/// Every marking is stored once: the index only maps the hash of a marking to the numbers of the markings with that hash.
+struct MarkingTable {
+    markings: Vec<Marking>,
+    index: HashMap<u64, Vec<usize>>,
+    hasher: RandomState,
+}
+
+impl MarkingTable {
+    /// Creates a table that contains the initial marking with the number 0.
+    fn new(initial_marking: Marking) -> Self {
+        let mut table = Self {
+            markings: Vec::new(),
+            index: HashMap::new(),
+            hasher: RandomState::new(),
+        };
+        table.insert(initial_marking);
+        table
+    }
+
+    /// Returns the number of markings in the table.
+    const fn len(&self) -> usize {
+        self.markings.len()
+    }
+
+    /// Returns the marking with the given number.
+    fn get(&self, number: usize) -> &Marking {
+        &self.markings[number]
+    }
+
+    /// Inserts the marking if it is not in the table yet.
+    /// Returns the number of the marking and whether it was inserted.
+    fn insert(&mut self, marking: Marking) -> (usize, bool) {
+        let numbers = self
+            .index
+            .entry(self.hasher.hash_one(&marking))
+            .or_default();
+        if let Some(number) = numbers
+            .iter()
+            .find(|number| self.markings[**number] == marking)
+        {
+            return (*number, false);
+        }
+        numbers.push(self.markings.len());
+        self.markings.push(marking);
+        (self.markings.len() - 1, true)
+    }
+}
+
+/// Finds a cycle of transitions from the marking reached by the witness path of a livelock.
+/// Starting from it, the first enabled transition is fired until a marking repeats.
+/// Since no terminal marking can be reached from the marking, a transition is always enabled.
+/// Returns the labels of the transitions in the cycle, in firing order,
+/// or `None` if the witness path cannot be replayed or the walk reaches a marking without enabled transitions.
+#[must_use]
+pub fn livelock_cycle(net: &PetriNet, witness_path: &[String]) -> Option<Vec<String>> {
+    let net = CompactNet::new(net);
+    let mut marking = net.initial_marking.clone();
+    for label in witness_path {
+        let transition = net.transitions.iter().position(|other| other == label)?;
+        if !net.is_enabled(&marking, transition) {
+            return None;
+        }
+        marking = net.fire(&marking, transition);
+    }
+    // The position in the walk where each marking was visited.
+    let mut visited: HashMap<Marking, usize> = HashMap::new();
+    let mut walk = Vec::new();
+    loop {
+        if let Some(start) = visited.insert(marking.clone(), walk.len()) {
+            return Some(walk.split_off(start));
+        }
+        let transition = net.enabled_transitions(&marking).next()?;
+        walk.push(net.transitions[transition].clone());
+        marking = net.fire(&marking, transition);
+    }
+}
+
+/// Checks a formula of the form `EF φ` or `AG φ`, where `φ` has no temporal operators,
+/// using the built-in model checker. The partial-order reduction is not applied, since it only preserves deadlocks.
+/// Returns whether the formula holds and the exploration, whose witness path leads to a state
+/// that satisfies `φ` for `EF φ` or violates it for `AG φ`.
+///
+/// # Errors
+///
+/// If the formula has another form, then an error describing the problem is returned.
+///
+/// # Panics
+///
+/// If a place holds more than `u32::MAX` tokens, then the function panics.
+pub fn check_formula(net: &PetriNet, formula: &Formula) -> Result<(bool, Exploration), String> {
+    let Some((exists, expression)) = formula.as_reachability() else {
+        return Err(
+            "the native model checker only supports formulas of the form `EF φ` or `AG φ` \
+             without nested temporal operators, use `LoLA` for other formulas"
+                .to_string(),
+        );
+    };
+    let net = CompactNet::new(net);
+    let predicate = Predicate::new(expression, &net)?;
+    // `AG φ` holds if no state violates `φ`.
+    let exploration = explore(&net, false, |marking, enabled| {
+        predicate.evaluate(marking, enabled) == exists
+    });
+    let found = exploration.witness_path.is_some();
+    Ok((found == exists, exploration))
+}
+
+/// Explores the state space until it finds a marking for which `is_target` holds,
+/// given the marking and the transitions enabled in it.
+/// Returns the witness path to it, if any, and the number of states visited.
+fn explore(
+    net: &CompactNet,
+    reduction: bool,
+    is_target: impl Fn(&Marking, &[usize]) -> bool,
+) -> Exploration {
+    let (witness_paths, states) = explore_targets(net, reduction, is_target, false);
+    Exploration {
+        witness_path: witness_paths.into_iter().next(),
+        states,
+    }
+}
+
+/// Explores the state space and collects the witness paths to the markings for which `is_target` holds,
+/// in the order in which they are found. If `all` is not set, the exploration stops at the first one.
+/// Returns the witness paths and the number of states visited.
+fn explore_targets(
+    net: &CompactNet,
+    reduction: bool,
+    is_target: impl Fn(&Marking, &[usize]) -> bool,
+    all: bool,
+) -> (Vec<Vec<String>>, usize) {
+    let initial_marking = net.initial_marking.clone();
+
+    // The markings visited so far.
+    let mut visited: HashSet<Marking> = HashSet::new();
+    // For every marking visited, the marking it was reached from and the transition fired.
+    let mut parents: Vec<Option<(usize, usize)>> = vec![None];
+    let mut queue = VecDeque::new();
+    let mut stubborn_set = StubbornSet::new(net.transitions.len());
+    let mut witness_paths = Vec::new();
+    visited.insert(initial_marking.clone());
+    queue.push_back((initial_marking, 0));
+
+    while let Some((marking, index)) = queue.pop_front() {
+        let enabled: Vec<usize> = net.enabled_transitions(&marking).collect();
+        if is_target(&marking, &enabled) {
+            witness_paths.push(net.witness_path(&parents, index));
+            if !all {
+                info!(
+                    "Found a target state after visiting {} states",
+                    visited.len()
+                );
+                return (witness_paths, visited.len());
+            }
+        }
+        if enabled.is_empty() {
+            continue;
+        }
+        let fired = if reduction {
+            stubborn_set.compute(net, &marking, &enabled)
+        } else {
+            enabled
+        };
+        for transition in fired {
+            let successor = net.fire(&marking, transition);
+            if !visited.insert(successor.clone()) {
+                continue;
+            }
+            let successor_index = parents.len();
+            parents.push(Some((index, transition)));
+            queue.push_back((successor, successor_index));
+        }
+    }
+    if witness_paths.is_empty() {
+        info!(
+            "No target state found after visiting {} states",
+            visited.len()
+        );
+    } else {
+        info!(
+            "Found {} target states after visiting {} states",
+            witness_paths.len(),
+            visited.len()
+        );
+    }
+    (witness_paths, visited.len())
+}
+
+/// The built-in model checker as a backend.
+pub struct Native {
+    /// Whether the partial-order reduction with stubborn sets is applied.
+    reduction: bool,
+}
+
+impl Native {
+    /// Creates a new `Native` backend.
+    /// If `reduction` is set, the partial-order reduction with stubborn sets is applied.
+    #[must_use]
+    pub const fn new(reduction: bool) -> Self {
+        Self { reduction }
+    }
+}
+
+impl ModelChecker for Native {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn check(
+        &self,
+        net: &PetriNet,
+        property: Property,
+    ) -> Result<ModelCheckerOutput, ModelCheckerError> {
+        let (result, exploration) = match &property {
+            Property::Deadlock => {
+                let exploration = check_deadlock(net, self.reduction);
+                (exploration.witness_path.is_some(), exploration)
+            }
+            Property::LostSignal { condvar } => {
+                let exploration = check_lost_signal(net, *condvar, self.reduction);
+                (exploration.witness_path.is_some(), exploration)
+            }
+            Property::HeldGuard { mutex, thread } => {
+                let exploration = check_held_guard(net, *mutex, thread.0);
+                (exploration.witness_path.is_some(), exploration)
+            }
+            Property::Panic => {
+                let exploration = check_panic(net);
+                (exploration.witness_path.is_some(), exploration)
+            }
+            Property::Livelock => {
+                let exploration = check_livelock(net);
+                (exploration.witness_path.is_some(), exploration)
+            }
+            Property::UnjoinedThread { blocked } => {
+                let exploration = check_unjoined_thread(net, *blocked);
+                (exploration.witness_path.is_some(), exploration)
+            }
+            Property::Formula(formula) => {
+                check_formula(net, formula).map_err(ModelCheckerError::UnsupportedProperty)?
+            }
+        };
+        let verdict = property.verdict(result);
+        Ok(ModelCheckerOutput {
+            verdict,
+            witness_path: exploration.witness_path,
+            states: Some(exploration.states),
+        })
+    }
+}
+
+/// A representation of the Petri net with places and transitions replaced by indices
+/// to fire transitions without looking up labels.
+struct CompactNet {
+    /// The index of each place by its label.
+    place_indices: HashMap<String, usize>,
+    /// The labels of the transitions.
+    transitions: Vec<String>,
+    /// The indices of the places in the preset of each transition.
+    presets: Vec<Vec<usize>>,
+    /// The indices of the places in the postset of each transition.
+    postsets: Vec<Vec<usize>>,
+    /// The indices of the transitions that consume a token from each place.
+    consumers: Vec<Vec<usize>>,
+    /// The indices of the transitions that produce a token in each place.
+    producers: Vec<Vec<usize>>,
+    /// The marking of the net before any transition fires.
+    initial_marking: Marking,
+    /// The indices of the places that mark the end of the program, normal or after a `panic!`.
+    end_places: Vec<usize>,
+}
+
+impl CompactNet {
+    /// Converts the Petri net to its compact representation.
+    fn new(net: &PetriNet) -> Self {
+        let mut place_indices = HashMap::new();
+        let mut initial_marking = Vec::with_capacity(net.get_cardinality_places());
+        let mut end_places = Vec::new();
+        for (index, (place_ref, place)) in net.places_iter().enumerate() {
+            place_indices.insert(place_ref.label().clone(), index);
+            initial_marking.push(
+                u32::try_from(place.marking())
+                    .expect("BUG: The initial marking of a place should fit in a `u32`"),
+            );
+            if place_ref.label() == PROGRAM_END || place_ref.label() == PROGRAM_PANIC {
+                end_places.push(index);
+            }
+        }
+
+        let mut transitions = Vec::with_capacity(net.get_cardinality_transitions());
+        let mut presets = Vec::with_capacity(net.get_cardinality_transitions());
+        let mut postsets = Vec::with_capacity(net.get_cardinality_transitions());
+        let mut consumers = vec![Vec::new(); initial_marking.len()];
+        let mut producers = vec![Vec::new(); initial_marking.len()];
+        for (index, (transition_ref, transition)) in net.transitions_iter().enumerate() {
+            transitions.push(transition_ref.label().clone());
+            let preset: Vec<usize> = transition
+                .get_preset()
+                .iter()
+                .map(|place_ref| place_indices[place_ref.label()])
+                .collect();
+            let postset: Vec<usize> = transition
+                .get_postset()
+                .iter()
+                .map(|place_ref| place_indices[place_ref.label()])
+                .collect();
+            for place in &preset {
+                consumers[*place].push(index);
+            }
+            for place in &postset {
+                producers[*place].push(index);
+            }
+            presets.push(preset);
+            postsets.push(postset);
+        }
+
+        Self {
+            place_indices,
+            transitions,
+            presets,
+            postsets,
+            consumers,
+            producers,
+            initial_marking: initial_marking.into_boxed_slice(),
+            end_places,
+        }
+    }
+
+    /// Returns the index of the place with the given label.
+    ///
+    /// # Panics
+    ///
+    /// If the place does not exist in the net, then the function panics.
+    fn place_index(&self, label: &str) -> usize {
+        *self
+            .place_indices
+            .get(label)
+            .unwrap_or_else(|| panic!("BUG: The place `{label}` should exist in the net"))
+    }
+
+    /// Checks whether the transition is enabled in the marking.
+    fn is_enabled(&self, marking: &Marking, transition: usize) -> bool {
+        self.presets[transition]
+            .iter()
+            .all(|place| marking[*place] > 0)
+    }
+
+    /// Returns the indices of the transitions enabled in the marking.
+    fn enabled_transitions<'a>(&'a self, marking: &'a Marking) -> impl Iterator<Item = usize> + 'a {
+        self.presets
+            .iter()
+            .enumerate()
+            .map(|(transition, _)| transition)
+            .filter(|transition| self.is_enabled(marking, *transition))
+    }
+
+    /// Returns the marking after firing the transition, which must be enabled.
+    fn fire(&self, marking: &Marking, transition: usize) -> Marking {
+        let mut successor = marking.clone();
+        for place in &self.presets[transition] {
+            successor[*place] -= 1;
+        }
+        for place in &self.postsets[transition] {
+            successor[*place] = successor[*place]
+                .checked_add(1)
+                .expect("The number of tokens in a place overflowed a `u32`");
+        }
+        successor
+    }
+
+    /// Checks whether a marking without enabled transitions is a deadlock,
+    /// i.e. the program did not end normally or with a `panic!`.
+    fn is_deadlock(&self, marking: &Marking) -> bool {
+        self.end_places.iter().all(|place| marking[*place] == 0)
+    }
+
+    /// Follows the parents from the marking with the given index back to the initial marking
+    /// and returns the labels of the transitions fired along the way, in firing order.
+    fn witness_path(&self, parents: &[Option<(usize, usize)>], mut index: usize) -> Vec<String> {
+        let mut path = Vec::new();
+        while let Some((parent, transition)) = parents[index] {
+            path.push(self.transitions[transition].clone());
+            index = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// A formula without temporal operators, with places and transitions replaced by indices in the `CompactNet`.
+enum Predicate {
+    Constant(bool),
+    Deadlock,
+    Fireable(usize),
+    Tokens {
+        places: Vec<usize>,
+        comparison: Comparison,
+        value: u64,
+    },
+    Not(Box<Self>),
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Implies(Box<Self>, Box<Self>),
+}
+
+impl Predicate {
+    /// Converts a resolved formula without temporal operators.
+    fn new(expression: &Expression, net: &CompactNet) -> Result<Self, String> {
+        let operand = |operand: &Expression| Self::new(operand, net).map(Box::new);
+        Ok(match expression {
+            Expression::Constant(value) => Self::Constant(*value),
+            Expression::Deadlock => Self::Deadlock,
+            Expression::Fireable(label) => Self::Fireable(
+                net.transitions
+                    .iter()
+                    .position(|transition| transition == label)
+                    .ok_or_else(|| format!("the transition `{label}` does not exist in the net"))?,
+            ),
+            Expression::Tokens {
+                places,
+                comparison,
+                value,
+            } => Self::Tokens {
+                places: places
+                    .iter()
+                    .map(|label| {
+                        net.place_indices
+                            .get(label)
+                            .copied()
+                            .ok_or_else(|| format!("the place `{label}` does not exist in the net"))
+                    })
+                    .collect::<Result<_, _>>()?,
+                comparison: *comparison,
+                value: *value,
+            },
+            Expression::Not(inner) => Self::Not(operand(inner)?),
+            Expression::And(left, right) => Self::And(operand(left)?, operand(right)?),
+            Expression::Or(left, right) => Self::Or(operand(left)?, operand(right)?),
+            Expression::Implies(left, right) => Self::Implies(operand(left)?, operand(right)?),
+            Expression::Symbol { name, .. } => {
+                return Err(format!("the atomic proposition `{name}` is not resolved"));
+            }
+            Expression::Until(_, _) | Expression::Temporal(_, _) => {
+                return Err("nested temporal operators are not supported".to_string());
+            }
+        })
+    }
+
+    /// Evaluates the predicate in the marking, given the transitions enabled in it.
+    fn evaluate(&self, marking: &Marking, enabled: &[usize]) -> bool {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Deadlock => enabled.is_empty(),
+            Self::Fireable(transition) => enabled.contains(transition),
+            Self::Tokens {
+                places,
+                comparison,
+                value,
+            } => {
+                let tokens = places.iter().map(|place| u64::from(marking[*place])).sum();
+                comparison.holds(tokens, *value)
+            }
+            Self::Not(operand) => !operand.evaluate(marking, enabled),
+            Self::And(left, right) => {
+                left.evaluate(marking, enabled) && right.evaluate(marking, enabled)
+            }
+            Self::Or(left, right) => {
+                left.evaluate(marking, enabled) || right.evaluate(marking, enabled)
+            }
+            Self::Implies(left, right) => {
+                !left.evaluate(marking, enabled) || right.evaluate(marking, enabled)
+            }
+        }
+    }
+}
+
+/// Computes stubborn sets for deadlock checking.
+/// The buffers are reused between markings to avoid allocating in every step of the exploration.
+struct StubbornSet {
+    /// Whether each transition is in the set being computed.
+    in_set: Vec<bool>,
+    /// The transitions added to the set being computed.
+    members: Vec<usize>,
+}
+
+impl StubbornSet {
+    /// Creates the buffers for a net with the given number of transitions.
+    fn new(transitions: usize) -> Self {
+        Self {
+            in_set: vec![false; transitions],
+            members: Vec::new(),
+        }
+    }
+
+    /// Returns the enabled transitions of a stubborn set in the marking, which must have enabled transitions.
+    ///
+    /// The set is the closure of a single enabled transition under the rules:
+    /// - For an enabled transition in the set, every transition that consumes from its preset is in the set,
+    ///   since they compete for the same tokens.
+    /// - For a disabled transition in the set, every transition that produces in one empty place of its preset
+    ///   is in the set, since only they can enable it.
+    ///
+    /// The closure is computed starting from every enabled transition and the one with fewest enabled transitions is kept.
+    fn compute(&mut self, net: &CompactNet, marking: &Marking, enabled: &[usize]) -> Vec<usize> {
+        let mut best: Option<Vec<usize>> = None;
+        for seed in enabled {
+            let bound = best.as_ref().map_or(usize::MAX, Vec::len);
+            if let Some(candidate) = self.closure(net, marking, *seed, bound) {
+                let is_singleton = candidate.len() == 1;
+                best = Some(candidate);
+                if is_singleton {
+                    break;
+                }
+            }
+        }
+        best.expect(
+            "BUG: The closure of the first enabled transition should always be a stubborn set",
+        )
+    }
+
+    /// Computes the closure of the seed transition and returns its enabled transitions.
+    /// Gives up and returns `None` as soon as the closure contains `bound` enabled transitions.
+    fn closure(
+        &mut self,
+        net: &CompactNet,
+        marking: &Marking,
+        seed: usize,
+        bound: usize,
+    ) -> Option<Vec<usize>> {
+        let mut enabled = Vec::new();
+        let mut pending = vec![seed];
+        self.in_set[seed] = true;
+        self.members.push(seed);
+        while let Some(transition) = pending.pop() {
+            if net.is_enabled(marking, transition) {
+                enabled.push(transition);
+                if enabled.len() >= bound {
+                    break;
+                }
+                for place in &net.presets[transition] {
+                    self.add_all(&net.consumers[*place], &mut pending);
+                }
+            } else {
+                let empty_place = net.presets[transition]
+                    .iter()
+                    .find(|place| marking[**place] == 0)
+                    .expect("BUG: A disabled transition should have an empty place in its preset");
+                self.add_all(&net.producers[*empty_place], &mut pending);
+            }
+        }
+        for member in self.members.drain(..) {
+            self.in_set[member] = false;
+        }
+        (enabled.len() < bound).then_some(enabled)
+    }
+
+    /// Adds the transitions that are not in the set yet to the set and to the pending transitions.
+    fn add_all(&mut self, transitions: &[usize], pending: &mut Vec<usize>) {
+        for transition in transitions {
+            if !self.in_set[*transition] {
+                self.in_set[*transition] = true;
+                self.members.push(*transition);
+                pending.push(*transition);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod native_tests {
+    use super::*;
+    use crate::data_structures::petri_net_interface::{
+        PlaceRef, add_arc_place_transition, add_arc_transition_place,
+    };
+    use crate::test_nets::{add_marked_place, connect, net_with_program_places};
+
+    #[test]
+    fn program_that_ends_is_deadlock_free() {
+        let (mut net, program_start, program_end) = net_with_program_places();
+        let transition = net.add_transition("RETURN");
+        add_arc_place_transition(&mut net, &program_start, &transition);
+        add_arc_transition_place(&mut net, &transition, &program_end);
+
+        assert_eq!(check_deadlock(&net, false).witness_path, None);
+    }
+
+    #[test]
+    fn finds_the_shortest_path_to_a_deadlock() {
+        let (mut net, program_start, program_end) = net_with_program_places();
+        let middle = net.add_place("MIDDLE");
+        let stuck = net.add_place("STUCK");
+        // A long path to the end of the program and a short path to a deadlock.
+        let first = net.add_transition("FIRST");
+        add_arc_place_transition(&mut net, &program_start, &first);
+        add_arc_transition_place(&mut net, &first, &middle);
+        let second = net.add_transition("SECOND");
+        add_arc_place_transition(&mut net, &middle, &second);
+        let third = net.add_transition("THIRD");
+        add_arc_transition_place(&mut net, &second, &stuck);
+        add_arc_place_transition(&mut net, &stuck, &third);
+        add_arc_transition_place(&mut net, &third, &program_end);
+        let shortcut = net.add_transition("SHORTCUT");
+        let dead_end = net.add_place("DEAD_END");
+        add_arc_place_transition(&mut net, &middle, &shortcut);
+        add_arc_transition_place(&mut net, &shortcut, &dead_end);
+
+        assert_eq!(
+            check_deadlock(&net, false).witness_path,
+            Some(vec!["FIRST".to_string(), "SHORTCUT".to_string()])
+        );
+    }
+
+    #[test]
+    fn initial_deadlock_has_an_empty_witness_path() {
+        let (net, _, _) = net_with_program_places();
+
+        assert_eq!(check_deadlock(&net, false).witness_path, Some(Vec::new()));
+    }
+
+    /// Adds a chain of places connected by transitions, with a token in the first place.
+    /// Returns the last place of the chain.
+    fn add_chain(net: &mut PetriNet, prefix: &str, length: usize) -> PlaceRef {
+        let mut place = add_marked_place(net, &format!("{prefix}_0"), 1);
+        for index in 1..=length {
+            let next_place = net.add_place(&format!("{prefix}_{index}"));
+            connect(net, &place, &next_place, &format!("{prefix}_T{index}"));
+            place = next_place;
+        }
+        place
+    }
+
+    #[test]
+    fn reduction_skips_interleavings_of_independent_threads() {
+        let (mut net, _, _) = net_with_program_places();
+        add_chain(&mut net, "A", 3);
+        add_chain(&mut net, "B", 3);
+
+        let full = check_deadlock(&net, false);
+        let reduced = check_deadlock(&net, true);
+
+        // `PROGRAM_START` is never consumed, so the final marking is a deadlock.
+        assert_eq!(full.states, 16);
+        assert!(reduced.states < full.states);
+        assert_eq!(reduced.witness_path.map(|path| path.len()), Some(6));
+    }
+
+    #[test]
+    fn reduction_preserves_the_deadlock_of_locks_in_opposite_order() {
+        let (mut net, _, program_end) = net_with_program_places();
+        let first_mutex = add_marked_place(&mut net, "MUTEX_0", 1);
+        let second_mutex = add_marked_place(&mut net, "MUTEX_1", 1);
+        let mut thread_ends = Vec::new();
+        for (name, mutexes) in [
+            ("A", [&first_mutex, &second_mutex]),
+            ("B", [&second_mutex, &first_mutex]),
+        ] {
+            let start = add_marked_place(&mut net, &format!("{name}_START"), 1);
+            let first_lock = net.add_transition(&format!("{name}_LOCK_FIRST"));
+            let holding_first = net.add_place(&format!("{name}_HOLDING_FIRST"));
+            add_arc_place_transition(&mut net, &start, &first_lock);
+            add_arc_place_transition(&mut net, mutexes[0], &first_lock);
+            add_arc_transition_place(&mut net, &first_lock, &holding_first);
+            let second_lock = net.add_transition(&format!("{name}_LOCK_SECOND"));
+            let holding_both = net.add_place(&format!("{name}_HOLDING_BOTH"));
+            add_arc_place_transition(&mut net, &holding_first, &second_lock);
+            add_arc_place_transition(&mut net, mutexes[1], &second_lock);
+            add_arc_transition_place(&mut net, &second_lock, &holding_both);
+            let unlock = net.add_transition(&format!("{name}_UNLOCK"));
+            let end = net.add_place(&format!("{name}_END"));
+            add_arc_place_transition(&mut net, &holding_both, &unlock);
+            add_arc_transition_place(&mut net, &unlock, &end);
+            add_arc_transition_place(&mut net, &unlock, mutexes[0]);
+            add_arc_transition_place(&mut net, &unlock, mutexes[1]);
+            thread_ends.push(end);
+        }
+        let join = net.add_transition("JOIN");
+        for end in &thread_ends {
+            add_arc_place_transition(&mut net, end, &join);
+        }
+        add_arc_transition_place(&mut net, &join, &program_end);
+
+        for reduction in [false, true] {
+            let witness_path = check_deadlock(&net, reduction)
+                .witness_path
+                .expect("The deadlock should be found");
+            assert!(witness_path.contains(&"A_LOCK_FIRST".to_string()));
+            assert!(witness_path.contains(&"B_LOCK_FIRST".to_string()));
+        }
+    }
+
+    #[test]
+    fn every_dead_marking_is_enumerated_with_a_shortest_path() {
+        let (mut net, program_start, _) = net_with_program_places();
+        // `PROGRAM_START` leads to two dead ends, one of them after a longer detour.
+        for (name, length) in [("LEFT", 1), ("RIGHT", 2)] {
+            let mut place = program_start.clone();
+            for index in 0..length {
+                let next_place = net.add_place(&format!("{name}_{index}"));
+                let transition = net.add_transition(&format!("{name}_T{index}"));
+                add_arc_place_transition(&mut net, &place, &transition);
+                add_arc_transition_place(&mut net, &transition, &next_place);
+                place = next_place;
+            }
+        }
+
+        for reduction in [false, true] {
+            let (witness_paths, _) = enumerate_deadlocks(&net, reduction);
+            assert_eq!(
+                witness_paths,
+                vec![
+                    vec!["LEFT_T0".to_string()],
+                    vec!["RIGHT_T0".to_string(), "RIGHT_T1".to_string()]
+                ]
+            );
+        }
+    }
+}
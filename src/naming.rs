@@ -11,6 +11,7 @@
 pub mod basic_block;
 pub mod condvar;
 pub mod function;
+pub mod local;
 pub mod mutex;
 pub mod thread;
 
@@ -20,6 +21,19 @@ pub const PROGRAM_START: &str = "PROGRAM_START";
 pub const PROGRAM_END: &str = "PROGRAM_END";
 /// Label of the place that models the program end state after a `panic!`.
 pub const PROGRAM_PANIC: &str = "PROGRAM_PANIC";
+/// Label of the place added to record that a panic started, used only to check whether a panic is reachable.
+pub const PANIC_STARTED: &str = "PANIC_STARTED";
+/// Label of the place added to count the spawned threads that did not end,
+/// used only to check whether the program can end before them.
+pub const THREADS_RUNNING: &str = "THREADS_RUNNING";
+
+/// Checks whether the place with the given label models a synchronization primitive
+/// or the start or end of a thread, i.e. a place shared between several threads.
+/// See the labels in the submodules `mutex`, `condvar` and `thread`.
+#[inline]
+pub fn is_synchronization_place(label: &str) -> bool {
+    label.starts_with("MUTEX_") || label.starts_with("CONDVAR_") || label.starts_with("THREAD_")
+}
 
 /// Sanitize the function name for the DOT and the `LoLA` format:
 /// - Replace generic types "<T>" with "T".
@@ -18,6 +18,14 @@ pub fn place_label(function_name: &str, index: usize) -> String {
     format!("{}_BB{index}", sanitize(function_name))
 }
 
+/// Checks whether the label is the one of the place of a `BasicBlock`.
+#[inline]
+pub fn is_place_label(label: &str) -> bool {
+    label
+        .rsplit_once("_BB")
+        .is_some_and(|(_, index)| index.parse::<usize>().is_ok())
+}
+
 /// Label of the transition that represents a goto terminator to another `BasicBlock`.
 #[inline]
 pub fn goto_transition_label(function_name: &str, index: usize) -> String {
@@ -67,8 +75,40 @@ pub fn assert_cleanup_transition_label(function_name: &str, index: usize) -> Str
     format!("{}_ASSERT_CLEANUP_{index}", sanitize(function_name))
 }
 
+/// Checks whether the label is the one of the transition that represents the unwind path of an assert terminator,
+/// i.e. the assertion failed.
+#[inline]
+pub fn is_assert_cleanup_label(label: &str) -> bool {
+    label
+        .rsplit_once("_ASSERT_CLEANUP_")
+        .is_some_and(|(_, index)| index.parse::<usize>().is_ok())
+}
+
 /// Label of the transition that represents the `Unreachable` terminator.
 #[inline]
 pub fn unreachable_transition_label(function_name: &str, index: usize) -> String {
     format!("{}_UNREACHABLE_{index}", sanitize(function_name))
 }
+
+/// Label of the transition that over-approximates an unsupported terminator
+/// by jumping to one of its possible targets.
+#[inline]
+pub fn opaque_transition_label(function_name: &str, from_index: usize, to_index: usize) -> String {
+    format!(
+        "{}_OPAQUE_FROM_BB{from_index}_TO_BB{to_index}",
+        sanitize(function_name)
+    )
+}
+
+/// Label of the transition that over-approximates an unsupported terminator
+/// that returns from the function.
+#[inline]
+pub fn opaque_return_transition_label(function_name: &str, index: usize) -> String {
+    format!("{}_OPAQUE_RETURN_{index}", sanitize(function_name))
+}
+
+/// Label of the transition that over-approximates an unsupported terminator without targets.
+#[inline]
+pub fn opaque_diverging_transition_label(function_name: &str, index: usize) -> String {
+    format!("{}_OPAQUE_DIVERGING_{index}", sanitize(function_name))
+}
@@ -36,3 +36,25 @@ pub fn transition_labels(index: usize) -> (String, String, String) {
 pub fn wait_skip_label(index: usize) -> String {
     format!("CONDVAR_{index}_WAIT_SKIP")
 }
+
+/// Labels of the two places added to record whether a signal on the `Condvar` was lost:
+/// the first one is marked until the first signal is lost, the second one afterwards.
+#[inline]
+pub fn signal_lost_place_labels(index: usize) -> (String, String) {
+    (
+        format!("CONDVAR_{index}_SIGNAL_NOT_LOST"),
+        format!("CONDVAR_{index}_SIGNAL_LOST"),
+    )
+}
+
+/// Label of the place added to record that a thread waits on the `Condvar`.
+#[inline]
+pub fn waiting_place_label(index: usize) -> String {
+    format!("CONDVAR_{index}_WAITING")
+}
+
+/// Label of the copy of the lost signal transition that fires once a signal was already lost.
+#[inline]
+pub fn lost_signal_repeated_label(index: usize) -> String {
+    format!("CONDVAR_{index}_LOST_SIGNAL_REPEATED")
+}
@@ -29,6 +29,12 @@ pub fn return_transition_label(function_name: &str) -> String {
     format!("{}_RETURN", sanitize(function_name))
 }
 
+/// Checks whether the transition with the given label is the return statement of a function.
+#[inline]
+pub fn is_return_transition_label(label: &str) -> bool {
+    label.ends_with("_RETURN")
+}
+
 /// Label of the transitions for a call to a foreign function.
 #[inline]
 pub fn foreign_call_transition_labels(function_name: &str, index: usize) -> (String, String) {
@@ -49,3 +55,20 @@ pub fn diverging_call_transition_label(function_name: &str) -> String {
 pub fn panic_transition_label(function_name: &str) -> String {
     format!("{}_PANIC", sanitize(function_name))
 }
+
+/// Checks whether the label is the one of the transition that represents a call to a `panic!`.
+#[inline]
+pub fn is_panic_transition_label(label: &str) -> bool {
+    label.ends_with("_PANIC")
+}
+
+/// Checks whether the label is the one of the transition that represents the unwind path
+/// of a call to the given function, i.e. the function panicked.
+#[inline]
+pub fn is_call_unwind_label(label: &str, function_name: &str) -> bool {
+    label
+        .strip_prefix(&sanitize(function_name))
+        .and_then(|rest| rest.strip_prefix('_'))
+        .and_then(|rest| rest.strip_suffix("_CALL_UNWIND"))
+        .is_some_and(|index| index.parse::<usize>().is_ok())
+}
@@ -0,0 +1,41 @@
+//! Submodule that defines the naming of places and transitions in the Petri net
+//! that track the value of boolean locals, added only to refine spurious witnesses.
+//!
+//! These functions are called every time that a new place or transition
+//! in the resulting net is created.
+//! This ensures a consistent naming and provides a centralized place to tweak
+//! the configuration if needed.
+//!
+//! All functions listed here should have an `#[inline]` attribute for performance reasons.
+//! See the reference for more information:
+//! <https://doc.rust-lang.org/stable/reference/attributes/codegen.html>
+
+use super::sanitize;
+
+/// Labels of the three places that hold the value of a boolean local: `true`, `false` or unknown.
+#[inline]
+pub fn value_place_labels(function_name: &str, local: usize) -> (String, String, String) {
+    let function_name = sanitize(function_name);
+    (
+        format!("{function_name}_LOCAL_{local}_TRUE"),
+        format!("{function_name}_LOCAL_{local}_FALSE"),
+        format!("{function_name}_LOCAL_{local}_UNKNOWN"),
+    )
+}
+
+/// Labels of the two transitions that choose the value of a boolean local whose value is unknown.
+#[inline]
+pub fn choose_transition_labels(function_name: &str, local: usize) -> (String, String) {
+    let function_name = sanitize(function_name);
+    (
+        format!("{function_name}_LOCAL_{local}_CHOOSE_TRUE"),
+        format!("{function_name}_LOCAL_{local}_CHOOSE_FALSE"),
+    )
+}
+
+/// Label of the copy of a transition that assigns boolean locals,
+/// for one combination of the values that the locals had before.
+#[inline]
+pub fn assignment_copy_label(transition_label: &str, combination: usize) -> String {
+    format!("{transition_label}_ASSIGN_{combination}")
+}
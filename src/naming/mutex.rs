@@ -25,3 +25,35 @@ pub fn condition_place_labels(index: usize) -> (String, String) {
         format!("MUTEX_{index}_CONDITION_SET"),
     )
 }
+
+/// Label of the place added to record that the thread with the given index holds the `Mutex`.
+/// The main thread has no index.
+#[inline]
+pub fn held_by_label(index: usize, thread: Option<usize>) -> String {
+    thread.map_or_else(
+        || format!("MUTEX_{index}_HELD_BY_MAIN"),
+        |thread| format!("MUTEX_{index}_HELD_BY_THREAD_{thread}"),
+    )
+}
+
+/// Label of the place added to record that none of the threads tracked holds the `Mutex`.
+#[inline]
+pub fn not_held_label(index: usize) -> String {
+    format!("MUTEX_{index}_NOT_HELD")
+}
+
+/// Label of the copy of a transition that unlocks the `Mutex` while the thread with the given index holds it.
+/// The main thread has no index.
+#[inline]
+pub fn release_copy_label(transition: &str, index: usize, thread: Option<usize>) -> String {
+    format!("{transition}_RELEASES_{}", held_by_label(index, thread))
+}
+
+/// Returns the label of the original transition if the label is the one of a copy
+/// created by `release_copy_label`.
+#[inline]
+pub fn release_copy_original(label: &str) -> Option<&str> {
+    label
+        .split_once("_RELEASES_MUTEX_")
+        .map(|(transition, _)| transition)
+}
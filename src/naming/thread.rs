@@ -22,3 +22,40 @@ pub fn start_place_label(index: usize) -> String {
 pub fn end_place_label(index: usize) -> String {
     format!("THREAD_{index}_END")
 }
+
+/// Returns the index of the thread if the label is the one created by `start_place_label`.
+#[inline]
+pub fn started_thread(label: &str) -> Option<usize> {
+    label
+        .strip_prefix("THREAD_")?
+        .strip_suffix("_START")?
+        .parse()
+        .ok()
+}
+
+/// Returns the index of the thread if the label is the one created by `end_place_label`.
+#[inline]
+pub fn ended_thread(label: &str) -> Option<usize> {
+    label
+        .strip_prefix("THREAD_")?
+        .strip_suffix("_END")?
+        .parse()
+        .ok()
+}
+
+/// Label of the place that models the end state of the thread with the given index.
+/// The main thread has no index, its end state is the normal program end state.
+#[inline]
+pub fn thread_end_place_label(thread: Option<usize>) -> String {
+    thread.map_or_else(|| super::PROGRAM_END.to_string(), end_place_label)
+}
+
+/// Label of the place added to record that the thread with the given index returned normally, i.e. without a `panic!`.
+/// The main thread has no index.
+#[inline]
+pub fn returned_place_label(thread: Option<usize>) -> String {
+    thread.map_or_else(
+        || "MAIN_RETURNED".to_string(),
+        |index| format!("THREAD_{index}_RETURNED"),
+    )
+}
@@ -0,0 +1,171 @@
+//! Mapping from the places and transitions of the Petri net back to the source code.
+//!
+//! The labels of the net (e.g. `main_BB3` or `MUTEX_0`) identify the elements uniquely
+//! but do not say where in the code they come from.
+//! During the translation, every element is recorded together with the MIR location
+//! being translated when it was added to the net: the function, the basic block,
+//! the statement index, the span in the source code and the thread.
+//!
+//! Some elements are not created while translating a MIR location,
+//! e.g. `PROGRAM_START` or the places added in the postprocessing of condition variables.
+//! They are recorded without an origin.
+//!
+//! The mapping can be written as a JSON file next to the net.
+
+use std::collections::BTreeMap;
+
+use crate::data_structures::petri_net_interface::PetriNet;
+use crate::json;
+use crate::source_span::SourceSpan;
+
+/// The MIR location that originated an element of the Petri net.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetElementOrigin {
+    /// The name of the function, as returned by `rustc_middle::ty::TyCtxt::def_path_str`.
+    pub function: String,
+    /// The definition ID of the function, formatted with `Debug`.
+    /// It is only stable within the same compiler session.
+    pub def_id: String,
+    /// The index of the thread being translated, `None` for the main thread.
+    pub thread: Option<usize>,
+    /// The index of the basic block in the MIR body of the function.
+    pub basic_block: usize,
+    /// The index of the statement in the basic block.
+    /// The terminator has the index following the last statement.
+    pub statement_index: usize,
+    /// The location in the source code, if the MIR location has one.
+    pub span: Option<SourceSpan>,
+}
+
+/// The origin of every place and transition in the Petri net, indexed by label.
+#[derive(Debug, Default, Clone)]
+pub struct NetMap {
+    places: BTreeMap<String, Option<NetElementOrigin>>,
+    transitions: BTreeMap<String, Option<NetElementOrigin>>,
+}
+
+impl NetMap {
+    /// Creates a new empty `NetMap`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the origin of the place with the given label.
+    /// Returns `None` if the place is unknown or has no origin.
+    #[must_use]
+    pub fn place_origin(&self, label: &str) -> Option<&NetElementOrigin> {
+        self.places.get(label).and_then(Option::as_ref)
+    }
+
+    /// Returns the origin of the transition with the given label.
+    /// Returns `None` if the transition is unknown or has no origin.
+    #[must_use]
+    pub fn transition_origin(&self, label: &str) -> Option<&NetElementOrigin> {
+        self.transitions.get(label).and_then(Option::as_ref)
+    }
+
+    /// Returns an iterator over the labels of the places and their origins.
+    /// The places are visited in alphabetical order.
+    pub fn places(&self) -> impl Iterator<Item = (&String, Option<&NetElementOrigin>)> {
+        self.places
+            .iter()
+            .map(|(label, origin)| (label, origin.as_ref()))
+    }
+
+    /// Returns an iterator over the labels of the transitions and their origins.
+    /// The transitions are visited in alphabetical order.
+    pub fn transitions(&self) -> impl Iterator<Item = (&String, Option<&NetElementOrigin>)> {
+        self.transitions
+            .iter()
+            .map(|(label, origin)| (label, origin.as_ref()))
+    }
+
+    /// Records the places and transitions of the net that are not in the mapping yet.
+    /// They are assigned the given origin.
+    pub(crate) fn record_new_elements(
+        &mut self,
+        net: &PetriNet,
+        origin: Option<&NetElementOrigin>,
+    ) {
+        // Checking the cardinality first avoids iterating over the whole net after every statement.
+        if net.get_cardinality_places() != self.places.len() {
+            for (place_ref, _) in net.places_iter() {
+                self.places
+                    .entry(place_ref.label().clone())
+                    .or_insert_with(|| origin.cloned());
+            }
+        }
+        if net.get_cardinality_transitions() != self.transitions.len() {
+            for (transition_ref, _) in net.transitions_iter() {
+                self.transitions
+                    .entry(transition_ref.label().clone())
+                    .or_insert_with(|| origin.cloned());
+            }
+        }
+    }
+
+    /// Replaces the origin of a transition that was already recorded.
+    /// Used for transitions created before the MIR location that actually fires them.
+    pub(crate) fn relocate_transition(&mut self, label: &str, origin: Option<&NetElementOrigin>) {
+        if let Some(recorded_origin) = self.transitions.get_mut(label) {
+            *recorded_origin = origin.cloned();
+        }
+    }
+
+    /// Writes the mapping in JSON format.
+    /// The top-level object contains two objects, `places` and `transitions`,
+    /// which map every label to its origin or to `null`.
+    ///
+    /// # Errors
+    ///
+    /// If writing to the writer fails, then the function returns an error.
+    pub fn to_json<T: std::io::Write>(&self, writer: &mut T) -> Result<(), std::io::Error> {
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"places\": {{")?;
+        write_elements(writer, &self.places)?;
+        writeln!(writer, "  }},")?;
+        writeln!(writer, "  \"transitions\": {{")?;
+        write_elements(writer, &self.transitions)?;
+        writeln!(writer, "  }}")?;
+        writeln!(writer, "}}")
+    }
+}
+
+/// Writes the entries of a JSON object that maps labels to origins, one per line.
+fn write_elements<T: std::io::Write>(
+    writer: &mut T,
+    elements: &BTreeMap<String, Option<NetElementOrigin>>,
+) -> Result<(), std::io::Error> {
+    let mut iter = elements.iter().peekable();
+    while let Some((label, origin)) = iter.next() {
+        write!(writer, "    {}: ", json::string(label))?;
+        match origin {
+            Some(origin) => write_origin(writer, origin)?,
+            None => write!(writer, "null")?,
+        }
+        if iter.peek().is_some() {
+            writeln!(writer, ",")?;
+        } else {
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes an origin as a JSON object in a single line.
+fn write_origin<T: std::io::Write>(
+    writer: &mut T,
+    origin: &NetElementOrigin,
+) -> Result<(), std::io::Error> {
+    write!(
+        writer,
+        "{{\"function\": {}, \"def_id\": {}, \"thread\": {}, \"basic_block\": {}, \"statement_index\": {}, \"span\": {}}}",
+        json::string(&origin.function),
+        json::string(&origin.def_id),
+        json::optional(origin.thread),
+        origin.basic_block,
+        origin.statement_index,
+        json::span(origin.span.as_ref())
+    )
+}
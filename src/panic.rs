@@ -0,0 +1,179 @@
+//! Module that prepares the Petri net to check whether a panic is reachable.
+//!
+//! A panic ends in the place `PROGRAM_PANIC` for the main thread and in the end place of the thread for a spawned thread.
+//! Since the net assumes that every call may unwind, both places are reachable in almost every program.
+//! Instead, the panics are recognized by the transitions that start them:
+//! - A call to a function that starts a panic, e.g. `core::panicking::panic_fmt` or `core::panicking::assert_failed`.
+//! - A failed assertion of an assert terminator, e.g. an overflow or an index out of bounds.
+//! - The unwind path of a call to `unwrap` or `expect` on a `Result` or an `Option`.
+//!
+//! The net does not record whether one of them fired, so the property is checked on an instrumented copy
+//! where every such transition also puts a token in the place `PANIC_STARTED`.
+//! A call to `unwrap` or `expect` without a cleanup block has no unwind path in the net,
+//! so the copy adds one that starts from the same place as the call.
+//! The property `Property::Panic` is then that the place `PANIC_STARTED` can be marked.
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, add_arc_place_transition, add_arc_transition_place, copy_net,
+};
+use crate::naming::PANIC_STARTED;
+use crate::naming::basic_block::is_assert_cleanup_label;
+use crate::naming::function::{is_call_unwind_label, is_panic_transition_label};
+
+/// The functions whose unwind path is a panic, since they panic on an `Err` or a `None` value.
+const UNWRAP_FUNCTIONS: [&str; 4] = [
+    "std::result::Result::<T, E>::unwrap",
+    "std::result::Result::<T, E>::expect",
+    "std::option::Option::<T>::unwrap",
+    "std::option::Option::<T>::expect",
+];
+
+/// The kind of transition that starts a panic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicCause {
+    /// A call to a function that starts a panic, e.g. `panic!`, `assert!` or `unreachable!`.
+    PanicCall,
+    /// A failed assertion inserted by the compiler, e.g. an overflow check or a bounds check.
+    FailedAssertion,
+    /// A call to `unwrap` or `expect` on an `Err` or a `None` value.
+    Unwrap,
+}
+
+impl PanicCause {
+    /// Returns the cause of the panic started by the transition with the given label,
+    /// or `None` if the transition does not start a panic.
+    #[must_use]
+    pub fn from_label(label: &str) -> Option<Self> {
+        if is_panic_transition_label(label) {
+            Some(Self::PanicCall)
+        } else if is_assert_cleanup_label(label) {
+            Some(Self::FailedAssertion)
+        } else if UNWRAP_FUNCTIONS
+            .iter()
+            .any(|function_name| is_call_unwind_label(label, function_name))
+        {
+            Some(Self::Unwrap)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for PanicCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PanicCall => write!(f, "panic_call"),
+            Self::FailedAssertion => write!(f, "failed_assertion"),
+            Self::Unwrap => write!(f, "unwrap"),
+        }
+    }
+}
+
+/// Returns the labels of the transitions that start a panic, in the order of the net.
+#[must_use]
+pub fn panic_transitions(net: &PetriNet) -> Vec<String> {
+    net.transitions_iter()
+        .map(|(transition_ref, _)| transition_ref.label())
+        .filter(|label| PanicCause::from_label(label).is_some())
+        .cloned()
+        .collect()
+}
+
+/// Copies the net and adds the place `PANIC_STARTED`, which receives a token from every transition that starts a panic.
+/// `PANIC_STARTED` only receives tokens and never disables a transition.
+/// The unwind paths added for the calls to `unwrap` or `expect` without a cleanup block
+/// only consume the token of the thread, so the witness path of the instrumented net,
+/// after `restore_witness_path`, is also a path of the original net.
+#[must_use]
+pub fn instrument(net: &PetriNet) -> PetriNet {
+    let (mut copy, place_refs, mut transition_refs) = copy_net(net);
+    let panic_started = copy.add_place(PANIC_STARTED);
+    for (transition_ref, transition) in net.transitions_iter() {
+        let unwind_label = format!("{}_UNWIND", transition_ref.label());
+        if PanicCause::from_label(&unwind_label) != Some(PanicCause::Unwrap)
+            || transition_refs.contains_key(&unwind_label)
+        {
+            continue;
+        }
+        let unwind = copy.add_transition(&unwind_label);
+        for place_ref in transition.get_preset() {
+            add_arc_place_transition(&mut copy, &place_refs[place_ref.label()], &unwind);
+        }
+        transition_refs.insert(unwind_label, unwind);
+    }
+    for label in panic_transitions(&copy) {
+        add_arc_transition_place(&mut copy, &transition_refs[&label], &panic_started);
+    }
+    copy
+}
+
+/// Replaces the unwind paths that `instrument` added to the net in a witness path
+/// by the calls to `unwrap` or `expect` they start from, so that the path can be replayed on the original net.
+/// The cause of the panic must be taken from the witness path before it is restored.
+#[must_use]
+pub fn restore_witness_path(witness_path: Vec<String>, net: &PetriNet) -> Vec<String> {
+    witness_path
+        .into_iter()
+        .map(|label| {
+            let added = PanicCause::from_label(&label) == Some(PanicCause::Unwrap)
+                && !net
+                    .transitions_iter()
+                    .any(|(transition_ref, _)| *transition_ref.label() == label);
+            match label.strip_suffix("_UNWIND") {
+                Some(call) if added => call.to_string(),
+                _ => label,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod panic_tests {
+    use super::*;
+    use crate::test_nets::{add_marked_place, connect};
+
+    #[test]
+    fn panic_causes_are_recognized_by_the_label() {
+        assert_eq!(
+            PanicCause::from_label("main_PANIC"),
+            Some(PanicCause::PanicCall)
+        );
+        assert_eq!(
+            PanicCause::from_label("main_ASSERT_CLEANUP_3"),
+            Some(PanicCause::FailedAssertion)
+        );
+        assert_eq!(
+            PanicCause::from_label("std_result_Result_unwrap_0_CALL_UNWIND"),
+            Some(PanicCause::Unwrap)
+        );
+        assert_eq!(
+            PanicCause::from_label("std_option_Option_T_expect_2_CALL_UNWIND"),
+            Some(PanicCause::Unwrap)
+        );
+        assert_eq!(PanicCause::from_label("std_io__print_0_CALL_UNWIND"), None);
+        assert_eq!(PanicCause::from_label("main_ASSERT_3"), None);
+        assert_eq!(PanicCause::from_label("main_UNWIND_7"), None);
+    }
+
+    #[test]
+    fn unwrap_without_cleanup_gets_an_unwind_path_in_the_copy_only() {
+        let mut net = PetriNet::new();
+        let start = add_marked_place(&mut net, "main_BB0", 1);
+        let end = net.add_place("main_BB1");
+        connect(&mut net, &start, &end, "std_option_Option_T_unwrap_0_CALL");
+
+        let copy = instrument(&net);
+        assert!(panic_transitions(&net).is_empty());
+        assert_eq!(
+            panic_transitions(&copy),
+            vec!["std_option_Option_T_unwrap_0_CALL_UNWIND".to_string()]
+        );
+        assert_eq!(
+            restore_witness_path(
+                vec!["std_option_Option_T_unwrap_0_CALL_UNWIND".to_string()],
+                &net
+            ),
+            vec!["std_option_Option_T_unwrap_0_CALL".to_string()]
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Structural reduction of the Petri net before the model checking.
+//!
+//! The translation produces long chains of transitions like `BB -> GOTO -> BB -> CALL -> BB`
+//! that only move the token of one thread forward and carry no synchronization.
+//! Every transition of such a chain multiplies the number of interleavings that the model checker explores.
+//!
+//! The reduction applies the following rules, which preserve the reachability of deadlocks,
+//! until none of them applies anymore:
+//!
+//! - Agglomeration through an unmarked place `p` whose consumers only take tokens from `p`.
+//!   Each producer of `p` is fused with the consumer that fires right after it.
+//!   With a single producer and a single consumer, this is the fusion of a series of transitions.
+//!   With several producers, the consumer is agglomerated into each of them (pre-agglomeration).
+//!   With several consumers, the producer is agglomerated into each of them (post-agglomeration).
+//! - Removal of redundant places: places without consumers
+//!   and places with the same producers, consumers and marking as another place.
+//! - Removal of duplicate transitions with the same preset and postset as another transition.
+//!
+//! The places that model synchronization primitives, threads and the program state are never removed.
+//! The rules apply to every other place. The reduction runs on the translated net, before any instrumentation,
+//! so these are the places of basic blocks.
+//! The transitions connected to them keep their label and their arcs to these places.
+//!
+//! The reduced net contains a subset of the labels of the original net.
+//! A fused transition keeps the label of one of the original transitions
+//! and the `Reduction` records the sequence of original transitions that it stands for.
+//! This allows to expand a witness path of the reduced net to a witness path of the original net.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use log::info;
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, add_arc_place_transition, add_arc_transition_place,
+};
+use crate::naming::{PROGRAM_END, PROGRAM_PANIC, PROGRAM_START, is_synchronization_place};
+
+/// The mapping of a reduced net back to the original net.
+pub struct Reduction {
+    /// The net before the reduction.
+    original: PetriNet,
+    /// For every fused transition of the reduced net, the labels of the original transitions
+    /// in the order in which they fire. Transitions that were not fused are not included.
+    expansions: HashMap<String, Vec<String>>,
+}
+
+impl Reduction {
+    /// Returns the net before the reduction.
+    #[must_use]
+    pub const fn original_net(&self) -> &PetriNet {
+        &self.original
+    }
+
+    /// Expands a witness path of the reduced net to a witness path of the original net.
+    /// Every fused transition is replaced by the sequence of original transitions that it stands for.
+    #[must_use]
+    pub fn expand(&self, witness_path: &[String]) -> Vec<String> {
+        witness_path
+            .iter()
+            .flat_map(|label| {
+                self.expansions
+                    .get(label)
+                    .cloned()
+                    .unwrap_or_else(|| vec![label.clone()])
+            })
+            .collect()
+    }
+}
+
+/// Reduces the Petri net with the rules described in the module documentation.
+/// Returns the reduced net and the mapping back to the original net, which is moved into the mapping.
+#[must_use]
+pub fn reduce(net: PetriNet) -> (PetriNet, Reduction) {
+    let mut work_net = WorkNet::new(&net);
+    loop {
+        let places: Vec<String> = work_net.marking.keys().cloned().collect();
+        let mut changed = false;
+        for place in places {
+            if work_net.marking.contains_key(&place) {
+                changed |= work_net.agglomerate(&place);
+            }
+        }
+        changed |= work_net.remove_redundant_places();
+        changed |= work_net.remove_duplicate_transitions();
+        if !changed {
+            break;
+        }
+    }
+
+    let reduced = work_net.to_petri_net();
+    info!(
+        "Reduced the net from {} places and {} transitions to {} places and {} transitions",
+        net.get_cardinality_places(),
+        net.get_cardinality_transitions(),
+        reduced.get_cardinality_places(),
+        reduced.get_cardinality_transitions()
+    );
+    let expansions = work_net
+        .expansions
+        .into_iter()
+        .filter(|(label, expansion)| expansion.len() > 1 || expansion[0] != *label)
+        .collect();
+    (
+        reduced,
+        Reduction {
+            original: net,
+            expansions,
+        },
+    )
+}
+
+/// The labels of the places connected to a transition.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Arcs {
+    preset: BTreeSet<String>,
+    postset: BTreeSet<String>,
+}
+
+/// A representation of the net indexed by label that supports removing places and transitions.
+struct WorkNet {
+    /// The initial marking of every place.
+    marking: BTreeMap<String, usize>,
+    /// The arcs of every transition.
+    transitions: BTreeMap<String, Arcs>,
+    /// The transitions that add tokens to every place.
+    producers: BTreeMap<String, BTreeSet<String>>,
+    /// The transitions that take tokens from every place.
+    consumers: BTreeMap<String, BTreeSet<String>>,
+    /// The original transitions that every transition stands for, in firing order.
+    expansions: BTreeMap<String, Vec<String>>,
+}
+
+impl WorkNet {
+    /// Copies the places, transitions and arcs of the Petri net.
+    fn new(net: &PetriNet) -> Self {
+        let mut work_net = Self {
+            marking: BTreeMap::new(),
+            transitions: BTreeMap::new(),
+            producers: BTreeMap::new(),
+            consumers: BTreeMap::new(),
+            expansions: BTreeMap::new(),
+        };
+        for (place_ref, place) in net.places_iter() {
+            let label = place_ref.label().clone();
+            work_net.marking.insert(label.clone(), place.marking());
+            work_net.producers.insert(label.clone(), BTreeSet::new());
+            work_net.consumers.insert(label, BTreeSet::new());
+        }
+        for (transition_ref, transition) in net.transitions_iter() {
+            let label = transition_ref.label().clone();
+            let arcs = Arcs {
+                preset: transition
+                    .get_preset()
+                    .iter()
+                    .map(|place_ref| place_ref.label().clone())
+                    .collect(),
+                postset: transition
+                    .get_postset()
+                    .iter()
+                    .map(|place_ref| place_ref.label().clone())
+                    .collect(),
+            };
+            work_net.add_transition(label.clone(), arcs, vec![label]);
+        }
+        work_net
+    }
+
+    /// Checks whether the place must be kept in the reduced net.
+    fn is_protected(place: &str) -> bool {
+        is_synchronization_place(place)
+            || place == PROGRAM_START
+            || place == PROGRAM_END
+            || place == PROGRAM_PANIC
+    }
+
+    /// Checks whether the transition takes tokens from or adds tokens to a synchronization place.
+    fn touches_synchronization(&self, transition: &str) -> bool {
+        let arcs = &self.transitions[transition];
+        arcs.preset
+            .iter()
+            .chain(&arcs.postset)
+            .any(|place| is_synchronization_place(place))
+    }
+
+    fn add_transition(&mut self, label: String, arcs: Arcs, expansion: Vec<String>) {
+        for place in &arcs.preset {
+            self.consumers
+                .get_mut(place)
+                .expect("BUG: The place in the preset should exist")
+                .insert(label.clone());
+        }
+        for place in &arcs.postset {
+            self.producers
+                .get_mut(place)
+                .expect("BUG: The place in the postset should exist")
+                .insert(label.clone());
+        }
+        self.expansions.insert(label.clone(), expansion);
+        self.transitions.insert(label, arcs);
+    }
+
+    /// Removes the transition and returns its arcs and its expansion.
+    fn remove_transition(&mut self, label: &str) -> (Arcs, Vec<String>) {
+        let arcs = self
+            .transitions
+            .remove(label)
+            .expect("BUG: The transition to remove should exist");
+        for place in &arcs.preset {
+            if let Some(consumers) = self.consumers.get_mut(place) {
+                consumers.remove(label);
+            }
+        }
+        for place in &arcs.postset {
+            if let Some(producers) = self.producers.get_mut(place) {
+                producers.remove(label);
+            }
+        }
+        let expansion = self
+            .expansions
+            .remove(label)
+            .expect("BUG: The transition to remove should have an expansion");
+        (arcs, expansion)
+    }
+
+    /// Removes the place and all the arcs connected to it.
+    fn remove_place(&mut self, label: &str) {
+        self.marking.remove(label);
+        for transition in self.producers.remove(label).unwrap_or_default() {
+            if let Some(arcs) = self.transitions.get_mut(&transition) {
+                arcs.postset.remove(label);
+            }
+        }
+        for transition in self.consumers.remove(label).unwrap_or_default() {
+            if let Some(arcs) = self.transitions.get_mut(&transition) {
+                arcs.preset.remove(label);
+            }
+        }
+    }
+
+    /// Fuses every producer of the place with every consumer of the place and removes the place.
+    /// Returns whether the rule applied.
+    ///
+    /// The rule applies if the place is an unmarked place of a basic block,
+    /// every consumer takes tokens only from this place and does not touch a synchronization place,
+    /// and there is only one producer or only one consumer.
+    /// A token added to the place can then always be consumed right away,
+    /// so no reachable deadlock has a token in the place.
+    fn agglomerate(&mut self, place: &str) -> bool {
+        if Self::is_protected(place) || self.marking[place] > 0 {
+            return false;
+        }
+        let producers: Vec<String> = self.producers[place].iter().cloned().collect();
+        let consumers: Vec<String> = self.consumers[place].iter().cloned().collect();
+        if producers.is_empty()
+            || consumers.is_empty()
+            || (producers.len() > 1 && consumers.len() > 1)
+        {
+            return false;
+        }
+        for consumer in &consumers {
+            let arcs = &self.transitions[consumer];
+            if arcs.preset.len() != 1
+                || arcs.postset.contains(place)
+                || self.touches_synchronization(consumer)
+            {
+                return false;
+            }
+        }
+        for producer in &producers {
+            let arcs = &self.transitions[producer];
+            if arcs.preset.contains(place) {
+                return false;
+            }
+            // Duplicating a producer would duplicate its label and its synchronization.
+            if consumers.len() > 1 && self.touches_synchronization(producer) {
+                return false;
+            }
+            // The arcs have multiplicity one, a place cannot receive two tokens from the fused transition.
+            let overlap = consumers.iter().any(|consumer| {
+                self.transitions[consumer]
+                    .postset
+                    .iter()
+                    .any(|other| other != place && arcs.postset.contains(other))
+            });
+            if overlap {
+                return false;
+            }
+        }
+
+        let consumers: Vec<(String, Arcs, Vec<String>)> = consumers
+            .into_iter()
+            .map(|consumer| {
+                let (arcs, expansion) = self.remove_transition(&consumer);
+                (consumer, arcs, expansion)
+            })
+            .collect();
+        let single_consumer = consumers.len() == 1;
+        for producer in producers {
+            let (producer_arcs, producer_expansion) = self.remove_transition(&producer);
+            for (consumer, consumer_arcs, consumer_expansion) in &consumers {
+                // The label must stay unique: it is taken from the transition that is not duplicated.
+                let label = if single_consumer {
+                    producer.clone()
+                } else {
+                    consumer.clone()
+                };
+                let mut postset = producer_arcs.postset.clone();
+                postset.remove(place);
+                postset.extend(consumer_arcs.postset.iter().cloned());
+                let arcs = Arcs {
+                    preset: producer_arcs.preset.clone(),
+                    postset,
+                };
+                let mut expansion = producer_expansion.clone();
+                expansion.extend(consumer_expansion.iter().cloned());
+                self.add_transition(label, arcs, expansion);
+            }
+        }
+        self.remove_place(place);
+        true
+    }
+
+    /// Removes the unprotected places without consumers, since they never enable a transition,
+    /// and the places with the same producers, consumers and marking as another place.
+    /// Returns whether a place was removed.
+    fn remove_redundant_places(&mut self) -> bool {
+        let mut seen = BTreeSet::new();
+        let mut redundant = Vec::new();
+        for (place, marking) in &self.marking {
+            if Self::is_protected(place) {
+                continue;
+            }
+            let consumers = &self.consumers[place];
+            if consumers.is_empty() || !seen.insert((&self.producers[place], consumers, *marking)) {
+                redundant.push(place.clone());
+            }
+        }
+        for place in &redundant {
+            self.remove_place(place);
+        }
+        !redundant.is_empty()
+    }
+
+    /// Removes the transitions with the same preset and postset as another transition.
+    /// Transitions connected to a synchronization place are kept.
+    /// Returns whether a transition was removed.
+    fn remove_duplicate_transitions(&mut self) -> bool {
+        let mut seen = BTreeSet::new();
+        let mut duplicates = Vec::new();
+        for (transition, arcs) in &self.transitions {
+            if !self.touches_synchronization(transition) && !seen.insert(arcs) {
+                duplicates.push(transition.clone());
+            }
+        }
+        for transition in &duplicates {
+            self.remove_transition(transition);
+        }
+        !duplicates.is_empty()
+    }
+
+    /// Builds a new Petri net with the remaining places, transitions and arcs.
+    fn to_petri_net(&self) -> PetriNet {
+        let mut net = PetriNet::new();
+        let mut place_refs = HashMap::new();
+        for (label, marking) in &self.marking {
+            let place_ref = net.add_place(label);
+            if *marking > 0 {
+                net.add_token(&place_ref, *marking)
+                    .expect("BUG: Adding the initial tokens to an empty place should not fail");
+            }
+            place_refs.insert(label, place_ref);
+        }
+        for (label, arcs) in &self.transitions {
+            let transition_ref = net.add_transition(label);
+            for place in &arcs.preset {
+                add_arc_place_transition(&mut net, &place_refs[place], &transition_ref);
+            }
+            for place in &arcs.postset {
+                add_arc_transition_place(&mut net, &transition_ref, &place_refs[place]);
+            }
+        }
+        net
+    }
+}
+
+#[cfg(test)]
+mod reduction_tests {
+    use super::*;
+    use crate::model_checker::native;
+    use crate::test_nets::{add_marked_place, connect, net_with_program_places};
+
+    #[test]
+    fn chain_of_transitions_is_fused_into_one() {
+        let (mut net, program_start, program_end) = net_with_program_places();
+        let bb1 = net.add_place("main_BB1");
+        let bb2 = net.add_place("main_BB2");
+        connect(&mut net, &program_start, &bb1, "main_GOTO_0");
+        connect(&mut net, &bb1, &bb2, "main_GOTO_1");
+        connect(&mut net, &bb2, &program_end, "main_RETURN");
+
+        let (reduced, reduction) = reduce(net);
+
+        assert_eq!(reduced.get_cardinality_places(), 3);
+        assert_eq!(reduced.get_cardinality_transitions(), 1);
+        assert_eq!(
+            reduction.expand(&["main_GOTO_0".to_string()]),
+            vec!["main_GOTO_0", "main_GOTO_1", "main_RETURN"]
+        );
+    }
+
+    #[test]
+    fn transitions_of_a_mutex_are_kept() {
+        let (mut net, program_start, program_end) = net_with_program_places();
+        let mutex = add_marked_place(&mut net, "MUTEX_0", 1);
+        let bb1 = net.add_place("main_BB1");
+        let bb2 = net.add_place("main_BB2");
+        // Lock the mutex twice: the second lock never succeeds.
+        let first_lock = net.add_transition("LOCK_0");
+        add_arc_place_transition(&mut net, &program_start, &first_lock);
+        add_arc_place_transition(&mut net, &mutex, &first_lock);
+        add_arc_transition_place(&mut net, &first_lock, &bb1);
+        connect(&mut net, &bb1, &bb2, "main_GOTO_1");
+        let second_lock = net.add_transition("LOCK_1");
+        add_arc_place_transition(&mut net, &bb2, &second_lock);
+        add_arc_place_transition(&mut net, &mutex, &second_lock);
+        add_arc_transition_place(&mut net, &second_lock, &program_end);
+
+        let (reduced, reduction) = reduce(net);
+
+        let labels: Vec<&String> = reduced
+            .transitions_iter()
+            .map(|(transition_ref, _)| transition_ref.label())
+            .collect();
+        assert_eq!(labels, vec!["LOCK_0", "LOCK_1"]);
+        let witness_path = native::check_deadlock(&reduced, false)
+            .witness_path
+            .expect("The second lock should deadlock");
+        assert_eq!(
+            reduction.expand(&witness_path),
+            vec!["LOCK_0", "main_GOTO_1"]
+        );
+    }
+
+    #[test]
+    fn branches_to_the_same_place_are_merged() {
+        let (mut net, program_start, program_end) = net_with_program_places();
+        let bb1 = net.add_place("main_BB1");
+        let bb2 = net.add_place("main_BB2");
+        connect(&mut net, &program_start, &bb1, "main_GOTO_0");
+        connect(&mut net, &bb1, &bb2, "main_SWITCH_INT_FROM_BB1_TO_BB2");
+        connect(&mut net, &bb1, &bb2, "main_SWITCH_INT_FROM_BB1_TO_BB3");
+        connect(&mut net, &bb2, &program_end, "main_RETURN");
+
+        let (reduced, _) = reduce(net);
+
+        assert_eq!(reduced.get_cardinality_transitions(), 1);
+        assert_eq!(native::check_deadlock(&reduced, false).witness_path, None);
+    }
+}
@@ -0,0 +1,534 @@
+//! Module that refines the Petri net when a witness path is spurious because of the conditions of the branches.
+//!
+//! Every `SwitchInt` terminator becomes a nondeterministic choice between its targets in the net,
+//! so the model checker may find a witness that takes contradicting branches,
+//! e.g. `if flag { drop(guard) } ... if flag { a.lock() }` where the first `if` is not taken and the second one is.
+//! During the translation, the branches on boolean locals and the assignments to them are recorded in `BranchConditions`.
+//!
+//! `spurious_locals` replays a witness path and returns the boolean locals whose assumed values contradict
+//! each other or a constant assigned before. If there are any, the witness is spurious
+//! and the locals are tracked in an instrumented copy of the net:
+//! - The places `{function}_LOCAL_{i}_TRUE`, `{function}_LOCAL_{i}_FALSE` and `{function}_LOCAL_{i}_UNKNOWN`
+//!   hold the value of the local, starting with a token in the last one.
+//!   The transitions `{function}_LOCAL_{i}_CHOOSE_TRUE` and `{function}_LOCAL_{i}_CHOOSE_FALSE` pick an unknown value.
+//! - The transitions of a branch on the local test the place of the value that they assume.
+//! - The transitions that leave a basic block assigning the local move the token to the place of the new value.
+//!   Since the value before is not known, the transition gets a copy for each combination of the values before.
+//!
+//! The model checker then runs again on the instrumented net, until the witness is consistent or no deadlock is left.
+//! Every round tracks at least one more local, so the refinement terminates.
+//! A local is not tracked if a basic block would then assign more than `MAX_TRACKED_LOCALS_PER_BLOCK` tracked locals,
+//! since the number of copies grows exponentially with them. A witness that only such a local refutes is kept.
+//! `Refinement` keeps the locals tracked across the rounds, also when every reachable deadlock is enumerated.
+//! Only constants are assigned precisely. Any other value, e.g. the result of a comparison or of a call,
+//! makes the local unknown again, which keeps the refined net an over-approximation of the program.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use log::{info, warn};
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, PlaceRef, add_arc_place_transition, add_arc_transition_place, copy_net,
+};
+use crate::naming::local::{assignment_copy_label, choose_transition_labels, value_place_labels};
+use crate::report::{BranchRefinement, TrackedLocal};
+use crate::source_span::SourceSpan;
+
+/// A local of type `bool` of a function translated to the net.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoolLocal {
+    /// The name of the function, with the index of the call as in the labels of its basic blocks.
+    pub function: String,
+    /// The index of the local in the MIR body of the function.
+    pub local: usize,
+}
+
+/// The value assigned to a boolean local.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assignment {
+    /// A constant value.
+    Constant(bool),
+    /// A value computed at runtime, e.g. the result of a comparison or of a call.
+    Unknown,
+}
+
+/// The branches of the net that depend on boolean locals and the assignments to them, recorded during the translation.
+#[derive(Debug, Default, Clone)]
+pub struct BranchConditions {
+    /// For every transition of a branch on a boolean local, the local and the value that the branch assumes.
+    branches: BTreeMap<String, (BoolLocal, bool)>,
+    /// For every place of a basic block, the boolean locals assigned in the block in order, with their value.
+    /// The assignments take effect when a transition of the terminator of the block fires.
+    assignments: BTreeMap<String, Vec<(BoolLocal, Assignment)>>,
+    /// The name of every boolean local in the source code and the location of its declaration.
+    declarations: BTreeMap<BoolLocal, (Option<String>, Option<SourceSpan>)>,
+}
+
+impl BranchConditions {
+    /// Creates a new empty `BranchConditions`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the transition with the given label is a branch that assumes the given value of the local.
+    pub fn record_branch(&mut self, transition: String, local: BoolLocal, value: bool) {
+        self.branches.insert(transition, (local, value));
+    }
+
+    /// Records an assignment to the local in the basic block whose place has the given label.
+    pub fn record_assignment(&mut self, place: String, local: BoolLocal, assignment: Assignment) {
+        self.assignments
+            .entry(place)
+            .or_default()
+            .push((local, assignment));
+    }
+
+    /// Records the name of the local in the source code and the location of its declaration, if not recorded yet.
+    pub fn record_declaration(
+        &mut self,
+        local: BoolLocal,
+        name: Option<String>,
+        span: Option<SourceSpan>,
+    ) {
+        self.declarations.entry(local).or_insert((name, span));
+    }
+
+    /// Returns the local with its name and the location of its declaration for the report.
+    #[must_use]
+    pub fn tracked_local(&self, local: &BoolLocal) -> TrackedLocal {
+        let (name, span) = self.declarations.get(local).cloned().unwrap_or_default();
+        TrackedLocal {
+            function: local.function.clone(),
+            local: local.local,
+            name,
+            span,
+        }
+    }
+
+    /// Returns the value assigned to every local when a transition with the given preset fires.
+    /// If a local is assigned several times, only the last value is kept.
+    fn assignments_of<'a>(
+        &'a self,
+        preset: impl IntoIterator<Item = &'a PlaceRef>,
+    ) -> Vec<(&'a BoolLocal, Assignment)> {
+        let mut assigned: Vec<(&BoolLocal, Assignment)> = Vec::new();
+        for place_ref in preset {
+            for (local, assignment) in self
+                .assignments
+                .get(place_ref.label())
+                .into_iter()
+                .flatten()
+            {
+                assigned.retain(|(other, _)| *other != local);
+                assigned.push((local, *assignment));
+            }
+        }
+        assigned
+    }
+}
+
+/// Replays the witness path and returns the boolean locals whose branches assume a value
+/// that contradicts the value known at that point, i.e. a constant assigned or a branch taken before.
+/// The witness is spurious if and only if the result is not empty.
+///
+/// This is only sound because every write to a recorded local is recorded during the translation,
+/// see the submodule `translator::branch`: between two recorded writes, the value of the local cannot change.
+#[must_use]
+pub fn spurious_locals(
+    conditions: &BranchConditions,
+    net: &PetriNet,
+    witness_path: &[String],
+) -> BTreeSet<BoolLocal> {
+    let presets: HashMap<&String, _> = net
+        .transitions_iter()
+        .map(|(transition_ref, transition)| (transition_ref.label(), transition.get_preset()))
+        .collect();
+    let mut values: HashMap<&BoolLocal, bool> = HashMap::new();
+    let mut spurious = BTreeSet::new();
+    for label in witness_path {
+        let Some(preset) = presets.get(label) else {
+            continue;
+        };
+        // The statements of the block execute before its terminator.
+        for (local, assignment) in conditions.assignments_of(*preset) {
+            match assignment {
+                Assignment::Constant(value) => values.insert(local, value),
+                Assignment::Unknown => values.remove(local),
+            };
+        }
+        if let Some((local, value)) = conditions.branches.get(label) {
+            if values.get(local).is_some_and(|known| known != value) {
+                spurious.insert(local.clone());
+            }
+            values.insert(local, *value);
+        }
+    }
+    spurious
+}
+
+/// The maximum number of tracked locals assigned in one basic block.
+/// A transition that leaves the block gets a copy for each of the `3^n` combinations of their values before.
+const MAX_TRACKED_LOCALS_PER_BLOCK: usize = 5;
+
+/// The state of the refinement across its rounds: the locals tracked so far
+/// and the number of spurious witnesses discarded.
+pub struct Refinement<'a> {
+    /// The branch conditions recorded during the translation.
+    conditions: &'a BranchConditions,
+    /// The net before any instrumentation, which the witness paths refer to.
+    net: &'a PetriNet,
+    /// The boolean locals tracked so far.
+    tracked: BTreeSet<BoolLocal>,
+    /// The number of spurious witnesses discarded so far.
+    spurious_witnesses: usize,
+}
+
+impl<'a> Refinement<'a> {
+    /// Creates a new refinement of the net that does not track any local yet.
+    #[must_use]
+    pub const fn new(conditions: &'a BranchConditions, net: &'a PetriNet) -> Self {
+        Self {
+            conditions,
+            net,
+            tracked: BTreeSet::new(),
+            spurious_witnesses: 0,
+        }
+    }
+
+    /// Checks the witness paths of the net against the branch conditions.
+    /// If some of them are spurious because of locals not tracked yet, the witnesses are discarded
+    /// and the net instrumented for every local tracked so far is returned, to be checked again.
+    /// Otherwise, the witnesses are kept and the function returns `None`.
+    /// A witness that is spurious only because of locals already tracked cannot be refined any further.
+    #[must_use]
+    pub fn refine(&mut self, witness_paths: &[Vec<String>]) -> Option<InstrumentedNet> {
+        let mut spurious = BTreeSet::new();
+        let mut spurious_witnesses = 0;
+        for witness_path in witness_paths {
+            let locals = spurious_locals(self.conditions, self.net, witness_path);
+            if !locals.is_empty() {
+                spurious_witnesses += 1;
+                spurious.extend(locals);
+            }
+        }
+        let mut added = false;
+        for local in spurious
+            .difference(&self.tracked)
+            .cloned()
+            .collect::<Vec<_>>()
+        {
+            let assigned = self.assigned_with(&local);
+            if assigned > MAX_TRACKED_LOCALS_PER_BLOCK {
+                warn!(
+                    "The boolean local `_{}` of `{}` is not tracked, a block would assign {assigned} tracked locals \
+                     and the limit is {MAX_TRACKED_LOCALS_PER_BLOCK}",
+                    local.local, local.function
+                );
+                continue;
+            }
+            self.tracked.insert(local);
+            added = true;
+        }
+        if !added {
+            if spurious_witnesses > 0 {
+                warn!(
+                    "{spurious_witnesses} witness path(s) still spurious after tracking the boolean locals, they are kept"
+                );
+            }
+            return None;
+        }
+        self.spurious_witnesses += spurious_witnesses;
+        info!(
+            "Discarding {spurious_witnesses} spurious witness path(s), tracking {} boolean local(s)...",
+            self.tracked.len()
+        );
+        Some(instrument(self.net, self.conditions, &self.tracked))
+    }
+
+    /// Returns the largest number of tracked locals assigned in a basic block that assigns the given local,
+    /// counting the local itself as tracked.
+    fn assigned_with(&self, local: &BoolLocal) -> usize {
+        self.net
+            .transitions_iter()
+            .map(|(_, transition)| self.conditions.assignments_of(transition.get_preset()))
+            .filter(|assigned| assigned.iter().any(|(other, _)| *other == local))
+            .map(|assigned| {
+                assigned
+                    .iter()
+                    .filter(|(other, _)| *other == local || self.tracked.contains(*other))
+                    .count()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the summary of the refinement for the report, or `None` if no witness was discarded.
+    #[must_use]
+    pub fn report(&self) -> Option<BranchRefinement> {
+        (self.spurious_witnesses > 0).then(|| BranchRefinement {
+            spurious_witnesses: self.spurious_witnesses,
+            tracked_locals: self
+                .tracked
+                .iter()
+                .map(|local| self.conditions.tracked_local(local))
+                .collect(),
+        })
+    }
+}
+
+/// The index of the place of an unknown value in the places of a local.
+const UNKNOWN: usize = 0;
+
+/// Returns the index of the place of the given value in the places of a local.
+const fn value_index(value: bool) -> usize {
+    if value { 1 } else { 2 }
+}
+
+/// A copy of the net that tracks the value of some boolean locals.
+pub struct InstrumentedNet {
+    /// The instrumented net.
+    pub net: PetriNet,
+    /// The original label of every transition added, or `None` if it has no counterpart in the original net.
+    originals: HashMap<String, Option<String>>,
+}
+
+impl InstrumentedNet {
+    /// Replaces the copies of the transitions in a witness path of the instrumented net by the original transitions
+    /// and removes the choices of unknown values, so that the path can be replayed on the original net.
+    #[must_use]
+    pub fn restore_witness_path(&self, witness_path: Vec<String>) -> Vec<String> {
+        witness_path
+            .into_iter()
+            .filter_map(|label| match self.originals.get(&label) {
+                Some(original) => original.clone(),
+                None => Some(label),
+            })
+            .collect()
+    }
+}
+
+/// Copies the net and adds the places and transitions that track the value of the given boolean locals.
+///
+/// # Panics
+///
+/// If the initial token cannot be added to the place of an unknown value, then the function panics.
+/// If a basic block assigns so many of the locals that the number of copies overflows, then the function panics.
+#[must_use]
+pub fn instrument(
+    net: &PetriNet,
+    conditions: &BranchConditions,
+    locals: &BTreeSet<BoolLocal>,
+) -> InstrumentedNet {
+    let (mut copy, place_refs, transition_refs) = copy_net(net);
+    let mut originals = HashMap::new();
+    let mut values: HashMap<&BoolLocal, [PlaceRef; 3]> = HashMap::new();
+    for local in locals {
+        let (true_label, false_label, unknown_label) =
+            value_place_labels(&local.function, local.local);
+        let places = [
+            copy.add_place(&unknown_label),
+            copy.add_place(&true_label),
+            copy.add_place(&false_label),
+        ];
+        copy.add_token(&places[UNKNOWN], 1)
+            .expect("BUG: Adding the initial token to an empty place should not fail");
+        let (choose_true, choose_false) = choose_transition_labels(&local.function, local.local);
+        for (label, value) in [(choose_true, true), (choose_false, false)] {
+            let choose = copy.add_transition(&label);
+            add_arc_place_transition(&mut copy, &places[UNKNOWN], &choose);
+            add_arc_transition_place(&mut copy, &choose, &places[value_index(value)]);
+            originals.insert(label, None);
+        }
+        values.insert(local, places);
+    }
+
+    for (transition_ref, transition) in net.transitions_iter() {
+        let label = transition_ref.label();
+        let branch = conditions
+            .branches
+            .get(label)
+            .filter(|(local, _)| values.contains_key(local));
+        let assigned: Vec<(&BoolLocal, Assignment)> = conditions
+            .assignments_of(transition.get_preset())
+            .into_iter()
+            .filter(|(local, _)| values.contains_key(local))
+            .collect();
+        // The value after the transition. An unknown value is the one that the branch assumes, if any.
+        let after: Vec<usize> = assigned
+            .iter()
+            .map(|(local, assignment)| match (assignment, branch) {
+                (Assignment::Constant(value), _) => value_index(*value),
+                (Assignment::Unknown, Some((branch_local, value))) if branch_local == *local => {
+                    value_index(*value)
+                }
+                (Assignment::Unknown, _) => UNKNOWN,
+            })
+            .collect();
+        // A branch on a local that the block does not assign tests the place of the value.
+        let tested = branch
+            .filter(|(local, _)| assigned.iter().all(|(other, _)| *other != local))
+            .map(|(local, value)| &values[local][value_index(*value)]);
+
+        // One copy for every combination of the values before, the original transition expects them all unknown.
+        let combinations = u32::try_from(assigned.len())
+            .ok()
+            .and_then(|exponent| 3_usize.checked_pow(exponent))
+            .expect("BUG: The number of tracked locals assigned in a block should be capped");
+        for combination in 0..combinations {
+            let copy_ref = if combination == 0 {
+                transition_refs[label].clone()
+            } else {
+                let copy_label = assignment_copy_label(label, combination);
+                let copy_ref = copy.add_transition(&copy_label);
+                for place_ref in transition.get_preset() {
+                    add_arc_place_transition(&mut copy, &place_refs[place_ref.label()], &copy_ref);
+                }
+                for place_ref in transition.get_postset() {
+                    add_arc_transition_place(&mut copy, &copy_ref, &place_refs[place_ref.label()]);
+                }
+                originals.insert(copy_label, Some(label.clone()));
+                copy_ref
+            };
+            let mut digits = combination;
+            for ((local, _), after) in assigned.iter().zip(&after) {
+                let before = digits % 3;
+                digits /= 3;
+                add_arc_place_transition(&mut copy, &values[local][before], &copy_ref);
+                add_arc_transition_place(&mut copy, &copy_ref, &values[local][*after]);
+            }
+            if let Some(place_ref) = tested {
+                add_arc_place_transition(&mut copy, place_ref, &copy_ref);
+                add_arc_transition_place(&mut copy, &copy_ref, place_ref);
+            }
+        }
+    }
+    InstrumentedNet {
+        net: copy,
+        originals,
+    }
+}
+
+#[cfg(test)]
+mod refinement_tests {
+    use super::*;
+    use crate::model_checker::native::check_deadlock;
+    use crate::test_nets::{LOCK, branch, net_with_flag};
+
+    #[test]
+    fn contradicting_branches_are_spurious() {
+        let (net, conditions, flag) = net_with_flag(true);
+        let witness_path = [LOCK.to_string(), branch(1, 3), branch(3, 4)];
+
+        assert_eq!(
+            spurious_locals(&conditions, &net, &witness_path),
+            BTreeSet::from([flag])
+        );
+    }
+
+    #[test]
+    fn consistent_branches_are_not_spurious() {
+        let (net, conditions, _) = net_with_flag(false);
+        let witness_path = [LOCK.to_string(), branch(1, 3), branch(3, 5)];
+
+        assert!(spurious_locals(&conditions, &net, &witness_path).is_empty());
+    }
+
+    #[test]
+    fn branch_contradicting_a_constant_is_spurious() {
+        let (net, mut conditions, flag) = net_with_flag(false);
+        conditions.record_assignment(
+            "main_BB1".to_string(),
+            flag.clone(),
+            Assignment::Constant(true),
+        );
+        let witness_path = [LOCK.to_string(), branch(1, 3)];
+
+        assert_eq!(
+            spurious_locals(&conditions, &net, &witness_path),
+            BTreeSet::from([flag])
+        );
+    }
+
+    #[test]
+    fn tracking_the_local_removes_the_spurious_deadlock() {
+        let (net, conditions, flag) = net_with_flag(true);
+        assert!(check_deadlock(&net, false).witness_path.is_some());
+
+        let instrumented = instrument(&net, &conditions, &BTreeSet::from([flag]));
+
+        assert!(
+            check_deadlock(&instrumented.net, false)
+                .witness_path
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn every_witness_path_is_checked_before_refining() {
+        let (net, conditions, flag) = net_with_flag(true);
+        let mut refinement = Refinement::new(&conditions, &net);
+        let consistent = vec![LOCK.to_string(), branch(1, 3), branch(3, 5)];
+        let spurious = vec![LOCK.to_string(), branch(1, 3), branch(3, 4)];
+
+        assert!(
+            refinement
+                .refine(std::slice::from_ref(&consistent))
+                .is_none()
+        );
+        assert!(refinement.report().is_none());
+        let instrumented = refinement
+            .refine(&[consistent, spurious.clone()])
+            .expect("The spurious witness path should be refined");
+        assert!(
+            check_deadlock(&instrumented.net, false)
+                .witness_path
+                .is_none()
+        );
+        // The local is already tracked, the witness path cannot be refined any further.
+        assert!(refinement.refine(&[spurious]).is_none());
+
+        let report = refinement.report().expect("A witness path was discarded");
+        assert_eq!(report.spurious_witnesses, 1);
+        assert_eq!(report.tracked_locals.len(), 1);
+        assert_eq!(report.tracked_locals[0].local, flag.local);
+    }
+
+    #[test]
+    fn witness_path_of_a_real_deadlock_is_restored() {
+        let (net, conditions, flag) = net_with_flag(false);
+        let instrumented = instrument(&net, &conditions, &BTreeSet::from([flag]));
+
+        let witness_path = check_deadlock(&instrumented.net, false)
+            .witness_path
+            .expect("The deadlock without unlocking the mutex should be reachable");
+        let witness_path = instrumented.restore_witness_path(witness_path);
+
+        assert_eq!(witness_path, [LOCK.to_string(), branch(1, 3), branch(3, 5)]);
+        assert!(spurious_locals(&conditions, &net, &witness_path).is_empty());
+    }
+
+    #[test]
+    fn local_assigned_with_too_many_tracked_locals_is_not_tracked() {
+        let (net, mut conditions, _) = net_with_flag(true);
+        let others: Vec<BoolLocal> = (3..3 + MAX_TRACKED_LOCALS_PER_BLOCK)
+            .map(|local| BoolLocal {
+                function: "main".to_string(),
+                local,
+            })
+            .collect();
+        for local in &others {
+            conditions.record_assignment(
+                "main_BB0".to_string(),
+                local.clone(),
+                Assignment::Unknown,
+            );
+        }
+        let mut refinement = Refinement::new(&conditions, &net);
+        refinement.tracked.extend(others);
+        let spurious = vec![LOCK.to_string(), branch(1, 3), branch(3, 4)];
+
+        assert!(refinement.refine(&[spurious]).is_none());
+        assert!(refinement.report().is_none());
+    }
+}
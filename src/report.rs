@@ -0,0 +1,1287 @@
+//! Machine-readable report of the analysis.
+//!
+//! The `AnalysisReport` collects everything that is known about a run of the analysis:
+//! the property checked, and for every entry point the verdict, the size of the Petri net,
+//! the runtime of the model checker, the witness found, the signals on condvars that were lost,
+//! the guards still held when a thread returns, the panic, the livelock or the threads still running at the end found,
+//! the distinct classes of deadlocks reachable, the cycles in the lock-order graph, the certificate of a structural proof,
+//! the boolean locals tracked to discard spurious witnesses and the constructs that were over-approximated or not supported.
+//!
+//! It can be written in JSON format for scripts and other tools that consume the result.
+
+use std::io::Write;
+
+use crate::baseline::Finding;
+use crate::data_structures::petri_net_interface::PetriNet;
+use crate::error::TranslationError;
+use crate::formula::Formula;
+use crate::json;
+use crate::model_checker::lola;
+use crate::naming::basic_block::is_place_label;
+use crate::naming::condvar::place_labels;
+use crate::naming::{mutex, thread};
+use crate::net_map::NetMap;
+use crate::panic::PanicCause;
+use crate::source_span::SourceSpan;
+use crate::trace::Trace;
+use crate::trace::{Resource, SyncAction, ThreadId, control_place_thread};
+
+/// The property of the Petri net that the model checker verifies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Property {
+    /// A state is reachable where no transition is enabled and the program did not end.
+    Deadlock,
+    /// A deadlock is reachable after a signal on the condvar with the given index was lost
+    /// and a thread waits on the same condvar in it.
+    /// The net must be instrumented with `lost_signal::instrument` first.
+    LostSignal {
+        /// The index of the condvar.
+        condvar: usize,
+    },
+    /// The thread returns while it holds the mutex with the given index.
+    /// The net must be instrumented with `held_guard::instrument` first.
+    HeldGuard {
+        /// The index of the mutex.
+        mutex: usize,
+        /// The thread that holds the mutex.
+        thread: ThreadId,
+    },
+    /// A transition that starts a panic can fire.
+    /// The net must be instrumented with `panic::instrument` first.
+    Panic,
+    /// A state is reachable from which the program can neither end nor reach a deadlock,
+    /// i.e. it loops forever under a fairness assumption.
+    Livelock,
+    /// The program can end while a spawned thread did not end.
+    /// The net must be instrumented with `unjoined_thread::instrument` first.
+    UnjoinedThread {
+        /// If set, the threads that did not end must also be blocked forever,
+        /// i.e. no transition is enabled after the program ends.
+        blocked: bool,
+    },
+    /// A formula supplied by the user. If it refers to the threads that hold mutexes,
+    /// the net must be the one instrumented by `Formula::resolve`.
+    Formula(Formula),
+}
+
+impl Property {
+    /// Returns the formula passed to the model checker to verify the property.
+    #[must_use]
+    pub fn formula(&self) -> String {
+        match self {
+            Self::Deadlock => lola::DEADLOCK_FORMULA.to_string(),
+            Self::LostSignal { condvar } => lola::lost_signal_formula(*condvar),
+            Self::HeldGuard { mutex, thread } => lola::held_guard_formula(*mutex, thread.0),
+            Self::Panic => lola::PANIC_FORMULA.to_string(),
+            Self::Livelock => lola::LIVELOCK_FORMULA.to_string(),
+            Self::UnjoinedThread { blocked } => lola::unjoined_thread_formula(*blocked),
+            Self::Formula(formula) => formula.expression.to_string(),
+        }
+    }
+
+    /// Returns the verdict for the result of the model checker,
+    /// i.e. whether the formula returned by `Property::formula` holds.
+    #[must_use]
+    pub const fn verdict(&self, result: bool) -> Verdict {
+        match (self, result) {
+            (Self::Deadlock | Self::LostSignal { .. } | Self::HeldGuard { .. }, true) => {
+                Verdict::Deadlock
+            }
+            (Self::Deadlock | Self::LostSignal { .. } | Self::HeldGuard { .. }, false) => {
+                Verdict::DeadlockFree
+            }
+            (Self::Panic, true) => Verdict::Panic,
+            (Self::Panic, false) => Verdict::PanicFree,
+            (Self::Livelock, true) => Verdict::Livelock,
+            (Self::Livelock, false) => Verdict::LivelockFree,
+            (Self::UnjoinedThread { .. }, true) => Verdict::UnjoinedThread,
+            (Self::UnjoinedThread { .. }, false) => Verdict::ThreadsFinished,
+            (Self::Formula(_), true) => Verdict::Holds,
+            (Self::Formula(_), false) => Verdict::DoesNotHold,
+        }
+    }
+}
+
+impl std::fmt::Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Deadlock => write!(f, "deadlock"),
+            Self::LostSignal { .. } => write!(f, "lost_signal"),
+            Self::HeldGuard { .. } => write!(f, "held_guard"),
+            Self::Panic => write!(f, "panic"),
+            Self::Livelock => write!(f, "livelock"),
+            Self::UnjoinedThread { .. } => write!(f, "unjoined_thread"),
+            Self::Formula(_) => write!(f, "formula"),
+        }
+    }
+}
+
+/// The outcome of the analysis of an entry point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The model checker found a state that violates the property.
+    Deadlock,
+    /// The model checker proved that no state violates the property.
+    DeadlockFree,
+    /// The model checker found a state where a panic started.
+    Panic,
+    /// The model checker proved that no panic can start.
+    PanicFree,
+    /// The model checker found a state from which the program loops forever.
+    Livelock,
+    /// The model checker proved that the program can always end or reach a deadlock.
+    LivelockFree,
+    /// The model checker found a state where the program ended while a spawned thread did not.
+    UnjoinedThread,
+    /// The model checker proved that every spawned thread ends before the program.
+    ThreadsFinished,
+    /// The formula supplied by the user holds.
+    Holds,
+    /// The formula supplied by the user does not hold.
+    DoesNotHold,
+    /// The model checker stopped without a result, e.g. because it exceeded the time limit.
+    Inconclusive {
+        /// A description of why the model checker stopped.
+        reason: String,
+    },
+    /// The net was translated but the model checker did not run.
+    NotChecked,
+    /// The translation stopped before the net was complete.
+    TranslationFailed,
+    /// The model checker could not check the property, e.g. because its executable was not found.
+    ModelCheckerFailed {
+        /// A description of the error of the model checker.
+        reason: String,
+    },
+    /// The formula supplied by the user could not be read, parsed or resolved to the net.
+    InvalidProperty {
+        /// A description of the problem with the formula.
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Deadlock => write!(f, "deadlock"),
+            Self::DeadlockFree => write!(f, "deadlock_free"),
+            Self::Panic => write!(f, "panic"),
+            Self::PanicFree => write!(f, "panic_free"),
+            Self::Livelock => write!(f, "livelock"),
+            Self::LivelockFree => write!(f, "livelock_free"),
+            Self::UnjoinedThread => write!(f, "unjoined_thread"),
+            Self::ThreadsFinished => write!(f, "threads_finished"),
+            Self::Holds => write!(f, "holds"),
+            Self::DoesNotHold => write!(f, "does_not_hold"),
+            Self::Inconclusive { .. } => write!(f, "inconclusive"),
+            Self::NotChecked => write!(f, "not_checked"),
+            Self::TranslationFailed => write!(f, "translation_failed"),
+            Self::ModelCheckerFailed { .. } => write!(f, "model_checker_failed"),
+            Self::InvalidProperty { .. } => write!(f, "invalid_property"),
+        }
+    }
+}
+
+/// The size of the Petri net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetStatistics {
+    /// The number of places.
+    pub places: usize,
+    /// The number of transitions.
+    pub transitions: usize,
+    /// The number of arcs, in both directions.
+    pub arcs: usize,
+}
+
+impl NetStatistics {
+    /// Counts the places, transitions and arcs of the Petri net.
+    #[must_use]
+    pub fn new(net: &PetriNet) -> Self {
+        Self {
+            places: net.get_cardinality_places(),
+            transitions: net.get_cardinality_transitions(),
+            arcs: net.find_arcs_place_transition().len() + net.find_arcs_transition_place().len(),
+        }
+    }
+}
+
+/// A run of the model checker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelCheckerRun {
+    /// The name of the model checker, e.g. `LoLA`.
+    pub name: String,
+    /// The wall-clock time that the model checker took.
+    pub runtime: std::time::Duration,
+    /// The number of states explored, if the model checker reports it.
+    pub states: Option<usize>,
+    /// The number of states explored without partial-order reduction, if it was measured for comparison.
+    pub states_without_reduction: Option<usize>,
+}
+
+/// The witness of a property violation found by the model checker.
+#[derive(Debug, Clone)]
+pub struct Witness {
+    /// The labels of the transitions in the order in which they fire.
+    pub transitions: Vec<String>,
+    /// The witness path replayed on the net, if it could be built.
+    pub trace: Option<Trace>,
+}
+
+/// A signal on a condvar that was lost before a thread waits on the condvar forever.
+#[derive(Debug, Clone)]
+pub struct LostSignal {
+    /// The index of the condvar.
+    pub condvar: usize,
+    /// The location where the condvar was created.
+    pub condvar_span: Option<SourceSpan>,
+    /// The location of the notify call whose signal was lost.
+    pub notify_span: Option<SourceSpan>,
+    /// The location of the wait that never returns.
+    pub wait_span: Option<SourceSpan>,
+    /// The witness path to the deadlock where the thread waits.
+    pub witness: Witness,
+}
+
+impl LostSignal {
+    /// Creates a new lost signal on the condvar from the witness found by the model checker.
+    /// The locations are taken from the trace of the witness:
+    /// the notify call is the last one on the condvar before the last signal lost.
+    #[must_use]
+    pub fn new(condvar: usize, witness: Witness) -> Self {
+        let mut lost_signal = Self {
+            condvar,
+            condvar_span: None,
+            notify_span: None,
+            wait_span: None,
+            witness,
+        };
+        let Some(trace) = &lost_signal.witness.trace else {
+            return lost_signal;
+        };
+        lost_signal.condvar_span = trace
+            .resources
+            .get(&place_labels(condvar).1)
+            .cloned()
+            .flatten();
+        let lost = trace
+            .steps
+            .iter()
+            .rposition(|step| step.actions.contains(&SyncAction::LostSignal(condvar)))
+            .unwrap_or_default();
+        lost_signal.notify_span = trace.steps[..lost]
+            .iter()
+            .rev()
+            .find(|step| step.actions.contains(&SyncAction::Notify(condvar)))
+            .and_then(|step| step.span.clone());
+        lost_signal.wait_span = trace
+            .blocked
+            .iter()
+            .flat_map(|blocked_thread| &blocked_thread.waits_for)
+            .find(|(resource, _, _)| *resource == Resource::Condvar(condvar))
+            .and_then(|(_, _, span)| span.clone());
+        lost_signal
+    }
+}
+
+impl std::fmt::Display for LostSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = |span: Option<&SourceSpan>| {
+            span.map_or_else(|| "an unknown location".to_string(), ToString::to_string)
+        };
+        write!(
+            f,
+            "The signal on condvar {} created at {} is lost: it is sent at {} while no thread waits, then a thread waits forever at {}",
+            self.condvar,
+            location(self.condvar_span.as_ref()),
+            location(self.notify_span.as_ref()),
+            location(self.wait_span.as_ref())
+        )
+    }
+}
+
+/// A mutex guard that a thread still holds when it returns, so the mutex stays locked forever.
+#[derive(Debug, Clone)]
+pub struct HeldGuard {
+    /// The index of the mutex.
+    pub mutex: usize,
+    /// The thread that returns while it holds the mutex.
+    pub thread: ThreadId,
+    /// The location where the mutex was created.
+    pub mutex_span: Option<SourceSpan>,
+    /// The location of the lock call whose guard is never released.
+    pub lock_span: Option<SourceSpan>,
+    /// The line of source code at `lock_span`, if the file can be read.
+    pub source_line: Option<String>,
+    /// The witness path to the return of the thread.
+    pub witness: Witness,
+}
+
+impl HeldGuard {
+    /// Creates a new guard held by the thread from the witness found by the model checker.
+    /// The lock call is the last step of the trace where the thread locks the mutex.
+    #[must_use]
+    pub fn new(mutex: usize, thread: ThreadId, witness: Witness) -> Self {
+        let mut held_guard = Self {
+            mutex,
+            thread,
+            mutex_span: None,
+            lock_span: None,
+            source_line: None,
+            witness,
+        };
+        let Some(trace) = &held_guard.witness.trace else {
+            return held_guard;
+        };
+        held_guard.mutex_span = trace
+            .resources
+            .get(&mutex::place_label(mutex))
+            .cloned()
+            .flatten();
+        if let Some(step) =
+            trace.steps.iter().rev().find(|step| {
+                step.thread == thread && step.actions.contains(&SyncAction::Lock(mutex))
+            })
+        {
+            held_guard.lock_span.clone_from(&step.span);
+            held_guard.source_line.clone_from(&step.source_line);
+        }
+        held_guard
+    }
+}
+
+impl std::fmt::Display for HeldGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = |span: Option<&SourceSpan>| {
+            span.map_or_else(|| "an unknown location".to_string(), ToString::to_string)
+        };
+        write!(
+            f,
+            "{} returns while holding mutex {} created at {}: the guard acquired at {}",
+            capitalize(&self.thread.to_string()),
+            self.mutex,
+            location(self.mutex_span.as_ref()),
+            location(self.lock_span.as_ref())
+        )?;
+        if let Some(source_line) = &self.source_line {
+            write!(f, " in `{source_line}`")?;
+        }
+        write!(f, " is never released")
+    }
+}
+
+/// A panic found by the model checker, located at the transition of the witness path that starts it.
+#[derive(Debug, Clone)]
+pub struct Panic {
+    /// The thread that panics.
+    pub thread: ThreadId,
+    /// The kind of transition that starts the panic.
+    pub cause: PanicCause,
+    /// The location of the panicking call or assertion.
+    pub span: Option<SourceSpan>,
+    /// The line of source code at `span`, if the file can be read.
+    pub source_line: Option<String>,
+}
+
+impl Panic {
+    /// Builds the panic from the trace of the witness found by the model checker,
+    /// whose last step fires the transition that starts the panic with the given cause.
+    /// Returns `None` if the witness could not be replayed or is empty.
+    #[must_use]
+    pub fn new(cause: PanicCause, witness: &Witness) -> Option<Self> {
+        let step = witness.trace.as_ref()?.steps.last()?;
+        Some(Self {
+            thread: step.thread,
+            cause,
+            span: step.span.clone(),
+            source_line: step.source_line.clone(),
+        })
+    }
+}
+
+impl std::fmt::Display for Panic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = self
+            .span
+            .as_ref()
+            .map_or_else(|| "an unknown location".to_string(), ToString::to_string);
+        let cause = match self.cause {
+            PanicCause::PanicCall => "calls a function that panics",
+            PanicCause::FailedAssertion => "fails an assertion",
+            PanicCause::Unwrap => "unwraps an `Err` or `None` value",
+        };
+        write!(
+            f,
+            "{} panics at {location}: it {cause}",
+            capitalize(&self.thread.to_string())
+        )?;
+        if let Some(source_line) = &self.source_line {
+            write!(f, " in `{source_line}`")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the text with the first letter in upper case, e.g. to start a sentence with the name of a thread.
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// A basic block that a thread executes in the cycle of a livelock.
+#[derive(Debug, Clone)]
+pub struct LoopingBlock {
+    /// The label of the place of the basic block, e.g. `main_BB3`.
+    pub label: String,
+    /// The thread that executes the basic block.
+    pub thread: ThreadId,
+    /// The location of the code that leaves the basic block in the cycle.
+    pub span: Option<SourceSpan>,
+}
+
+/// A livelock found by the model checker, i.e. a cycle that the program repeats forever without terminating.
+#[derive(Debug, Clone)]
+pub struct Livelock {
+    /// The labels of the transitions in the cycle, in firing order,
+    /// starting from the final state of the witness path.
+    pub cycle: Vec<String>,
+    /// The basic blocks executed in the cycle, in order of appearance.
+    pub blocks: Vec<LoopingBlock>,
+}
+
+impl Livelock {
+    /// Creates a new livelock from the cycle of transitions found after the witness path.
+    /// The basic blocks are the places of basic blocks in the preset of the transitions of the cycle.
+    #[must_use]
+    pub fn new(cycle: Vec<String>, net: &PetriNet, net_map: &NetMap) -> Self {
+        let mut blocks: Vec<LoopingBlock> = Vec::new();
+        for label in &cycle {
+            let Some((_, transition)) = net
+                .transitions_iter()
+                .find(|(transition_ref, _)| transition_ref.label() == label)
+            else {
+                continue;
+            };
+            for place_ref in transition.get_preset() {
+                let place_label = place_ref.label();
+                if !is_place_label(place_label)
+                    || blocks.iter().any(|block| block.label == *place_label)
+                {
+                    continue;
+                }
+                blocks.push(LoopingBlock {
+                    label: place_label.clone(),
+                    thread: control_place_thread(place_label, net_map).unwrap_or(ThreadId(None)),
+                    span: net_map
+                        .transition_origin(label)
+                        .and_then(|origin| origin.span.clone()),
+                });
+            }
+        }
+        Self { cycle, blocks }
+    }
+}
+
+impl std::fmt::Display for Livelock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The program loops forever without terminating in the basic blocks:"
+        )?;
+        for block in &self.blocks {
+            write!(f, "\n  - {} of the {}", block.label, block.thread)?;
+            if let Some(span) = &block.span {
+                write!(f, " at {span}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A spawned thread that did not end when the program ends, found in the final state of the witness path.
+#[derive(Debug, Clone)]
+pub struct UnjoinedThread {
+    /// The index of the thread.
+    pub thread: usize,
+    /// The location where the thread was spawned.
+    pub spawn_span: Option<SourceSpan>,
+    /// The mutexes that the thread holds.
+    pub holds: Vec<usize>,
+    /// The resources that the thread waits for, with the location of the blocked call.
+    pub waits_for: Vec<(Resource, Option<SourceSpan>)>,
+    /// The location of the last step of the thread, or `None` if it did not start.
+    pub last_span: Option<SourceSpan>,
+}
+
+impl UnjoinedThread {
+    /// Creates a new unjoined thread from the trace of the witness found by the model checker.
+    /// Returns `None` if the witness could not be replayed.
+    #[must_use]
+    pub fn new(thread: usize, witness: &Witness) -> Option<Self> {
+        let trace = witness.trace.as_ref()?;
+        let thread_id = ThreadId(Some(thread));
+        let mut holds = Vec::new();
+        let mut last_span = None;
+        for step in &trace.steps {
+            for action in &step.actions {
+                match action {
+                    SyncAction::Lock(index) if step.thread == thread_id => holds.push(*index),
+                    SyncAction::Unlock(index) => holds.retain(|held| held != index),
+                    _ => {}
+                }
+            }
+            if step.thread == thread_id {
+                last_span.clone_from(&step.span);
+            }
+        }
+        let waits_for = trace
+            .blocked
+            .iter()
+            .filter(|blocked_thread| blocked_thread.thread == thread_id)
+            .flat_map(|blocked_thread| &blocked_thread.waits_for)
+            .map(|(resource, _, span)| (*resource, span.clone()))
+            .collect();
+        Some(Self {
+            thread,
+            spawn_span: trace
+                .resources
+                .get(&thread::start_place_label(thread))
+                .cloned()
+                .flatten(),
+            holds,
+            waits_for,
+            last_span,
+        })
+    }
+}
+
+impl std::fmt::Display for UnjoinedThread {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = |span: Option<&SourceSpan>| {
+            span.map_or_else(|| "an unknown location".to_string(), ToString::to_string)
+        };
+        write!(
+            f,
+            "Thread {} spawned at {} has not ended when the main thread returns: ",
+            self.thread,
+            location(self.spawn_span.as_ref())
+        )?;
+        if !self.waits_for.is_empty() {
+            let waits_for: Vec<String> = self
+                .waits_for
+                .iter()
+                .map(|(resource, span)| format!("{resource} at {}", location(span.as_ref())))
+                .collect();
+            write!(f, "it waits for {}", waits_for.join(" or "))?;
+        } else if let Some(span) = &self.last_span {
+            write!(f, "its last step is at {span}")?;
+        } else {
+            write!(f, "it has not started yet")?;
+        }
+        if !self.holds.is_empty() {
+            let holds: Vec<String> = self
+                .holds
+                .iter()
+                .map(|index| format!("mutex {index}"))
+                .collect();
+            write!(f, " while holding {}", holds.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The operations that the threads are blocked on in a deadlock: every blocked thread
+/// with the resources it waits for, each with the label of the blocked transition.
+type BlockedOperations = Vec<(ThreadId, Vec<(Resource, String)>)>;
+
+/// The resources that a blocked thread waits for, with the location of the blocked call.
+pub type WaitsFor = Vec<(Resource, Option<SourceSpan>)>;
+
+/// A class of reachable deadlocks where the same threads are blocked on the same operations,
+/// e.g. the main thread waits for mutex 0 and thread 1 waits for mutex 1 at the same calls.
+/// The deadlocks of a class only differ in the rest of the state, e.g. the position of the threads that ended.
+#[derive(Debug, Clone)]
+pub struct DeadlockClass {
+    /// The blocked threads with the resources they wait for and the location of the blocked call.
+    pub blocked: Vec<(ThreadId, WaitsFor)>,
+    /// The number of distinct deadlock states in the class.
+    pub states: usize,
+    /// The witness path to the first deadlock state of the class found.
+    pub witness: Witness,
+}
+
+impl DeadlockClass {
+    /// Groups the witnesses of the distinct deadlock states by the operations that the threads are blocked on.
+    /// Returns the classes in the order in which their first witness appears, which is the witness kept for the class.
+    /// Witnesses that could not be replayed form a class of their own without blocked threads.
+    #[must_use]
+    pub fn group(witnesses: Vec<Witness>) -> Vec<Self> {
+        let mut classes: Vec<(BlockedOperations, Self)> = Vec::new();
+        for witness in witnesses {
+            let blocked_threads = witness
+                .trace
+                .as_ref()
+                .map(|trace| trace.blocked.as_slice())
+                .unwrap_or_default();
+            let operations: BlockedOperations = blocked_threads
+                .iter()
+                .map(|blocked_thread| {
+                    let waits_for = blocked_thread
+                        .waits_for
+                        .iter()
+                        .map(|(resource, transition, _)| (*resource, transition.clone()))
+                        .collect();
+                    (blocked_thread.thread, waits_for)
+                })
+                .collect();
+            if let Some((_, class)) = classes.iter_mut().find(|(other, _)| *other == operations) {
+                class.states += 1;
+                continue;
+            }
+            let blocked = blocked_threads
+                .iter()
+                .map(|blocked_thread| {
+                    let waits_for = blocked_thread
+                        .waits_for
+                        .iter()
+                        .map(|(resource, _, span)| (*resource, span.clone()))
+                        .collect();
+                    (blocked_thread.thread, waits_for)
+                })
+                .collect();
+            classes.push((
+                operations,
+                Self {
+                    blocked,
+                    states: 1,
+                    witness,
+                },
+            ));
+        }
+        classes.into_iter().map(|(_, class)| class).collect()
+    }
+}
+
+impl std::fmt::Display for DeadlockClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = |span: Option<&SourceSpan>| {
+            span.map_or_else(|| "an unknown location".to_string(), ToString::to_string)
+        };
+        let blocked: Vec<String> = self
+            .blocked
+            .iter()
+            .map(|(thread, waits_for)| {
+                let thread = match thread.0 {
+                    Some(_) => thread.to_string(),
+                    None => format!("the {thread}"),
+                };
+                let waits_for: Vec<String> = waits_for
+                    .iter()
+                    .map(|(resource, span)| format!("{resource} at {}", location(span.as_ref())))
+                    .collect();
+                format!("{thread} waits for {}", waits_for.join(" or "))
+            })
+            .collect();
+        if blocked.is_empty() {
+            write!(f, "Deadlock without a known blocked thread")?;
+        } else {
+            write!(f, "Deadlock where {}", blocked.join(", "))?;
+        }
+        write!(f, " ({} state(s))", self.states)
+    }
+}
+
+/// An edge of the lock-order graph: a thread locks a mutex while it holds another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockOrderEdge {
+    /// The thread that locks both mutexes.
+    pub thread: ThreadId,
+    /// The index of the mutex held.
+    pub held: usize,
+    /// The location of the lock call that acquired the mutex held.
+    pub held_span: Option<SourceSpan>,
+    /// The index of the mutex locked while the other is held.
+    pub acquired: usize,
+    /// The location of the lock call that acquires the mutex.
+    pub acquired_span: Option<SourceSpan>,
+}
+
+impl std::fmt::Display for LockOrderEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = |span: Option<&SourceSpan>| {
+            span.map_or_else(|| "an unknown location".to_string(), ToString::to_string)
+        };
+        let thread = match self.thread.0 {
+            Some(_) => self.thread.to_string(),
+            None => format!("the {}", self.thread),
+        };
+        write!(
+            f,
+            "{thread} locks mutex {} at {} while holding mutex {} locked at {}",
+            self.acquired,
+            location(self.acquired_span.as_ref()),
+            self.held,
+            location(self.held_span.as_ref())
+        )
+    }
+}
+
+/// A cycle in the lock-order graph, i.e. threads that lock the same mutexes in different orders.
+/// Each edge is the acquisition of the mutex held by the next edge, so every thread may wait for the next one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockOrderCycle {
+    /// The edges of the cycle, from different threads.
+    pub edges: Vec<LockOrderEdge>,
+}
+
+impl std::fmt::Display for LockOrderCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut mutexes: Vec<String> = self
+            .edges
+            .iter()
+            .map(|edge| format!("mutex {}", edge.held))
+            .collect();
+        let last = mutexes.pop().unwrap_or_default();
+        let edges: Vec<String> = self.edges.iter().map(ToString::to_string).collect();
+        write!(
+            f,
+            "Lock-order cycle between {} and {last}: {}",
+            mutexes.join(", "),
+            edges.join(", ")
+        )
+    }
+}
+
+/// A place invariant: the sum of the tokens in the places, multiplied by their weights, is the same in every marking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaceInvariant {
+    /// The labels of the places with a positive weight, with their weight.
+    pub weights: Vec<(String, u64)>,
+    /// The weighted sum of the tokens in the initial marking.
+    pub tokens: u64,
+}
+
+impl std::fmt::Display for PlaceInvariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let terms: Vec<String> = self
+            .weights
+            .iter()
+            .map(|(label, weight)| {
+                if *weight == 1 {
+                    label.clone()
+                } else {
+                    format!("{weight} * {label}")
+                }
+            })
+            .collect();
+        write!(f, "{} = {}", terms.join(" + "), self.tokens)
+    }
+}
+
+/// The certificate of a proof of deadlock-freedom from the structure of the net, see the module `structural`.
+/// Every set of places that could be empty in a deadlock contradicts one of the invariants or traps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuralProof {
+    /// The place invariants used, valid while the program did not end.
+    pub invariants: Vec<PlaceInvariant>,
+    /// The labels of the places of the traps used, each marked initially and therefore in every reachable marking.
+    pub traps: Vec<Vec<String>>,
+}
+
+impl StructuralProof {
+    /// Returns a sentence with the number of invariants and traps used.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        format!(
+            "Every deadlock is ruled out by {} place invariant(s) and {} trap(s) marked initially",
+            self.invariants.len(),
+            self.traps.len()
+        )
+    }
+}
+
+impl std::fmt::Display for StructuralProof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+        for invariant in &self.invariants {
+            writeln!(f, "Invariant: {invariant}")?;
+        }
+        for trap in &self.traps {
+            writeln!(f, "Trap: {{{}}}", trap.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A boolean local whose value was tracked in the net to discard spurious witnesses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedLocal {
+    /// The name of the function, with the index of the call as in the labels of its basic blocks.
+    pub function: String,
+    /// The index of the local in the MIR body of the function.
+    pub local: usize,
+    /// The name of the variable in the source code, if the local is one.
+    pub name: Option<String>,
+    /// The location of the declaration of the local.
+    pub span: Option<SourceSpan>,
+}
+
+impl std::fmt::Display for TrackedLocal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "`{name}` in `{}`", self.function)?,
+            None => write!(f, "local _{} in `{}`", self.local, self.function)?,
+        }
+        if let Some(span) = &self.span {
+            write!(f, " declared at {span}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The refinement of the net after witnesses that take contradicting branches on boolean locals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchRefinement {
+    /// The number of spurious witnesses discarded.
+    pub spurious_witnesses: usize,
+    /// The boolean locals tracked in the net to discard them.
+    pub tracked_locals: Vec<TrackedLocal>,
+}
+
+impl std::fmt::Display for BranchRefinement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let locals: Vec<String> = self
+            .tracked_locals
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        write!(
+            f,
+            "Discarded {} spurious witness(es) by tracking the boolean local(s) {}",
+            self.spurious_witnesses,
+            locals.join(", ")
+        )
+    }
+}
+
+/// The result of the analysis of a single entry point of the program.
+#[derive(Debug, Clone)]
+pub struct EntryPointReport {
+    /// The name of the entry point, e.g. `main`.
+    pub entry_point: String,
+    /// The outcome of the analysis.
+    pub verdict: Verdict,
+    /// The error that stopped the translation, if the verdict is `Verdict::TranslationFailed`.
+    pub error: Option<TranslationError>,
+    /// The size of the Petri net, if the translation succeeded.
+    pub net: Option<NetStatistics>,
+    /// The run of the model checker, if it ran.
+    pub model_checker: Option<ModelCheckerRun>,
+    /// The witness found by the model checker, if the property is violated.
+    pub witness: Option<Witness>,
+    /// The signals on condvars that were lost before a thread waits forever, checked only if a deadlock was found.
+    pub lost_signals: Vec<LostSignal>,
+    /// The guards still held when a thread returns, checked only if requested together with deadlocks.
+    pub held_guards: Vec<HeldGuard>,
+    /// The panic found, if the property checked is `Property::Panic` and a panic is reachable.
+    pub panic: Option<Panic>,
+    /// The livelock found, if the property checked is `Property::Livelock` and a livelock is reachable.
+    pub livelock: Option<Livelock>,
+    /// The threads that did not end when the program ends,
+    /// if the property checked is `Property::UnjoinedThread` and such a state is reachable.
+    pub unjoined_threads: Vec<UnjoinedThread>,
+    /// The distinct classes of deadlocks reachable, enumerated only if requested and a deadlock was found.
+    pub deadlock_classes: Vec<DeadlockClass>,
+    /// The cycles in the lock-order graph, built only if requested.
+    pub lock_order_cycles: Vec<LockOrderCycle>,
+    /// The certificate of the structural proof of deadlock-freedom, if it succeeded.
+    pub structural_proof: Option<StructuralProof>,
+    /// The refinement of the net after spurious witnesses, if requested and a spurious witness was found.
+    pub refinement: Option<BranchRefinement>,
+    /// The unsupported constructs that were over-approximated in lenient mode.
+    pub approximations: Vec<TranslationError>,
+    /// The unsupported constructs found by the support check.
+    pub unsupported: Vec<TranslationError>,
+}
+
+impl EntryPointReport {
+    /// Creates a new report for the given entry point with the given verdict.
+    /// The remaining fields are empty.
+    #[must_use]
+    pub const fn new(entry_point: String, verdict: Verdict) -> Self {
+        Self {
+            entry_point,
+            verdict,
+            error: None,
+            net: None,
+            model_checker: None,
+            witness: None,
+            lost_signals: Vec::new(),
+            held_guards: Vec::new(),
+            panic: None,
+            livelock: None,
+            unjoined_threads: Vec::new(),
+            deadlock_classes: Vec::new(),
+            lock_order_cycles: Vec::new(),
+            structural_proof: None,
+            refinement: None,
+            approximations: Vec::new(),
+            unsupported: Vec::new(),
+        }
+    }
+}
+
+/// The report of a run of the analysis on a source code file.
+#[derive(Debug, Clone)]
+pub struct AnalysisReport {
+    /// The path to the source code file analyzed.
+    pub source_file: String,
+    /// The property checked, unless the formula supplied by the user could not be read or parsed.
+    pub property: Option<Property>,
+    /// The results for each entry point.
+    pub entry_points: Vec<EntryPointReport>,
+}
+
+impl AnalysisReport {
+    /// Creates a new report for the given source code file without any entry point.
+    #[must_use]
+    pub fn new(source_file: String, property: Option<Property>) -> Self {
+        Self {
+            source_file,
+            property,
+            entry_points: Vec::new(),
+        }
+    }
+
+    /// Writes the report in JSON format.
+    /// The entry points are written one per block, the witness trace one step per line.
+    ///
+    /// # Errors
+    ///
+    /// If writing to the writer fails, then the function returns an error.
+    pub fn to_json<T: Write>(&self, writer: &mut T) -> Result<(), std::io::Error> {
+        writeln!(writer, "{{")?;
+        writeln!(
+            writer,
+            "  \"source_file\": {},",
+            json::string(&self.source_file)
+        )?;
+        writeln!(
+            writer,
+            "  \"property\": {},",
+            json::optional(self.property.as_ref().map(|property| format!(
+                "{{\"name\": {}, \"formula\": {}}}",
+                json::string(&property.to_string()),
+                json::string(&property.formula())
+            )))
+        )?;
+        writeln!(writer, "  \"entry_points\": [")?;
+        let mut iter = self.entry_points.iter().peekable();
+        while let Some(entry_point) = iter.next() {
+            write_entry_point(writer, entry_point)?;
+            if iter.peek().is_some() {
+                writeln!(writer, ",")?;
+            } else {
+                writeln!(writer)?;
+            }
+        }
+        writeln!(writer, "  ]")?;
+        writeln!(writer, "}}")
+    }
+}
+
+/// Writes the report of an entry point as a JSON object, without the trailing newline.
+fn write_entry_point<T: Write>(
+    writer: &mut T,
+    entry_point: &EntryPointReport,
+) -> Result<(), std::io::Error> {
+    writeln!(writer, "    {{")?;
+    writeln!(
+        writer,
+        "      \"entry_point\": {},",
+        json::string(&entry_point.entry_point)
+    )?;
+    writeln!(
+        writer,
+        "      \"verdict\": {},",
+        json::string(&entry_point.verdict.to_string())
+    )?;
+    let reason = match &entry_point.verdict {
+        Verdict::Inconclusive { reason }
+        | Verdict::ModelCheckerFailed { reason }
+        | Verdict::InvalidProperty { reason } => Some(json::string(reason)),
+        _ => None,
+    };
+    writeln!(writer, "      \"reason\": {},", json::optional(reason))?;
+    writeln!(
+        writer,
+        "      \"fingerprint\": {},",
+        json::optional(Finding::new(entry_point).map(|finding| json::string(&finding.fingerprint)))
+    )?;
+    writeln!(
+        writer,
+        "      \"error\": {},",
+        json::optional(entry_point.error.as_ref().map(error_json))
+    )?;
+    writeln!(
+        writer,
+        "      \"net\": {},",
+        json::optional(entry_point.net.map(|net| format!(
+            "{{\"places\": {}, \"transitions\": {}, \"arcs\": {}}}",
+            net.places, net.transitions, net.arcs
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"model_checker\": {},",
+        json::optional(entry_point.model_checker.as_ref().map(|run| format!(
+            "{{\"name\": {}, \"runtime_ms\": {}, \"states\": {}, \"states_without_reduction\": {}}}",
+            json::string(&run.name),
+            run.runtime.as_millis(),
+            json::optional(run.states),
+            json::optional(run.states_without_reduction)
+        )))
+    )?;
+    write!(writer, "      \"witness\": ")?;
+    match &entry_point.witness {
+        Some(witness) => write_witness(writer, witness)?,
+        None => write!(writer, "null")?,
+    }
+    writeln!(writer, ",")?;
+    writeln!(
+        writer,
+        "      \"lost_signals\": {},",
+        json::array(entry_point.lost_signals.iter().map(|lost_signal| format!(
+            "{{\"condvar\": {}, \"condvar_span\": {}, \"notify_span\": {}, \"wait_span\": {}, \"transitions\": {}}}",
+            lost_signal.condvar,
+            json::span(lost_signal.condvar_span.as_ref()),
+            json::span(lost_signal.notify_span.as_ref()),
+            json::span(lost_signal.wait_span.as_ref()),
+            json::array(
+                lost_signal
+                    .witness
+                    .transitions
+                    .iter()
+                    .map(|label| json::string(label))
+            )
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"held_guards\": {},",
+        json::array(entry_point.held_guards.iter().map(|held_guard| format!(
+            "{{\"mutex\": {}, \"thread\": {}, \"mutex_span\": {}, \"lock_span\": {}, \"transitions\": {}}}",
+            held_guard.mutex,
+            json::optional(held_guard.thread.0),
+            json::span(held_guard.mutex_span.as_ref()),
+            json::span(held_guard.lock_span.as_ref()),
+            json::array(
+                held_guard
+                    .witness
+                    .transitions
+                    .iter()
+                    .map(|label| json::string(label))
+            )
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"panic\": {},",
+        json::optional(entry_point.panic.as_ref().map(|panic| format!(
+            "{{\"thread\": {}, \"cause\": {}, \"span\": {}}}",
+            json::optional(panic.thread.0),
+            json::string(&panic.cause.to_string()),
+            json::span(panic.span.as_ref())
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"livelock\": {},",
+        json::optional(entry_point.livelock.as_ref().map(|livelock| format!(
+            "{{\"cycle\": {}, \"blocks\": {}}}",
+            json::array(livelock.cycle.iter().map(|label| json::string(label))),
+            json::array(livelock.blocks.iter().map(|block| format!(
+                "{{\"label\": {}, \"thread\": {}, \"span\": {}}}",
+                json::string(&block.label),
+                json::optional(block.thread.0),
+                json::span(block.span.as_ref())
+            )))
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"unjoined_threads\": {},",
+        json::array(entry_point.unjoined_threads.iter().map(|unjoined_thread| format!(
+            "{{\"thread\": {}, \"spawn_span\": {}, \"holds\": {}, \"waits_for\": {}, \"last_span\": {}}}",
+            unjoined_thread.thread,
+            json::span(unjoined_thread.spawn_span.as_ref()),
+            json::array(unjoined_thread.holds.iter().map(ToString::to_string)),
+            json::array(unjoined_thread.waits_for.iter().map(|(resource, span)| format!(
+                "{{\"resource\": {}, \"span\": {}}}",
+                json::string(&resource.to_string()),
+                json::span(span.as_ref())
+            ))),
+            json::span(unjoined_thread.last_span.as_ref())
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"deadlock_classes\": {},",
+        json::array(entry_point.deadlock_classes.iter().map(|class| format!(
+            "{{\"blocked\": {}, \"states\": {}, \"transitions\": {}}}",
+            json::array(class.blocked.iter().map(|(thread, waits_for)| format!(
+                "{{\"thread\": {}, \"waits_for\": {}}}",
+                json::optional(thread.0),
+                json::array(waits_for.iter().map(|(resource, span)| format!(
+                    "{{\"resource\": {}, \"span\": {}}}",
+                    json::string(&resource.to_string()),
+                    json::span(span.as_ref())
+                )))
+            ))),
+            class.states,
+            json::array(
+                class
+                    .witness
+                    .transitions
+                    .iter()
+                    .map(|label| json::string(label))
+            )
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"lock_order_cycles\": {},",
+        json::array(entry_point.lock_order_cycles.iter().map(|cycle| format!(
+            "{{\"edges\": {}}}",
+            json::array(cycle.edges.iter().map(|edge| format!(
+                "{{\"thread\": {}, \"held\": {}, \"held_span\": {}, \"acquired\": {}, \"acquired_span\": {}}}",
+                json::optional(edge.thread.0),
+                edge.held,
+                json::span(edge.held_span.as_ref()),
+                edge.acquired,
+                json::span(edge.acquired_span.as_ref())
+            )))
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"structural_proof\": {},",
+        json::optional(entry_point.structural_proof.as_ref().map(|proof| format!(
+            "{{\"invariants\": {}, \"traps\": {}}}",
+            json::array(proof.invariants.iter().map(|invariant| format!(
+                "{{\"weights\": {}, \"tokens\": {}}}",
+                json::array(invariant.weights.iter().map(|(label, weight)| format!(
+                    "{{\"place\": {}, \"weight\": {weight}}}",
+                    json::string(label)
+                ))),
+                invariant.tokens
+            ))),
+            json::array(
+                proof
+                    .traps
+                    .iter()
+                    .map(|trap| json::array(trap.iter().map(|label| json::string(label))))
+            )
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"refinement\": {},",
+        json::optional(entry_point.refinement.as_ref().map(|refinement| format!(
+            "{{\"spurious_witnesses\": {}, \"tracked_locals\": {}}}",
+            refinement.spurious_witnesses,
+            json::array(refinement.tracked_locals.iter().map(|local| format!(
+                "{{\"function\": {}, \"local\": {}, \"name\": {}, \"span\": {}}}",
+                json::string(&local.function),
+                local.local,
+                json::optional(local.name.as_deref().map(json::string)),
+                json::span(local.span.as_ref())
+            )))
+        )))
+    )?;
+    writeln!(
+        writer,
+        "      \"approximations\": {},",
+        json::array(entry_point.approximations.iter().map(error_json))
+    )?;
+    writeln!(
+        writer,
+        "      \"unsupported\": {}",
+        json::array(entry_point.unsupported.iter().map(error_json))
+    )?;
+    write!(writer, "    }}")
+}
+
+/// Writes a witness as a JSON object, without the trailing newline.
+fn write_witness<T: Write>(writer: &mut T, witness: &Witness) -> Result<(), std::io::Error> {
+    writeln!(writer, "{{")?;
+    writeln!(
+        writer,
+        "        \"transitions\": {},",
+        json::array(witness.transitions.iter().map(|label| json::string(label)))
+    )?;
+    let Some(trace) = &witness.trace else {
+        writeln!(writer, "        \"trace\": null")?;
+        return write!(writer, "      }}");
+    };
+    writeln!(writer, "        \"trace\": {{")?;
+    writeln!(writer, "          \"steps\": [")?;
+    let steps: Vec<String> = trace
+        .steps
+        .iter()
+        .map(|step| {
+            format!(
+                "            {{\"transition\": {}, \"thread\": {}, \"span\": {}, \"actions\": {}}}",
+                json::string(&step.transition),
+                json::optional(step.thread.0),
+                json::span(step.span.as_ref()),
+                json::array(
+                    step.actions
+                        .iter()
+                        .map(|action| json::string(&action.to_string()))
+                )
+            )
+        })
+        .collect();
+    if !steps.is_empty() {
+        writeln!(writer, "{}", steps.join(",\n"))?;
+    }
+    writeln!(writer, "          ],")?;
+    writeln!(writer, "          \"blocked\": [")?;
+    let blocked: Vec<String> = trace
+        .blocked
+        .iter()
+        .map(|blocked_thread| {
+            format!(
+                "            {{\"thread\": {}, \"holds\": {}, \"waits_for\": {}}}",
+                json::optional(blocked_thread.thread.0),
+                json::array(blocked_thread.holds.iter().map(ToString::to_string)),
+                json::array(blocked_thread.waits_for.iter().map(
+                    |(resource, transition, span)| format!(
+                        "{{\"resource\": {}, \"transition\": {}, \"span\": {}}}",
+                        json::string(&resource.to_string()),
+                        json::string(transition),
+                        json::span(span.as_ref())
+                    )
+                ))
+            )
+        })
+        .collect();
+    if !blocked.is_empty() {
+        writeln!(writer, "{}", blocked.join(",\n"))?;
+    }
+    writeln!(writer, "          ]")?;
+    writeln!(writer, "        }}")?;
+    write!(writer, "      }}")
+}
+
+/// Formats a translation error as a JSON object in a single line.
+fn error_json(error: &TranslationError) -> String {
+    format!(
+        "{{\"message\": {}, \"function\": {}, \"span\": {}}}",
+        json::string(&error.kind.to_string()),
+        json::optional(error.function.as_deref().map(json::string)),
+        json::span(error.span.as_ref())
+    )
+}
@@ -0,0 +1,80 @@
+//! Representation of a location in the source code.
+//!
+//! The spans of `rustc` (`rustc_span::Span`) are only meaningful while the compiler session is alive.
+//! `SourceSpan` stores the file name, the lines and the columns so that the location can be
+//! reported after the translation finished.
+
+/// A region of the source code, delimited by a start and an end position.
+/// Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceSpan {
+    /// The name of the file as shown in the `rustc` diagnostics.
+    pub file: String,
+    /// The line where the span starts.
+    pub start_line: usize,
+    /// The column where the span starts.
+    pub start_column: usize,
+    /// The line where the span ends.
+    pub end_line: usize,
+    /// The column where the span ends.
+    pub end_column: usize,
+}
+
+impl SourceSpan {
+    /// Converts a span of `rustc` to a `SourceSpan`.
+    /// Spans that originate in a macro expansion are mapped to the call site of the macro in the user code.
+    /// Returns `None` for dummy spans that do not point to any source file.
+    #[must_use]
+    pub fn from_span(span: rustc_span::Span, tcx: rustc_middle::ty::TyCtxt) -> Option<Self> {
+        let source_map = tcx.sess.source_map();
+        let span = span.source_callsite();
+        let (file, start_line, start_column, end_line, end_column) =
+            source_map.span_to_location_info(span);
+        let file = file?;
+        Some(Self {
+            file: source_map.filename_for_diagnostics(&file.name).to_string(),
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        })
+    }
+
+    /// Converts the `SourceSpan` back to a span of `rustc`.
+    /// Returns `None` if the file is not part of the current compiler session
+    /// or if the position lies outside of the file.
+    #[must_use]
+    pub fn to_span(&self, tcx: rustc_middle::ty::TyCtxt) -> Option<rustc_span::Span> {
+        let source_map = tcx.sess.source_map();
+        let file = source_map
+            .files()
+            .iter()
+            .find(|file| source_map.filename_for_diagnostics(&file.name).to_string() == self.file)?
+            .clone();
+        let lo = byte_position(&file, self.start_line, self.start_column)?;
+        let hi = byte_position(&file, self.end_line, self.end_column)?;
+        Some(rustc_span::Span::with_root_ctxt(lo, hi))
+    }
+}
+
+/// Converts a line and a column (both starting at 1) to an absolute position in the source map.
+/// The column counts characters, not bytes, like `rustc_span::source_map::SourceMap::lookup_char_pos`.
+fn byte_position(
+    file: &rustc_span::SourceFile,
+    line: usize,
+    column: usize,
+) -> Option<rustc_span::BytePos> {
+    let line_index = line.checked_sub(1)?;
+    let text = file.get_line(line_index)?;
+    let offset = text
+        .char_indices()
+        .nth(column.checked_sub(1)?)
+        .map_or(text.len(), |(offset, _)| offset);
+    Some(file.line_bounds(line_index).start + rustc_span::BytePos(u32::try_from(offset).ok()?))
+}
+
+impl std::fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.start_line, self.start_column)
+    }
+}
@@ -0,0 +1,590 @@
+//! Module that tries to prove that the Petri net is deadlock-free from its structure, without exploring the state space.
+//!
+//! In a deadlock, every transition has an empty place in its preset, while `PROGRAM_END` and `PROGRAM_PANIC` are empty.
+//! The empty places of such a marking form a siphon. The analysis searches for the sets of places
+//! that could be empty in a deadlock and rules out each of them with a fact that holds in every reachable marking:
+//!
+//! - A place invariant `y` with `y · M0 > 0`: the weighted sum of the tokens is constant,
+//!   so one of the places in its support is marked and the places marked cannot weigh more than `y · M0`.
+//!   The invariants are computed with the Farkas algorithm on the net without the transitions
+//!   that mark `PROGRAM_END` or `PROGRAM_PANIC`, since a marking reached after them is never a deadlock.
+//! - A trap marked initially: a trap never loses all its tokens, so it cannot be contained in the empty places.
+//!   The traps are computed on demand, as the maximal trap contained in a set of places that the invariants do not rule out.
+//!
+//! The search is a small DPLL procedure where every place is either empty or marked.
+//! If no set of empty places survives, the net is deadlock-free and the facts used are the certificate of the proof.
+//! Otherwise the analysis fails without a result: the conditions are sufficient but not necessary,
+//! e.g. a set of places may be ruled out only by the order in which the transitions fire.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::data_structures::petri_net_interface::PetriNet;
+use crate::naming::{PROGRAM_END, PROGRAM_PANIC};
+use crate::report::{PlaceInvariant, StructuralProof};
+
+/// The maximum number of rows kept by the Farkas algorithm before the invariants are given up.
+const MAX_FARKAS_ROWS: usize = 10_000;
+
+/// The maximum number of decisions of the search before the proof is given up.
+const MAX_DECISIONS: usize = 1_000_000;
+
+/// The maximum number of traps added to the search before the proof is given up.
+const MAX_TRAPS: usize = 1_000;
+
+/// The places and transitions of the net as indices, with the arcs of every transition.
+struct IndexedNet {
+    /// The labels of the places.
+    places: Vec<String>,
+    /// The initial marking of each place.
+    marking: Vec<u64>,
+    /// The indices of the places in the preset of each transition.
+    presets: Vec<Vec<usize>>,
+    /// The indices of the places in the postset of each transition.
+    postsets: Vec<Vec<usize>>,
+    /// The indices of the places that mark the end of the program, normal or after a `panic!`.
+    end_places: Vec<usize>,
+}
+
+impl IndexedNet {
+    fn new(net: &PetriNet) -> Self {
+        let mut indices = HashMap::new();
+        let mut places = Vec::new();
+        let mut marking = Vec::new();
+        let mut end_places = Vec::new();
+        for (index, (place_ref, place)) in net.places_iter().enumerate() {
+            indices.insert(place_ref.label().clone(), index);
+            places.push(place_ref.label().clone());
+            marking.push(
+                u64::try_from(place.marking())
+                    .expect("The marking of a place should fit in 64 bits"),
+            );
+            if place_ref.label() == PROGRAM_END || place_ref.label() == PROGRAM_PANIC {
+                end_places.push(index);
+            }
+        }
+        let mut presets = Vec::new();
+        let mut postsets = Vec::new();
+        for (_, transition) in net.transitions_iter() {
+            presets.push(
+                transition
+                    .get_preset()
+                    .iter()
+                    .map(|place_ref| indices[place_ref.label()])
+                    .collect(),
+            );
+            postsets.push(
+                transition
+                    .get_postset()
+                    .iter()
+                    .map(|place_ref| indices[place_ref.label()])
+                    .collect(),
+            );
+        }
+        Self {
+            places,
+            marking,
+            presets,
+            postsets,
+            end_places,
+        }
+    }
+}
+
+/// The weight of every place in the support of a place invariant.
+type Weights = Vec<(usize, u64)>;
+
+/// A fact that holds in every reachable marking where the program did not end.
+#[derive(Debug, Clone)]
+enum Fact {
+    /// The sum of the tokens in the places, multiplied by their weights, is always `tokens`.
+    Invariant { weights: Weights, tokens: u64 },
+    /// One of the places is always marked.
+    Trap(Vec<usize>),
+}
+
+/// Tries to prove that no deadlock is reachable in the net, as defined by `Property::Deadlock`.
+/// Returns the certificate of the proof, or `None` if the structure of the net does not suffice.
+#[must_use]
+pub fn prove_deadlock_freedom(net: &PetriNet) -> Option<StructuralProof> {
+    let net = IndexedNet::new(net);
+    let mut facts: Vec<Fact> = invariants(&net)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(weights, tokens)| Fact::Invariant { weights, tokens })
+        .collect();
+    let mut traps = 0;
+    loop {
+        let Ok(empty) = Search::new(&net, &facts).solve() else {
+            return None;
+        };
+        let Some(empty) = empty else {
+            break;
+        };
+        // The set of empty places is not ruled out by the facts known, it may be ruled out by a trap in it.
+        let trap = maximal_trap(&net, &empty);
+        if traps == MAX_TRAPS || !trap.iter().any(|place| net.marking[*place] > 0) {
+            return None;
+        }
+        facts.push(Fact::Trap(trap));
+        traps += 1;
+    }
+
+    // Keep only the facts needed for the proof, so that the certificate is as short as possible.
+    let mut index = 0;
+    while index < facts.len() {
+        let fact = facts.remove(index);
+        if !matches!(Search::new(&net, &facts).solve(), Ok(None)) {
+            facts.insert(index, fact);
+            index += 1;
+        }
+    }
+    let mut proof = StructuralProof {
+        invariants: Vec::new(),
+        traps: Vec::new(),
+    };
+    for fact in facts {
+        match fact {
+            Fact::Invariant { weights, tokens } => proof.invariants.push(PlaceInvariant {
+                weights: weights
+                    .into_iter()
+                    .map(|(place, weight)| (net.places[place].clone(), weight))
+                    .collect(),
+                tokens,
+            }),
+            Fact::Trap(places) => proof.traps.push(
+                places
+                    .into_iter()
+                    .map(|place| net.places[place].clone())
+                    .collect(),
+            ),
+        }
+    }
+    Some(proof)
+}
+
+/// Computes the minimal semi-positive place invariants of the net without the transitions
+/// that mark the end places, with the Farkas algorithm. Returns the weights of every invariant with its token count.
+/// Returns `None` if the number of intermediate rows exceeds `MAX_FARKAS_ROWS`.
+fn invariants(net: &IndexedNet) -> Option<Vec<(Weights, u64)>> {
+    // The end places must never lose their token, otherwise a deadlock could be reached after them.
+    let end_places_kept = net
+        .presets
+        .iter()
+        .flatten()
+        .any(|place| net.end_places.contains(place));
+    let transitions: Vec<usize> = (0..net.presets.len())
+        .filter(|transition| {
+            end_places_kept
+                || !net.postsets[*transition]
+                    .iter()
+                    .any(|place| net.end_places.contains(place))
+        })
+        .collect();
+    // Every row is the change of the weighted sum by each transition, with the weight of each place.
+    let mut rows: Vec<(Vec<i64>, BTreeMap<usize, i64>)> = (0..net.places.len())
+        .filter(|place| end_places_kept || !net.end_places.contains(place))
+        .map(|place| {
+            let changes = transitions
+                .iter()
+                .map(|transition| {
+                    i64::from(net.postsets[*transition].contains(&place))
+                        - i64::from(net.presets[*transition].contains(&place))
+                })
+                .collect();
+            (changes, BTreeMap::from([(place, 1)]))
+        })
+        .collect();
+
+    let mut remaining: BTreeSet<usize> = (0..transitions.len()).collect();
+    while !remaining.is_empty() {
+        // Eliminating the column with the fewest combinations first keeps the number of rows small.
+        let column = *remaining.iter().min_by_key(|column| {
+            let positive = rows
+                .iter()
+                .filter(|(changes, _)| changes[**column] > 0)
+                .count();
+            let negative = rows
+                .iter()
+                .filter(|(changes, _)| changes[**column] < 0)
+                .count();
+            positive.saturating_mul(negative)
+        })?;
+        remaining.remove(&column);
+
+        let (zero, nonzero): (Vec<_>, Vec<_>) = rows
+            .into_iter()
+            .partition(|(changes, _)| changes[column] == 0);
+        let (positive, negative): (Vec<_>, Vec<_>) = nonzero
+            .into_iter()
+            .partition(|(changes, _)| changes[column] > 0);
+        let mut combined = zero;
+        for positive_row in &positive {
+            for negative_row in &negative {
+                // A combination whose coefficients overflow is dropped, the other rows are still invariants.
+                let Some(row) = combine(positive_row, negative_row, column) else {
+                    continue;
+                };
+                combined.push(row);
+                if combined.len() > MAX_FARKAS_ROWS {
+                    return None;
+                }
+            }
+        }
+        rows = minimal_supports(combined);
+    }
+    Some(
+        rows.into_iter()
+            .filter_map(|(_, weights)| {
+                // An invariant whose weighted sum of the initial marking overflows is dropped.
+                let tokens = weights.iter().try_fold(0_u64, |tokens, (place, weight)| {
+                    tokens.checked_add(net.marking[*place].checked_mul(weight.unsigned_abs())?)
+                })?;
+                let weights = weights
+                    .into_iter()
+                    .map(|(place, weight)| (place, weight.unsigned_abs()))
+                    .collect();
+                Some((weights, tokens))
+            })
+            .collect(),
+    )
+}
+
+/// Combines a row with a positive change in the column with a row with a negative change,
+/// so that the change in the column cancels out, and divides the result by its greatest common divisor.
+/// Returns `None` if a coefficient overflows.
+fn combine(
+    (positive_changes, positive_weights): &(Vec<i64>, BTreeMap<usize, i64>),
+    (negative_changes, negative_weights): &(Vec<i64>, BTreeMap<usize, i64>),
+    column: usize,
+) -> Option<(Vec<i64>, BTreeMap<usize, i64>)> {
+    let factor_positive = negative_changes[column].checked_neg()?;
+    let factor_negative = positive_changes[column];
+    let changes = positive_changes
+        .iter()
+        .zip(negative_changes)
+        .map(|(a, b)| {
+            factor_positive
+                .checked_mul(*a)?
+                .checked_add(factor_negative.checked_mul(*b)?)
+        })
+        .collect::<Option<Vec<i64>>>()?;
+    let mut weights: BTreeMap<usize, i64> = BTreeMap::new();
+    for (factor, row_weights) in [
+        (factor_positive, positive_weights),
+        (factor_negative, negative_weights),
+    ] {
+        for (place, weight) in row_weights {
+            let sum = weights.entry(*place).or_default();
+            *sum = sum.checked_add(factor.checked_mul(*weight)?)?;
+        }
+    }
+    Some(normalize(changes, weights))
+}
+
+/// Divides the row by the greatest common divisor of its changes and weights.
+fn normalize(
+    mut changes: Vec<i64>,
+    mut weights: BTreeMap<usize, i64>,
+) -> (Vec<i64>, BTreeMap<usize, i64>) {
+    let divisor = changes
+        .iter()
+        .chain(weights.values())
+        .fold(0, |divisor, value| gcd(divisor, value.unsigned_abs()));
+    // A divisor of 2^63 does not fit in `i64`, the row is then kept as it is.
+    if let Ok(divisor) = i64::try_from(divisor)
+        && divisor > 1
+    {
+        for change in &mut changes {
+            *change /= divisor;
+        }
+        for weight in weights.values_mut() {
+            *weight /= divisor;
+        }
+    }
+    (changes, weights)
+}
+
+/// Returns the greatest common divisor of two integers.
+const fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Removes the rows whose support contains the support of another row, keeping one row per support.
+fn minimal_supports(
+    rows: Vec<(Vec<i64>, BTreeMap<usize, i64>)>,
+) -> Vec<(Vec<i64>, BTreeMap<usize, i64>)> {
+    let supports: Vec<BTreeSet<usize>> = rows
+        .iter()
+        .map(|(_, weights)| weights.keys().copied().collect())
+        .collect();
+    rows.into_iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            !supports.iter().enumerate().any(|(other, support)| {
+                other != *index
+                    && support.is_subset(&supports[*index])
+                    && (support.len() < supports[*index].len() || other < *index)
+            })
+        })
+        .map(|(_, row)| row)
+        .collect()
+}
+
+/// Returns the largest trap contained in the given places, i.e. the largest set of places
+/// such that every transition that takes a token from the set puts a token back in it.
+fn maximal_trap(net: &IndexedNet, places: &[usize]) -> Vec<usize> {
+    let mut trap: BTreeSet<usize> = places.iter().copied().collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (preset, postset) in net.presets.iter().zip(&net.postsets) {
+            if postset.iter().any(|place| trap.contains(place)) {
+                continue;
+            }
+            for place in preset {
+                changed |= trap.remove(place);
+            }
+        }
+    }
+    trap.into_iter().collect()
+}
+
+/// The search for a set of places that may be empty in a deadlock.
+/// A place is assigned `true` if it is empty and `false` if it is marked.
+struct Search<'a> {
+    net: &'a IndexedNet,
+    facts: &'a [Fact],
+    decisions: usize,
+}
+
+impl<'a> Search<'a> {
+    const fn new(net: &'a IndexedNet, facts: &'a [Fact]) -> Self {
+        Self {
+            net,
+            facts,
+            decisions: 0,
+        }
+    }
+
+    /// Returns the places that are empty in a marking where every transition is disabled and the facts hold,
+    /// or `None` if there is no such marking.
+    ///
+    /// # Errors
+    ///
+    /// If the search exceeds `MAX_DECISIONS`, then the function returns an error.
+    fn solve(&mut self) -> Result<Option<Vec<usize>>, ()> {
+        let mut assignment = vec![None; self.net.places.len()];
+        for place in &self.net.end_places {
+            assignment[*place] = Some(true);
+        }
+        Ok(self.search(assignment)?.map(|assignment| {
+            assignment
+                .into_iter()
+                .enumerate()
+                .filter(|(_, empty)| *empty == Some(true))
+                .map(|(place, _)| place)
+                .collect()
+        }))
+    }
+
+    /// Extends the assignment until every place is assigned, backtracking on conflicts.
+    fn search(
+        &mut self,
+        mut assignment: Vec<Option<bool>>,
+    ) -> Result<Option<Vec<Option<bool>>>, ()> {
+        if !self.propagate(&mut assignment) {
+            return Ok(None);
+        }
+        self.decisions += 1;
+        if self.decisions > MAX_DECISIONS {
+            return Err(());
+        }
+        // Branch on the input places of a transition that is not disabled yet.
+        let unsatisfied = self
+            .net
+            .presets
+            .iter()
+            .find(|preset| !preset.iter().any(|place| assignment[*place] == Some(true)));
+        let choices: Vec<(usize, bool)> = match unsatisfied {
+            Some(preset) => preset
+                .iter()
+                .filter(|place| assignment[**place].is_none())
+                .map(|place| (*place, true))
+                .collect(),
+            None => match assignment.iter().position(Option::is_none) {
+                Some(place) => vec![(place, true), (place, false)],
+                None => return Ok(Some(assignment)),
+            },
+        };
+        for (place, empty) in choices {
+            let mut next = assignment.clone();
+            next[place] = Some(empty);
+            if let Some(model) = self.search(next)? {
+                return Ok(Some(model));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Assigns the places forced by the transitions and the facts until nothing changes.
+    /// Returns `false` if the assignment violates one of them.
+    fn propagate(&self, assignment: &mut [Option<bool>]) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            // Every transition is disabled, i.e. one of its input places is empty.
+            for preset in &self.net.presets {
+                match at_least_one(assignment, preset, true) {
+                    Propagation::Conflict => return false,
+                    Propagation::Forced(place) => {
+                        assignment[place] = Some(true);
+                        changed = true;
+                    }
+                    Propagation::None => {}
+                }
+            }
+            for fact in self.facts {
+                match fact {
+                    Fact::Invariant { weights, tokens } => {
+                        let marked: u64 = weights
+                            .iter()
+                            .filter(|(place, _)| assignment[*place] == Some(false))
+                            .map(|(_, weight)| weight)
+                            .sum();
+                        if marked > *tokens {
+                            return false;
+                        }
+                        // Every marked place holds at least one token, so they cannot weigh more than the invariant.
+                        for (place, weight) in weights {
+                            if assignment[*place].is_none() && marked + weight > *tokens {
+                                assignment[*place] = Some(true);
+                                changed = true;
+                            }
+                        }
+                        if *tokens > 0 {
+                            let places: Vec<usize> =
+                                weights.iter().map(|(place, _)| *place).collect();
+                            match at_least_one(assignment, &places, false) {
+                                Propagation::Conflict => return false,
+                                Propagation::Forced(place) => {
+                                    assignment[place] = Some(false);
+                                    changed = true;
+                                }
+                                Propagation::None => {}
+                            }
+                        }
+                    }
+                    Fact::Trap(places) => match at_least_one(assignment, places, false) {
+                        Propagation::Conflict => return false,
+                        Propagation::Forced(place) => {
+                            assignment[place] = Some(false);
+                            changed = true;
+                        }
+                        Propagation::None => {}
+                    },
+                }
+            }
+        }
+        true
+    }
+}
+
+/// The result of checking that at least one place has a given value.
+enum Propagation {
+    /// No place has the value and no place is left to take it.
+    Conflict,
+    /// No place has the value and the given place is the only one left to take it.
+    Forced(usize),
+    /// The condition holds or more than one place can still take the value.
+    None,
+}
+
+/// Checks that at least one of the places is assigned `value`.
+fn at_least_one(assignment: &[Option<bool>], places: &[usize], value: bool) -> Propagation {
+    let mut unassigned = None;
+    let mut count = 0;
+    for place in places {
+        match assignment[*place] {
+            Some(assigned) if assigned == value => return Propagation::None,
+            Some(_) => {}
+            None => {
+                unassigned = Some(*place);
+                count += 1;
+            }
+        }
+    }
+    match (count, unassigned) {
+        (0, _) => Propagation::Conflict,
+        (1, Some(place)) => Propagation::Forced(place),
+        _ => Propagation::None,
+    }
+}
+
+#[cfg(test)]
+mod structural_tests {
+    use super::*;
+    use crate::naming::PROGRAM_START;
+    use crate::test_nets::net_with_mutex;
+
+    #[test]
+    fn lock_and_unlock_is_proven_deadlock_free() {
+        let proof = prove_deadlock_freedom(&net_with_mutex(false))
+            .expect("Locking and unlocking a mutex should never deadlock");
+
+        assert!(!proof.invariants.is_empty() || !proof.traps.is_empty());
+    }
+
+    #[test]
+    fn double_lock_is_not_proven_deadlock_free() {
+        assert!(prove_deadlock_freedom(&net_with_mutex(true)).is_none());
+    }
+
+    #[test]
+    fn invariants_of_the_control_flow_and_the_mutex_are_found() {
+        let net = IndexedNet::new(&net_with_mutex(false));
+        let invariants =
+            invariants(&net).expect("The net is small enough for the Farkas algorithm");
+        let supports: Vec<(BTreeSet<&str>, u64)> = invariants
+            .iter()
+            .map(|(weights, tokens)| {
+                let support = weights
+                    .iter()
+                    .map(|(place, _)| net.places[*place].as_str())
+                    .collect();
+                (support, *tokens)
+            })
+            .collect();
+
+        assert!(supports.contains(&(BTreeSet::from([PROGRAM_START, "main_BB1"]), 1)));
+        assert!(supports.contains(&(BTreeSet::from(["MUTEX_0", "main_BB1"]), 1)));
+    }
+
+    #[test]
+    fn combination_that_overflows_is_dropped() {
+        let positive = (vec![i64::MAX, 1], BTreeMap::from([(0, 1)]));
+        let negative = (vec![-2, 1], BTreeMap::from([(1, 1)]));
+
+        assert!(combine(&positive, &negative, 0).is_none());
+    }
+
+    #[test]
+    fn combination_is_divided_by_its_gcd() {
+        let positive = (vec![2, 0], BTreeMap::from([(0, 1)]));
+        let negative = (vec![-2, 0], BTreeMap::from([(1, 1)]));
+
+        assert_eq!(
+            combine(&positive, &negative, 0),
+            Some((vec![0, 0], BTreeMap::from([(0, 1), (1, 1)])))
+        );
+    }
+
+    #[test]
+    fn gcd_of_coefficients() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(7, 0), 7);
+        assert_eq!(gcd(0, 0), 0);
+    }
+}
@@ -0,0 +1,130 @@
+//! Small Petri nets shared by the unit tests of the analyses.
+//! They only contain the places and transitions that the tests need, not the full translation of a program.
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, PlaceRef, TransitionRef, add_arc_place_transition, add_arc_transition_place,
+};
+use crate::naming::basic_block::switch_int_transition_label;
+use crate::naming::mutex::place_label;
+use crate::naming::{PROGRAM_END, PROGRAM_PANIC, PROGRAM_START};
+use crate::refinement::{Assignment, BoolLocal, BranchConditions};
+
+/// The labels of the transitions that lock the mutex in the net created by `net_with_flag`.
+pub const LOCK: &str = "LOCK";
+const RELOCK: &str = "RELOCK";
+
+/// Adds a place with the given label and number of tokens.
+pub fn add_marked_place(net: &mut PetriNet, label: &str, tokens: usize) -> PlaceRef {
+    let place = net.add_place(label);
+    net.add_token(&place, tokens)
+        .expect("Adding a token to an empty place should not fail");
+    place
+}
+
+/// Adds a transition with the given label from the place `from` to the place `to`.
+pub fn connect(net: &mut PetriNet, from: &PlaceRef, to: &PlaceRef, label: &str) -> TransitionRef {
+    let transition = net.add_transition(label);
+    add_arc_place_transition(net, from, &transition);
+    add_arc_transition_place(net, &transition, to);
+    transition
+}
+
+/// Creates a net with the places `PROGRAM_START` (with one token), `PROGRAM_END` and `PROGRAM_PANIC`.
+/// Returns the net and the places `PROGRAM_START` and `PROGRAM_END`.
+pub fn net_with_program_places() -> (PetriNet, PlaceRef, PlaceRef) {
+    let mut net = PetriNet::new();
+    let program_end = net.add_place(PROGRAM_END);
+    net.add_place(PROGRAM_PANIC);
+    let program_start = add_marked_place(&mut net, PROGRAM_START, 1);
+    (net, program_start, program_end)
+}
+
+/// Creates a net where the main thread locks the mutex `MUTEX_0`, then locks it again
+/// if `double_lock` is set, or unlocks it otherwise, and ends.
+pub fn net_with_mutex(double_lock: bool) -> PetriNet {
+    let (mut net, program_start, program_end) = net_with_program_places();
+    let mutex = add_marked_place(&mut net, &place_label(0), 1);
+    let bb1 = net.add_place("main_BB1");
+
+    let lock = connect(&mut net, &program_start, &bb1, "LOCK_0");
+    add_arc_place_transition(&mut net, &mutex, &lock);
+    let second = connect(&mut net, &bb1, &program_end, "LOCK_1");
+    if double_lock {
+        add_arc_place_transition(&mut net, &mutex, &second);
+    } else {
+        add_arc_transition_place(&mut net, &second, &mutex);
+    }
+    net
+}
+
+/// Returns the label of the branch of `main` from the given block to the given block.
+pub fn branch(from: usize, to: usize) -> String {
+    switch_int_transition_label("main", from, to)
+}
+
+/// Creates a net where `main` assigns an unknown value to `flag` and locks the mutex `MUTEX_0` in `main_BB0`,
+/// unlocks it if `flag` is true in `main_BB1`, then locks it again in `main_BB4`
+/// if `flag` is true in `main_BB3`, or if it is false when `same_condition` is not set.
+/// With `same_condition`, the only deadlock takes contradicting branches.
+pub fn net_with_flag(same_condition: bool) -> (PetriNet, BranchConditions, BoolLocal) {
+    let flag = BoolLocal {
+        function: "main".to_string(),
+        local: 2,
+    };
+    let mut conditions = BranchConditions::new();
+    conditions.record_assignment("main_BB0".to_string(), flag.clone(), Assignment::Unknown);
+
+    let mut net = PetriNet::new();
+    let program_end = net.add_place(PROGRAM_END);
+    net.add_place(PROGRAM_PANIC);
+    let mutex = add_marked_place(&mut net, &place_label(0), 1);
+    let blocks: Vec<PlaceRef> = (0..6)
+        .map(|index| {
+            add_marked_place(
+                &mut net,
+                &format!("main_BB{index}"),
+                usize::from(index == 0),
+            )
+        })
+        .collect();
+
+    let lock = connect(&mut net, &blocks[0], &blocks[1], LOCK);
+    add_arc_place_transition(&mut net, &mutex, &lock);
+    // The branch to `main_BB2` unlocks the mutex and goes on directly to `main_BB3` for brevity.
+    for (to, value) in [(3, false), (2, true)] {
+        let label = branch(1, to);
+        let transition = connect(&mut net, &blocks[1], &blocks[3], &label);
+        if value {
+            add_arc_transition_place(&mut net, &transition, &mutex);
+        }
+        conditions.record_branch(label, flag.clone(), value);
+    }
+    for (to, value) in [(5, false), (4, true)] {
+        let label = branch(3, to);
+        connect(&mut net, &blocks[3], &blocks[to], &label);
+        conditions.record_branch(label, flag.clone(), value);
+    }
+    let (relock_block, end_block) = if same_condition { (4, 5) } else { (5, 4) };
+    let relock = connect(&mut net, &blocks[relock_block], &program_end, RELOCK);
+    add_arc_place_transition(&mut net, &mutex, &relock);
+    connect(&mut net, &blocks[end_block], &program_end, "END");
+    (net, conditions, flag)
+}
+
+/// Creates a net where thread 1 locks the mutex `MUTEX_0` and hands over the guard to the main thread,
+/// which drops it before the program ends.
+pub fn net_with_guard_handed_over() -> PetriNet {
+    let mut net = PetriNet::new();
+    let program_start = add_marked_place(&mut net, PROGRAM_START, 1);
+    let program_end = net.add_place(PROGRAM_END);
+    let thread_start = add_marked_place(&mut net, "THREAD_1_START", 1);
+    let mutex = add_marked_place(&mut net, &place_label(0), 1);
+    let handed_over = net.add_place("main_GUARD");
+
+    let lock = connect(&mut net, &thread_start, &handed_over, "LOCK");
+    add_arc_place_transition(&mut net, &mutex, &lock);
+    let unlock = connect(&mut net, &program_start, &program_end, "UNLOCK");
+    add_arc_place_transition(&mut net, &handed_over, &unlock);
+    add_arc_transition_place(&mut net, &unlock, &mutex);
+    net
+}
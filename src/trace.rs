@@ -0,0 +1,559 @@
+//! Human-readable trace of a witness path found by the model checker.
+//!
+//! A witness path is the sequence of transition labels that leads from the initial marking
+//! to the deadlock. The `Trace` replays it on the Petri net and uses the `NetMap`
+//! to attribute every step to a thread and to a location in the source code.
+//!
+//! Each step lists the synchronization primitives it touched:
+//! locking or unlocking a mutex, spawning or joining a thread, waiting on or notifying a condition variable.
+//! The trace ends with the blocked state, i.e. which mutexes each thread holds
+//! and what each thread waits for.
+//!
+//! The synchronization primitives are recognized by the labels defined in the `naming` module,
+//! e.g. `MUTEX_0`, `CONDVAR_1_NOTIFY` or `THREAD_2_END`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::data_structures::petri_net_interface::{PetriNet, PlaceRef};
+use crate::naming::condvar::{place_labels, transition_labels};
+use crate::naming::{PROGRAM_END, PROGRAM_PANIC, PROGRAM_START, mutex, thread};
+use crate::net_map::NetMap;
+use crate::source_span::SourceSpan;
+
+/// A thread of the program. The main thread has no index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadId(pub Option<usize>);
+
+impl std::fmt::Display for ThreadId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(index) => write!(f, "thread {index}"),
+            None => write!(f, "main thread"),
+        }
+    }
+}
+
+/// An interaction of a step with a synchronization primitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncAction {
+    /// Locks the mutex with the given index.
+    Lock(usize),
+    /// Unlocks the mutex with the given index.
+    Unlock(usize),
+    /// Sets the condition stored in the mutex with the given index.
+    SetCondition(usize),
+    /// Spawns the thread with the given index.
+    Spawn(usize),
+    /// Joins the thread with the given index.
+    Join(usize),
+    /// Starts waiting on the condition variable with the given index.
+    Wait(usize),
+    /// Skips the wait on the condition variable with the given index because the condition is set.
+    SkipWait(usize),
+    /// Notifies the condition variable with the given index.
+    Notify(usize),
+    /// Wakes up after a notification on the condition variable with the given index.
+    WakeUp(usize),
+    /// Loses a notification on the condition variable with the given index, since no thread was waiting.
+    LostSignal(usize),
+}
+
+impl std::fmt::Display for SyncAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lock(index) => write!(f, "locks mutex {index}"),
+            Self::Unlock(index) => write!(f, "unlocks mutex {index}"),
+            Self::SetCondition(index) => write!(f, "sets the condition of mutex {index}"),
+            Self::Spawn(index) => write!(f, "spawns thread {index}"),
+            Self::Join(index) => write!(f, "joins thread {index}"),
+            Self::Wait(index) => write!(f, "waits on condvar {index}"),
+            Self::SkipWait(index) => write!(f, "skips the wait on condvar {index}"),
+            Self::Notify(index) => write!(f, "notifies condvar {index}"),
+            Self::WakeUp(index) => write!(f, "wakes up from condvar {index}"),
+            Self::LostSignal(index) => write!(f, "loses a signal on condvar {index}"),
+        }
+    }
+}
+
+/// A resource that a blocked thread waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Resource {
+    /// The mutex with the given index.
+    Mutex(usize),
+    /// The condition stored in the mutex with the given index.
+    Condition(usize),
+    /// A notification on the condition variable with the given index.
+    Condvar(usize),
+    /// The end of the thread with the given index.
+    Thread(usize),
+}
+
+impl std::fmt::Display for Resource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mutex(index) => write!(f, "mutex {index}"),
+            Self::Condition(index) => write!(f, "the condition of mutex {index}"),
+            Self::Condvar(index) => write!(f, "a notification on condvar {index}"),
+            Self::Thread(index) => write!(f, "thread {index} to finish"),
+        }
+    }
+}
+
+/// A step of the trace, i.e. the firing of one transition.
+#[derive(Debug, Clone)]
+pub struct TraceStep {
+    /// The label of the transition fired.
+    pub transition: String,
+    /// The thread that fired the transition.
+    pub thread: ThreadId,
+    /// The location in the source code that originated the transition.
+    pub span: Option<SourceSpan>,
+    /// The line of source code at `span`, if the file can be read.
+    pub source_line: Option<String>,
+    /// The interactions with synchronization primitives.
+    pub actions: Vec<SyncAction>,
+}
+
+/// A thread that cannot progress in the final state of the trace.
+#[derive(Debug, Clone)]
+pub struct BlockedThread {
+    /// The thread that is blocked.
+    pub thread: ThreadId,
+    /// The mutexes that the thread holds.
+    pub holds: Vec<usize>,
+    /// The resources that the thread waits for, with the label of the blocked transition
+    /// and its location in the source code.
+    pub waits_for: Vec<(Resource, String, Option<SourceSpan>)>,
+}
+
+/// The trace of a witness path, replayed on the Petri net.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    /// The steps in the order in which the transitions fire.
+    pub steps: Vec<TraceStep>,
+    /// The threads that are blocked in the final state.
+    pub blocked: Vec<BlockedThread>,
+    /// The locations where the mutexes, the condition variables and the threads were created.
+    pub resources: BTreeMap<String, Option<SourceSpan>>,
+}
+
+impl Trace {
+    /// Replays the witness path on the net, starting from the initial marking.
+    ///
+    /// # Errors
+    ///
+    /// If a transition of the witness path does not exist in the net or is not enabled when it should fire,
+    /// then an error describing the problem is returned.
+    pub fn new(witness_path: &[String], net: &PetriNet, net_map: &NetMap) -> Result<Self, String> {
+        let mut marking: BTreeMap<PlaceRef, usize> = net
+            .places_iter()
+            .map(|(place_ref, place)| (place_ref.clone(), place.marking()))
+            .collect();
+        let transitions: HashMap<&String, _> = net
+            .transitions_iter()
+            .map(|(transition_ref, transition)| (transition_ref.label(), transition))
+            .collect();
+
+        let mut holders: BTreeMap<usize, ThreadId> = BTreeMap::new();
+        // The threads waiting on each condition variable, with the location of the wait.
+        let mut waiting: BTreeMap<usize, (ThreadId, Option<SourceSpan>)> = BTreeMap::new();
+        let mut source_files = SourceFiles::default();
+        let mut steps = Vec::new();
+        for label in witness_path {
+            let transition = transitions
+                .get(label)
+                .ok_or_else(|| format!("the transition `{label}` does not exist in the net"))?;
+            for place_ref in transition.get_preset() {
+                let tokens = marking.entry(place_ref.clone()).or_default();
+                if *tokens == 0 {
+                    return Err(format!(
+                        "the transition `{label}` is not enabled, the place `{}` is empty",
+                        place_ref.label()
+                    ));
+                }
+                *tokens -= 1;
+            }
+            for place_ref in transition.get_postset() {
+                *marking.entry(place_ref.clone()).or_default() += 1;
+            }
+
+            let mut thread = transition
+                .get_preset()
+                .iter()
+                .find_map(|place_ref| control_place_thread(place_ref.label(), net_map))
+                .unwrap_or(ThreadId(None));
+            let mut span = net_map
+                .transition_origin(label)
+                .and_then(|origin| origin.span.clone());
+            let actions = sync_actions(label, transition.get_preset(), transition.get_postset());
+            for action in &actions {
+                match action {
+                    SyncAction::Wait(index) => {
+                        waiting.insert(*index, (thread, span.clone()));
+                    }
+                    // The thread that wakes up is the one that waits, at the same location.
+                    SyncAction::WakeUp(index) => {
+                        if let Some((waiting_thread, wait_span)) = waiting.remove(index) {
+                            thread = waiting_thread;
+                            span = wait_span;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            for action in &actions {
+                match action {
+                    SyncAction::Lock(index) => {
+                        holders.insert(*index, thread);
+                    }
+                    SyncAction::Unlock(index) => {
+                        holders.remove(index);
+                    }
+                    _ => {}
+                }
+            }
+            let source_line = span.as_ref().and_then(|span| source_files.line(span));
+            steps.push(TraceStep {
+                transition: label.clone(),
+                thread,
+                span,
+                source_line,
+                actions,
+            });
+        }
+
+        let blocked = blocked_threads(net, net_map, &marking, &holders, &waiting);
+        let resources = net_map
+            .places()
+            .filter(|(label, _)| resource_creation_label(label))
+            .map(|(label, origin)| (label.clone(), origin.and_then(|origin| origin.span.clone())))
+            .collect();
+        Ok(Self {
+            steps,
+            blocked,
+            resources,
+        })
+    }
+}
+
+impl Trace {
+    /// Returns the location where the synchronization primitive or the thread of the resource was created.
+    #[must_use]
+    pub fn creation_span(&self, resource: Resource) -> Option<&SourceSpan> {
+        let label = match resource {
+            Resource::Mutex(index) | Resource::Condition(index) => mutex::place_label(index),
+            Resource::Condvar(index) => place_labels(index).1,
+            Resource::Thread(index) => thread::start_place_label(index),
+        };
+        self.resources.get(&label)?.as_ref()
+    }
+
+    /// Formats the synchronization primitives and the steps of the trace under the given title,
+    /// without the blocked state. Used for witnesses whose final state is not a deadlock.
+    #[must_use]
+    pub fn steps_to_string(&self, title: &str) -> String {
+        let mut output = String::new();
+        self.write_steps(&mut output, title)
+            .expect("BUG: Writing to a `String` should not fail");
+        output
+    }
+
+    /// Writes the synchronization primitives and the steps of the trace under the given title.
+    fn write_steps<W: std::fmt::Write>(&self, f: &mut W, title: &str) -> std::fmt::Result {
+        if !self.resources.is_empty() {
+            writeln!(f, "Synchronization primitives:")?;
+            for (label, span) in &self.resources {
+                write!(f, "  - {}", resource_name(label))?;
+                if let Some(span) = span {
+                    write!(f, " created at {span}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        writeln!(f, "{title}:")?;
+        let thread_width = self
+            .steps
+            .iter()
+            .map(|step| step.thread.to_string().len())
+            .max()
+            .unwrap_or_default();
+        let number_width = self.steps.len().to_string().len();
+        let mut previous: Option<&TraceStep> = None;
+        for (number, step) in self.steps.iter().enumerate() {
+            // Collapse consecutive steps of the same thread at the same location,
+            // e.g. the several calls of a `println!`, unless they touch a synchronization primitive.
+            let is_repeated = previous.is_some_and(|previous| {
+                previous.thread == step.thread && previous.span == step.span
+            });
+            previous = Some(step);
+            if is_repeated && step.actions.is_empty() {
+                continue;
+            }
+            write!(
+                f,
+                "  {:>number_width$}. [{:<thread_width$}] ",
+                number + 1,
+                step.thread.to_string()
+            )?;
+            match &step.span {
+                Some(span) => write!(f, "{span}")?,
+                None => write!(f, "{}", step.transition)?,
+            }
+            if !step.actions.is_empty() {
+                let actions: Vec<String> = step.actions.iter().map(ToString::to_string).collect();
+                write!(f, ": {}", actions.join(", "))?;
+            }
+            if let Some(source_line) = &step.source_line {
+                write!(f, " | {source_line}")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_steps(f, "Trace to the deadlock")?;
+        writeln!(f, "Blocked state:")?;
+        for blocked_thread in &self.blocked {
+            write!(f, "  - {}", blocked_thread.thread)?;
+            if !blocked_thread.holds.is_empty() {
+                let holds: Vec<String> = blocked_thread
+                    .holds
+                    .iter()
+                    .map(|index| format!("mutex {index}"))
+                    .collect();
+                write!(f, " holds {} and", holds.join(", "))?;
+            }
+            let waits_for: Vec<String> = blocked_thread
+                .waits_for
+                .iter()
+                .map(|(resource, transition, span)| {
+                    span.as_ref().map_or_else(
+                        || format!("{resource} in {transition}"),
+                        |span| format!("{resource} at {span}"),
+                    )
+                })
+                .collect();
+            writeln!(f, " waits for {}", waits_for.join(" or "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Caches the lines of the source files read so far.
+#[derive(Default)]
+pub(crate) struct SourceFiles {
+    files: HashMap<String, Option<Vec<String>>>,
+}
+
+impl SourceFiles {
+    /// Returns the trimmed line of source code where the span starts,
+    /// or `None` if the file cannot be read.
+    pub(crate) fn line(&mut self, span: &SourceSpan) -> Option<String> {
+        let lines = self.files.entry(span.file.clone()).or_insert_with(|| {
+            std::fs::read_to_string(&span.file)
+                .ok()
+                .map(|contents| contents.lines().map(ToString::to_string).collect())
+        });
+        lines
+            .as_ref()?
+            .get(span.start_line.checked_sub(1)?)
+            .map(|line| line.trim().to_string())
+    }
+}
+
+/// Parses the index in a label of the form `{prefix}{index}{suffix}`, e.g. `MUTEX_0` or `THREAD_1_END`.
+fn parse_index(label: &str, prefix: &str, suffix: &str) -> Option<usize> {
+    label
+        .strip_prefix(prefix)?
+        .strip_suffix(suffix)?
+        .parse()
+        .ok()
+}
+
+/// Checks whether the place models a synchronization primitive instead of the control flow of a thread.
+fn is_resource_place(label: &str) -> bool {
+    label.starts_with("MUTEX_")
+        || label.starts_with("CONDVAR_")
+        || parse_index(label, "THREAD_", "_END").is_some()
+}
+
+/// Checks whether the place is the one created together with a synchronization primitive or a thread.
+fn resource_creation_label(label: &str) -> bool {
+    parse_index(label, "MUTEX_", "").is_some()
+        || parse_index(label, "CONDVAR_", "_NOTIFY").is_some()
+        || parse_index(label, "THREAD_", "_START").is_some()
+}
+
+/// Returns a readable name for a place created together with a synchronization primitive or a thread.
+fn resource_name(label: &str) -> String {
+    if let Some(index) = parse_index(label, "MUTEX_", "") {
+        return format!("mutex {index}");
+    }
+    if let Some(index) = parse_index(label, "CONDVAR_", "_NOTIFY") {
+        return format!("condvar {index}");
+    }
+    if let Some(index) = parse_index(label, "THREAD_", "_START") {
+        return format!("thread {index}");
+    }
+    label.to_string()
+}
+
+/// Returns the thread whose control flow the place models.
+/// Returns `None` if the place models a synchronization primitive.
+pub(crate) fn control_place_thread(label: &str, net_map: &NetMap) -> Option<ThreadId> {
+    if is_resource_place(label) {
+        return None;
+    }
+    if let Some(index) = parse_index(label, "THREAD_", "_START") {
+        return Some(ThreadId(Some(index)));
+    }
+    if [PROGRAM_START, PROGRAM_END, PROGRAM_PANIC].contains(&label) {
+        return Some(ThreadId(None));
+    }
+    Some(ThreadId(
+        net_map.place_origin(label).and_then(|origin| origin.thread),
+    ))
+}
+
+/// Finds the interactions of a transition with the synchronization primitives,
+/// based on the label of the transition and the places connected to it.
+pub(crate) fn sync_actions(
+    label: &str,
+    preset: &BTreeSet<PlaceRef>,
+    postset: &BTreeSet<PlaceRef>,
+) -> Vec<SyncAction> {
+    let mut actions = Vec::new();
+    if let Some(index) = parse_index(label, "CONDVAR_", "_WAIT_START") {
+        actions.push(SyncAction::Wait(index));
+    }
+    if let Some(index) = parse_index(label, "CONDVAR_", "_WAIT_SKIP") {
+        actions.push(SyncAction::SkipWait(index));
+    }
+    if let Some(index) = parse_index(label, "CONDVAR_", "_NOTIFY_RECEIVED") {
+        actions.push(SyncAction::WakeUp(index));
+    }
+    if let Some(index) = parse_index(label, "CONDVAR_", "_LOST_SIGNAL") {
+        actions.push(SyncAction::LostSignal(index));
+    }
+    for place_ref in preset {
+        let place_label = place_ref.label();
+        if let Some(index) = parse_index(place_label, "MUTEX_", "")
+            && !postset.contains(place_ref)
+        {
+            actions.push(SyncAction::Lock(index));
+        }
+        if let Some(index) = parse_index(place_label, "MUTEX_", "_CONDITION_NOT_SET")
+            && !postset.contains(place_ref)
+        {
+            actions.push(SyncAction::SetCondition(index));
+        }
+        if let Some(index) = parse_index(place_label, "THREAD_", "_END") {
+            actions.push(SyncAction::Join(index));
+        }
+    }
+    for place_ref in postset {
+        let place_label = place_ref.label();
+        if let Some(index) = parse_index(place_label, "MUTEX_", "")
+            && !preset.contains(place_ref)
+        {
+            actions.push(SyncAction::Unlock(index));
+        }
+        if let Some(index) = parse_index(place_label, "THREAD_", "_START") {
+            actions.push(SyncAction::Spawn(index));
+        }
+        if let Some(index) = parse_index(place_label, "CONDVAR_", "_NOTIFY") {
+            actions.push(SyncAction::Notify(index));
+        }
+    }
+    actions
+}
+
+/// Maps an empty place in the preset of a blocked transition to the resource it models.
+fn waited_resource(label: &str) -> Option<Resource> {
+    if let Some(index) = parse_index(label, "MUTEX_", "") {
+        return Some(Resource::Mutex(index));
+    }
+    if let Some(index) = parse_index(label, "MUTEX_", "_CONDITION_SET") {
+        return Some(Resource::Condition(index));
+    }
+    if let Some(index) = parse_index(label, "CONDVAR_", "_NOTIFY") {
+        return Some(Resource::Condvar(index));
+    }
+    if let Some(index) = parse_index(label, "THREAD_", "_END") {
+        return Some(Resource::Thread(index));
+    }
+    None
+}
+
+/// Finds the threads that cannot progress in the final marking.
+/// A thread is blocked if one of its control places is marked
+/// and the transitions leaving it wait for a synchronization primitive.
+fn blocked_threads(
+    net: &PetriNet,
+    net_map: &NetMap,
+    marking: &BTreeMap<PlaceRef, usize>,
+    holders: &BTreeMap<usize, ThreadId>,
+    waiting: &BTreeMap<usize, (ThreadId, Option<SourceSpan>)>,
+) -> Vec<BlockedThread> {
+    let mut blocked: BTreeMap<ThreadId, BlockedThread> = BTreeMap::new();
+    let new_blocked_thread = |thread: ThreadId| BlockedThread {
+        thread,
+        holds: holders
+            .iter()
+            .filter(|(_, holder)| **holder == thread)
+            .map(|(index, _)| *index)
+            .collect(),
+        waits_for: Vec::new(),
+    };
+    // The threads waiting on a condition variable do not have a marked control place.
+    for (index, (thread, span)) in waiting {
+        blocked
+            .entry(*thread)
+            .or_insert_with(|| new_blocked_thread(*thread))
+            .waits_for
+            .push((
+                Resource::Condvar(*index),
+                wait_start_label(*index),
+                span.clone(),
+            ));
+    }
+    for (transition_ref, transition) in net.transitions_iter() {
+        let preset = transition.get_preset();
+        let Some(thread) = preset
+            .iter()
+            .filter(|place_ref| marking.get(*place_ref).copied().unwrap_or_default() > 0)
+            .find_map(|place_ref| control_place_thread(place_ref.label(), net_map))
+        else {
+            continue;
+        };
+        let span = net_map
+            .transition_origin(transition_ref.label())
+            .and_then(|origin| origin.span.clone());
+        for place_ref in preset {
+            if marking.get(place_ref).copied().unwrap_or_default() > 0 {
+                continue;
+            }
+            let Some(resource) = waited_resource(place_ref.label()) else {
+                continue;
+            };
+            let blocked_thread = blocked
+                .entry(thread)
+                .or_insert_with(|| new_blocked_thread(thread));
+            blocked_thread
+                .waits_for
+                .push((resource, transition_ref.label().clone(), span.clone()));
+        }
+    }
+    blocked.into_values().collect()
+}
+
+/// Returns the label of the transition that starts the wait on the condition variable with the given index.
+fn wait_start_label(index: usize) -> String {
+    let (wait_start, _, _) = transition_labels(index);
+    wait_start
+}
@@ -25,23 +25,31 @@
 //! A `HashMapCounter` keeps track of how many time each function name has been seen so far.
 //! After every call the counter for the corresponding function is incremented.
 
+mod branch;
 mod function;
 mod mir_function;
 mod mir_visitor;
 mod special_function;
+mod support_check;
 mod sync;
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use rustc_middle::mir::UnwindAction;
 use rustc_middle::mir::visit::Visitor;
 use std::collections::{BinaryHeap, VecDeque};
 use std::rc::Rc;
 
+use crate::Translation;
 use crate::data_structures::hash_map_counter::HashMapCounter;
 use crate::data_structures::petri_net_interface::{PetriNet, PlaceRef, connect_places};
 use crate::data_structures::stack::Stack;
+use crate::error::{TranslationError, TranslationErrorKind};
 use crate::naming::function::{indexed_mir_function_cleanup_label, indexed_mir_function_name};
 use crate::naming::{PROGRAM_END, PROGRAM_PANIC, PROGRAM_START};
+use crate::net_map::{NetElementOrigin, NetMap};
+use crate::reduction::{Reduction, reduce};
+use crate::refinement::BranchConditions;
+use crate::source_span::SourceSpan;
 use crate::translator::mir_function::memory::Single;
 use crate::utils::{extract_def_id_of_called_function_from_operand, extract_nth_argument_as_place};
 use function::{Places, PostprocessingTask, Transitions};
@@ -54,6 +62,8 @@ use special_function::{
 use sync::thread::Thread;
 use sync::{check_if_mutex_variable, mutex};
 
+pub use support_check::check_support;
+
 /// The central data structure and coordinator for the translation.
 pub struct Translator<'tcx> {
     /// The global typing context that enables interaction with `rustc` during the translation.
@@ -78,6 +88,25 @@ pub struct Translator<'tcx> {
     /// Translation tasks performed after all threads have been translated.
     /// These tasks usually require to make changes to the final Petri net.
     postprocessing: BinaryHeap<PostprocessingTask>,
+    /// The first error found during the translation.
+    /// Once it is set, the rest of the MIR is skipped and the translation stops.
+    error: Option<TranslationError>,
+    /// If set, unsupported constructs are over-approximated instead of stopping the translation.
+    lenient: bool,
+    /// The unsupported constructs that were over-approximated in lenient mode.
+    approximations: Vec<TranslationError>,
+    /// If set, the net is reduced after the postprocessing.
+    reduce: bool,
+    /// The mapping of the reduced net back to the original net, if the net was reduced.
+    reduction: Option<Reduction>,
+    /// The origin of every place and transition added to the net so far.
+    net_map: NetMap,
+    /// The MIR location being translated, assigned as origin to the new places and transitions.
+    origin: Option<NetElementOrigin>,
+    /// The index of the thread being translated, `None` for the main thread.
+    thread: Option<usize>,
+    /// The branches on boolean locals and the assignments to them recorded so far.
+    branches: BranchConditions,
 }
 
 impl<'tcx> Translator<'tcx> {
@@ -85,7 +114,9 @@ impl<'tcx> Translator<'tcx> {
     /// Requires a global typing context `rustc_middle::ty::TyCtxt`, the main data structure of the compiler.
     /// The initial Petri net contains three places representing the program start state,
     /// the program end state and the abnormal end state after `panic!()`.
-    pub fn new(tcx: rustc_middle::ty::TyCtxt<'tcx>) -> Self {
+    /// In lenient mode, unsupported constructs are replaced by opaque transitions.
+    /// If `reduce` is set, the structural reduction is applied to the net at the end of the translation.
+    pub fn new(tcx: rustc_middle::ty::TyCtxt<'tcx>, lenient: bool, reduce: bool) -> Self {
         let mut net = PetriNet::new();
         let program_panic = net.add_place(PROGRAM_PANIC);
         let program_end = net.add_place(PROGRAM_END);
@@ -104,29 +135,42 @@ impl<'tcx> Translator<'tcx> {
             function_counter: HashMapCounter::new(),
             threads: VecDeque::new(),
             postprocessing: BinaryHeap::new(),
+            error: None,
+            lenient,
+            approximations: Vec::new(),
+            reduce,
+            reduction: None,
+            net_map: NetMap::new(),
+            origin: None,
+            thread: None,
+            branches: BranchConditions::new(),
         }
     }
 
-    /// Returns the result of the translation, i.e. the Petri net.
+    /// Returns the result of the translation, i.e. the Petri net, the approximations made
+    /// and the mapping of the net back to the source code.
     /// The ownership is transferred to the caller.
-    pub fn get_result(&mut self) -> PetriNet {
-        std::mem::take(&mut self.net)
+    pub fn get_result(&mut self) -> Translation {
+        Translation {
+            net: std::mem::take(&mut self.net),
+            approximations: std::mem::take(&mut self.approximations),
+            net_map: std::mem::take(&mut self.net_map),
+            reduction: self.reduction.take(),
+            branches: std::mem::take(&mut self.branches),
+        }
     }
 
     /// Translates the source code to a Petri net.
     ///
     /// # Errors
     ///
-    /// If the translation fails due to a recoverable error, then an error message is set.
-    ///
-    /// # Panics
-    ///
-    /// If the translation fails due to an unsupported feature present in the code, then the function panics.
-    pub fn run(&mut self) {
-        let (main_function_id, _) = self
-            .tcx
-            .entry_fn(())
-            .expect("ERROR: No main function found in the source code");
+    /// If the source code does not contain a main function, then an error is returned.
+    /// If the translation fails due to an unsupported feature present in the code,
+    /// then the error describing it and its location is returned.
+    pub fn run(&mut self) -> Result<(), TranslationError> {
+        let Some((main_function_id, _)) = self.tcx.entry_fn(()) else {
+            return Err(TranslationError::new(TranslationErrorKind::NoEntryPoint));
+        };
         let function_name = self.tcx.def_path_str(main_function_id);
         let function = MirFunction::new(
             main_function_id,
@@ -139,8 +183,59 @@ impl<'tcx> Translator<'tcx> {
         self.translate_top_call_stack();
         info!("Finished translating the main thread");
         self.translate_threads();
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
         info!("Running translation postprocessing...");
         self.translation_postprocessing();
+        // The elements added outside of a MIR location have no origin.
+        self.net_map.record_new_elements(&self.net, None);
+        if self.reduce {
+            let (net, reduction) = reduce(std::mem::take(&mut self.net));
+            self.net = net;
+            self.reduction = Some(reduction);
+        }
+        Ok(())
+    }
+
+    /// Reports an unsupported construct found while translating the function on the top of the call stack.
+    ///
+    /// In lenient mode, the construct is recorded as an approximation and `true` is returned.
+    /// The caller must then replace the construct with an opaque subnet and continue.
+    ///
+    /// Otherwise the error is recorded and `false` is returned, the translation stops.
+    /// Only the first error is kept, since the following ones are usually a consequence of it.
+    fn report_unsupported(&mut self, kind: TranslationErrorKind, span: rustc_span::Span) -> bool {
+        let function = self.call_stack.peek();
+        let span = SourceSpan::from_span(span, self.tcx);
+        let error = TranslationError::located(kind, function.name.clone(), span);
+        if self.lenient {
+            warn!("Over-approximating an unsupported construct: {error}");
+            self.approximations.push(error);
+            return true;
+        }
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+        false
+    }
+
+    /// Records the places and transitions added to the net since the previous MIR location.
+    /// Then sets the given location of the function on the top of the call stack
+    /// as the origin of the places and transitions added next.
+    fn enter_location(&mut self, location: rustc_middle::mir::Location) {
+        self.net_map
+            .record_new_elements(&self.net, self.origin.as_ref());
+        let def_id = self.call_stack.peek().def_id;
+        let body = self.tcx.optimized_mir(def_id);
+        self.origin = Some(NetElementOrigin {
+            function: self.tcx.def_path_str(def_id),
+            def_id: format!("{def_id:?}"),
+            thread: self.thread,
+            basic_block: location.block.index(),
+            statement_index: location.statement_index,
+            span: SourceSpan::from_span(body.source_info(location).span, self.tcx),
+        });
     }
 
     /// Main translation loop for the threads.
@@ -150,9 +245,13 @@ impl<'tcx> Translator<'tcx> {
     /// since abnormal thread termination does not affect the main thread.
     fn translate_threads(&mut self) {
         while let Some(thread) = self.threads.pop_front() {
+            if self.error.is_some() {
+                return;
+            }
             let index = thread.index;
 
             info!("Starting translating thread {index}");
+            self.thread = Some(index);
             // Replace the panic place so that unwind transitions and similar point to the thread's end place.
             self.program_panic = thread.clone_end_place();
             thread.create_arcs_for_transitions(&mut self.net);
@@ -209,7 +308,12 @@ impl<'tcx> Translator<'tcx> {
         let body = self.tcx.optimized_mir(function.def_id);
         // Visit the MIR body of the function using the methods of `rustc_middle::mir::visit::Visitor`.
         // <https://doc.rust-lang.org/stable/nightly-rustc/rustc_middle/mir/visit/trait.Visitor.html>
+        let caller_origin = self.origin.take();
         self.visit_body(body);
+        // The elements added after returning belong to the caller location again.
+        self.net_map
+            .record_new_elements(&self.net, self.origin.as_ref());
+        self.origin = caller_origin;
         let function = self.call_stack.peek();
         let value = function.memory.get_return_value();
         // Finished processing this function.
@@ -232,6 +336,11 @@ impl<'tcx> Translator<'tcx> {
     ///
     /// This is the handler for the enum variant `TerminatorKind::Call` in the MIR Visitor.
     /// <https://doc.rust-lang.org/stable/nightly-rustc/rustc_middle/mir/enum.TerminatorKind.html#variant.Call>
+    ///
+    /// # Errors
+    ///
+    /// If the called function cannot be resolved or the call cannot be translated, then an error is returned.
+    /// The handlers fail before adding any transition, so the caller may replace the call with its own.
     fn call_function(
         &mut self,
         func: &rustc_middle::mir::Operand<'tcx>,
@@ -239,10 +348,13 @@ impl<'tcx> Translator<'tcx> {
         destination: rustc_middle::mir::Place<'tcx>,
         target: Option<rustc_middle::mir::BasicBlock>,
         unwind: UnwindAction,
-    ) -> Option<Value> {
+    ) -> Result<Option<Value>, TranslationErrorKind> {
         let current_function = self.call_stack.peek_mut();
-        let function_def_id =
-            extract_def_id_of_called_function_from_operand(func, current_function.def_id, self.tcx);
+        let function_def_id = extract_def_id_of_called_function_from_operand(
+            func,
+            current_function.def_id,
+            self.tcx,
+        )?;
         let function_name = self.tcx.def_path_str(function_def_id);
         let start_place = current_function.get_start_place_for_function_call();
         info!("Encountered function call: {function_name}");
@@ -314,7 +426,7 @@ impl<'tcx> Translator<'tcx> {
                 } else {
                     call_diverging_function(&start_place, &function_name, &mut self.net);
                 }
-                return None; // Diverging function do not return a value
+                return Ok(None); // Diverging function do not return a value
             }
             (Some(return_block), UnwindAction::Unreachable) => {
                 // Support the unreachable case simply by matching the cleanup place to the program end place.
@@ -347,6 +459,7 @@ impl<'tcx> Translator<'tcx> {
             destination,
             places,
         );
+        // Increment the counter even if the call failed, the labels created so far must stay unique.
         self.function_counter.increment(&function_name);
         return_value
     }
@@ -356,6 +469,10 @@ impl<'tcx> Translator<'tcx> {
     /// supported synchronization or multithreading functions,
     /// then if the function is a foreign function call and
     /// lastly handle the standard MIR function case.
+    ///
+    /// # Errors
+    ///
+    /// If the handler cannot translate the call, then an error is returned.
     pub fn translate_function_call(
         &mut self,
         function_def_id: rustc_hir::def_id::DefId,
@@ -363,10 +480,10 @@ impl<'tcx> Translator<'tcx> {
         args: &[rustc_span::Spanned<rustc_middle::mir::Operand<'tcx>>],
         destination: rustc_middle::mir::Place<'tcx>,
         places: Places,
-    ) -> Option<Value> {
+    ) -> Result<Option<Value>, TranslationErrorKind> {
         // Special cases
         if function_name == "std::mem::drop" {
-            return self.call_mem_drop(function_name, args, places);
+            return Ok(self.call_mem_drop(function_name, args, places));
         }
         if (function_name == "std::ops::Deref::deref"
             || function_name == "std::ops::DerefMut::deref_mut")
@@ -377,11 +494,11 @@ impl<'tcx> Translator<'tcx> {
         if function_name == "std::result::Result::<T, E>::unwrap"
             && self.is_self_ref_mutex(function_name, args)
         {
-            return self.call_unwrap_mutex(function_name, args, places);
+            return Ok(self.call_unwrap_mutex(function_name, args, places));
         }
         if function_name == "std::thread::spawn" {
-            let return_value = self.call_thread_spawn(function_name, args, destination, places);
-            return Some(return_value);
+            let return_value = self.call_thread_spawn(function_name, args, destination, places)?;
+            return Ok(Some(return_value));
         }
         // Sync or multithreading function
         if sync::is_supported_function(function_name) {
@@ -393,17 +510,23 @@ impl<'tcx> Translator<'tcx> {
             // A reference to the Petri net to add transitions and places
             let net = &mut self.net;
             if let Some(task) =
-                sync::call_function(function_name, index, args, destination, places, net, memory)
+                sync::call_function(function_name, index, args, destination, places, net, memory)?
             {
+                // The transition that starts the wait was created together with the condition variable.
+                // Map it to the call to `wait` instead, which is where the thread blocks.
+                if let PostprocessingTask::LinkMutexToCondvar { wait_start, .. } = &task {
+                    self.net_map
+                        .relocate_transition(wait_start.label(), self.origin.as_ref());
+                }
                 self.postprocessing.push(task);
             }
-            return None;
+            return Ok(None);
         }
         // Default case for standard and core library calls
         if is_foreign_function(function_def_id, function_name, self.tcx) {
             let index = self.function_counter.get_count(function_name);
             call_foreign_function(function_name, index, places, &mut self.net);
-            return self.get_linked_value_in_first_argument(args);
+            return Ok(self.get_linked_value_in_first_argument(args));
         }
         // Default case: A function with MIR representation
         self.call_mir_function(function_def_id, function_name, args, places)
@@ -441,13 +564,18 @@ impl<'tcx> Translator<'tcx> {
     ///
     /// A separate counter is incremented every time that
     /// the function is called to generate a unique label.
+    ///
+    /// # Errors
+    ///
+    /// If the arguments cannot be mapped to the memory of the called function, then an error is returned.
+    /// Errors found inside the called function are recorded by the MIR Visitor with their own location.
     fn call_mir_function(
         &mut self,
         function_def_id: rustc_hir::def_id::DefId,
         function_name: &str,
         args: &[rustc_span::Spanned<rustc_middle::mir::Operand<'tcx>>],
         places: Places,
-    ) -> Option<Value> {
+    ) -> Result<Option<Value>, TranslationErrorKind> {
         let index = self.function_counter.get_count(function_name);
 
         match places {
@@ -456,21 +584,16 @@ impl<'tcx> Translator<'tcx> {
                 end_place,
                 cleanup_place,
             } => {
-                connect_places(
-                    &mut self.net,
-                    &start_place,
-                    &cleanup_place,
-                    &indexed_mir_function_cleanup_label(function_name, index),
-                );
-
+                let cleanup_label = indexed_mir_function_cleanup_label(function_name, index);
                 let mir_function = MirFunction::new_with_mapped_args(
                     function_def_id,
                     indexed_mir_function_name(function_name, index),
-                    start_place,
+                    start_place.clone(),
                     end_place,
                     args,
                     &self.call_stack.peek().memory,
-                );
+                )?;
+                connect_places(&mut self.net, &start_place, &cleanup_place, &cleanup_label);
                 self.call_stack.push(mir_function);
             }
             Places::Basic {
@@ -484,12 +607,12 @@ impl<'tcx> Translator<'tcx> {
                     end_place,
                     args,
                     &self.call_stack.peek().memory,
-                );
+                )?;
                 self.call_stack.push(mir_function);
             }
         }
         info!("Pushed function {function_name} to the translation call stack");
-        self.translate_top_call_stack()
+        Ok(self.translate_top_call_stack())
     }
 
     /// Call to `std::mem::drop`.
@@ -540,31 +663,40 @@ impl<'tcx> Translator<'tcx> {
     ///
     /// A separate counter is incremented every time that
     /// the function is called to generate a unique label.
+    ///
+    /// # Errors
+    ///
+    /// If a mutable dereference is applied to a place not linked to a mutex guard, then an error is returned.
     fn call_deref_mutex(
         &mut self,
         function_name: &str,
         args: &[rustc_span::Spanned<rustc_middle::mir::Operand<'tcx>>],
         places: Places,
-    ) -> Option<Value> {
+    ) -> Result<Option<Value>, TranslationErrorKind> {
         let index = self.function_counter.get_count(function_name);
+        let first_argument = extract_nth_argument_as_place(args, 0);
+        let current_function = self.call_stack.peek();
+        let mutex_guard_ref = match first_argument {
+            Some(first_argument) if function_name == "std::ops::DerefMut::deref_mut" => {
+                Some(current_function.memory.get_mutex_guard(&first_argument)?)
+            }
+            _ => None,
+        };
+
         let places = places.ignore_cleanup_place();
         let transitions = call_foreign_function(function_name, index, places, &mut self.net);
         let transition = transitions.default();
-
-        let Some(first_argument) = extract_nth_argument_as_place(args, 0) else {
-            return None; // Nothing to return: Either the first argument is not present or it is a constant.
-        };
-        let current_function = self.call_stack.peek_mut();
-
-        if function_name == "std::ops::DerefMut::deref_mut" {
-            let mutex_guard_ref = current_function.memory.get_mutex_guard(&first_argument);
+        if let Some(mutex_guard_ref) = mutex_guard_ref {
             mutex_guard_ref.mutex.add_deref_mut_transition(transition);
             info!("Encountered a mutable dereference of a mutex guard");
         }
 
-        current_function
-            .memory
-            .get_linked_value_or_none(&first_argument)
+        // Nothing to return if the first argument is not present or it is a constant.
+        Ok(first_argument.and_then(|first_argument| {
+            current_function
+                .memory
+                .get_linked_value_or_none(&first_argument)
+        }))
     }
 
     /// Call to `std::result::Result::<T, E>::unwrap`.
@@ -602,17 +734,18 @@ impl<'tcx> Translator<'tcx> {
     ///
     /// A separate counter is incremented every time that
     /// the function is called to generate a unique label.
+    ///
+    /// # Errors
+    ///
+    /// If the function run by the thread cannot be resolved
+    /// or the join handle cannot be linked, then an error is returned.
     fn call_thread_spawn(
         &mut self,
         function_name: &str,
         args: &[rustc_span::Spanned<rustc_middle::mir::Operand<'tcx>>],
         destination: rustc_middle::mir::Place<'tcx>,
         places: Places,
-    ) -> Value {
-        let index = self.function_counter.get_count(function_name);
-        let transitions = call_foreign_function(function_name, index, places, &mut self.net);
-        let transition = transitions.default();
-
+    ) -> Result<Value, TranslationErrorKind> {
         // Extract the definition ID of the thread function
         let current_function = self.call_stack.peek_mut();
         let function_to_be_run = args.first().unwrap_or_else(|| {
@@ -622,7 +755,7 @@ impl<'tcx> Translator<'tcx> {
             &function_to_be_run.node,
             current_function.def_id,
             self.tcx,
-        );
+        )?;
 
         // Create a new thread
         let index = self.threads.len();
@@ -636,18 +769,28 @@ impl<'tcx> Translator<'tcx> {
             end_place,
             args,
             &current_function.memory,
+        )?;
+        current_function.memory.check_link(&destination)?;
+
+        // Every fallible step is done, add the transitions of the call.
+        let transitions = call_foreign_function(
+            function_name,
+            self.function_counter.get_count(function_name),
+            places,
+            &mut self.net,
         );
+        let transition = transitions.default();
         let thread = sync::thread::Thread::new(transition, mir_function, index);
 
         // The return value contains a new join handle. Link the local variable to it.
         let thread_ref = current_function
             .memory
-            .link_join_handle(destination, thread);
+            .link_join_handle(destination, thread)?;
         debug!("NEW JOIN HANDLE: {destination:?}");
 
         // Add the thread to the translator
         self.threads.push_back(thread_ref.clone());
         info!("Found thread {index} and pushed it to the back of the thread translation queue");
-        Value::Single(Single::JoinHandle(thread_ref.clone()))
+        Ok(Value::Single(Single::JoinHandle(thread_ref.clone())))
     }
 }
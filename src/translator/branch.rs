@@ -0,0 +1,164 @@
+//! Submodule that records the branches on boolean locals and the assignments to them,
+//! used to refine spurious witnesses in the module `refinement`.
+//!
+//! A `SwitchInt` terminator on a boolean local is translated to one transition per target,
+//! each of them assumes a value of the local. The assignments are recorded for the place of the basic block
+//! where they happen, since the statements of a block execute when a transition of its terminator fires.
+//! The arguments of a function are assigned an unknown value when its first block starts.
+//!
+//! The refinement relies on the invariant that every write to a recorded local is recorded:
+//! otherwise a value could change without an assignment and a real deadlock would look spurious.
+//! Only the writes by an assignment to the whole local and by the destination of a call are recorded,
+//! so only locals of type `bool` whose every use is one of these or a plain read are recorded.
+//! A local used with a projection, borrowed, pointed to or written by any other means,
+//! e.g. inline assembly, is never recorded, so its value stays unknown in the refinement.
+
+use rustc_middle::mir::visit::{MutatingUseContext, NonMutatingUseContext, PlaceContext, Visitor};
+
+use crate::naming::basic_block::switch_int_transition_label;
+use crate::refinement::{Assignment, BoolLocal};
+use crate::source_span::SourceSpan;
+
+use super::Translator;
+
+impl<'tcx> Translator<'tcx> {
+    /// Records the branches of a `SwitchInt` terminator if its discriminant is a boolean local.
+    /// The target for the value `0` assumes `false`, the other target assumes `true`.
+    pub(super) fn record_branches(
+        &mut self,
+        discr: &rustc_middle::mir::Operand<'tcx>,
+        targets: &rustc_middle::mir::SwitchTargets,
+        location: rustc_middle::mir::Location,
+    ) {
+        let (rustc_middle::mir::Operand::Copy(place) | rustc_middle::mir::Operand::Move(place)) =
+            discr
+        else {
+            return;
+        };
+        let Some(local) = self.bool_local(place) else {
+            return;
+        };
+        let Some((_, false_target)) = targets.iter().find(|(value, _)| *value == 0) else {
+            return;
+        };
+        let true_target = targets.otherwise();
+        if false_target == true_target {
+            return;
+        }
+        let function_name = &self.call_stack.peek().name;
+        for (target, value) in [(false_target, false), (true_target, true)] {
+            let label =
+                switch_int_transition_label(function_name, location.block.index(), target.index());
+            self.branches.record_branch(label, local.clone(), value);
+        }
+    }
+
+    /// Records an assignment to the place in the active basic block if the place is a boolean local.
+    /// The value is `Assignment::Unknown` unless the assigned operand is a constant.
+    pub(super) fn record_assignment(
+        &mut self,
+        place: &rustc_middle::mir::Place<'tcx>,
+        operand: Option<&rustc_middle::mir::Operand<'tcx>>,
+    ) {
+        let Some(local) = self.bool_local(place) else {
+            return;
+        };
+        let assignment = match operand {
+            Some(rustc_middle::mir::Operand::Constant(constant)) => constant
+                .const_
+                .try_to_bool()
+                .map_or(Assignment::Unknown, Assignment::Constant),
+            _ => Assignment::Unknown,
+        };
+        let block_place = self.call_stack.peek().get_start_place_for_function_call();
+        self.branches
+            .record_assignment(block_place.label().clone(), local, assignment);
+    }
+
+    /// Records the boolean arguments of the function on the top of the call stack
+    /// as assigned an unknown value in the active basic block, which must be the first one.
+    pub(super) fn record_arguments(&mut self) {
+        let body = self.tcx.optimized_mir(self.call_stack.peek().def_id);
+        for local in body.args_iter() {
+            self.record_assignment(&rustc_middle::mir::Place::from(local), None);
+        }
+    }
+
+    /// Returns the boolean local of the function on the top of the call stack that the place refers to,
+    /// if the place is a whole local of type `bool` whose every write is recorded.
+    /// Records the name and the declaration of the local the first time.
+    fn bool_local(&mut self, place: &rustc_middle::mir::Place<'tcx>) -> Option<BoolLocal> {
+        if !place.projection.is_empty() {
+            return None;
+        }
+        let function = self.call_stack.peek();
+        let body = self.tcx.optimized_mir(function.def_id);
+        let declaration = &body.local_decls[place.local];
+        if !declaration.ty.is_bool() || !is_trackable(body, place.local) {
+            return None;
+        }
+        let local = BoolLocal {
+            function: function.name.clone(),
+            local: place.local.index(),
+        };
+        let name =
+            body.var_debug_info
+                .iter()
+                .find_map(|var_debug_info| match var_debug_info.value {
+                    rustc_middle::mir::VarDebugInfoContents::Place(debug_place)
+                        if debug_place.local == place.local
+                            && debug_place.projection.is_empty() =>
+                    {
+                        Some(var_debug_info.name.to_string())
+                    }
+                    _ => None,
+                });
+        let span = SourceSpan::from_span(declaration.source_info.span, self.tcx);
+        self.branches.record_declaration(local.clone(), name, span);
+        Some(local)
+    }
+}
+
+/// Checks whether every use of the local in the body is a read of the whole local,
+/// an assignment to the whole local or the destination of a call, i.e. whether every write to it is recorded.
+fn is_trackable(body: &rustc_middle::mir::Body<'_>, local: rustc_middle::mir::Local) -> bool {
+    let mut uses = RecordedUses {
+        local,
+        recorded: true,
+    };
+    uses.visit_body(body);
+    uses.recorded
+}
+
+/// Visitor that checks the uses of a local, see `is_trackable`.
+struct RecordedUses {
+    /// The local whose uses are checked.
+    local: rustc_middle::mir::Local,
+    /// Whether every use visited so far is recorded.
+    recorded: bool,
+}
+
+impl<'tcx> Visitor<'tcx> for RecordedUses {
+    fn visit_place(
+        &mut self,
+        place: &rustc_middle::mir::Place<'tcx>,
+        context: PlaceContext,
+        location: rustc_middle::mir::Location,
+    ) {
+        if place.local == self.local {
+            self.recorded &= place.projection.is_empty()
+                && matches!(
+                    context,
+                    PlaceContext::NonMutatingUse(
+                        NonMutatingUseContext::Copy
+                            | NonMutatingUseContext::Move
+                            | NonMutatingUseContext::Inspect
+                    ) | PlaceContext::MutatingUse(
+                        MutatingUseContext::Store | MutatingUseContext::Call
+                    ) | PlaceContext::NonUse(_)
+                );
+        }
+        // The locals used as an index in the projection are only read.
+        self.super_place(place, context, location);
+    }
+}
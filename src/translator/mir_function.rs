@@ -20,6 +20,7 @@ use log::debug;
 use std::collections::HashMap;
 
 use crate::data_structures::petri_net_interface::{PetriNet, PlaceRef};
+use crate::error::TranslationErrorKind;
 use basic_block::BasicBlock;
 use memory::Memory;
 
@@ -66,6 +67,11 @@ impl MirFunction {
     /// - Maps the arguments to the memory of the new function
     ///
     /// Returns the new MIR function
+    ///
+    /// # Errors
+    ///
+    /// If the sync variables passed as arguments cannot be mapped to the memory of the new function,
+    /// then an error is returned.
     pub fn new_with_mapped_args(
         def_id: rustc_hir::def_id::DefId,
         function_name: String,
@@ -73,17 +79,17 @@ impl MirFunction {
         end_place: PlaceRef,
         args: &[rustc_span::Spanned<rustc_middle::mir::Operand<'_>>],
         calling_function_memory: &Memory,
-    ) -> Self {
+    ) -> Result<Self, TranslationErrorKind> {
         let mut mir_function = Self::new(def_id, function_name, start_place, end_place);
-        mir_function.map_args_to_memory(args, calling_function_memory);
-        mir_function
+        mir_function.map_args_to_memory(args, calling_function_memory)?;
+        Ok(mir_function)
     }
 
     fn map_args_to_memory(
         &mut self,
         args: &[rustc_span::Spanned<rustc_middle::mir::Operand<'_>>],
         calling_function_memory: &Memory,
-    ) {
+    ) -> Result<(), TranslationErrorKind> {
         for (index, arg) in args.iter().enumerate() {
             let index = index + 1; // Place _0 is the return place, locals start at _1
             let operand = &arg.node;
@@ -101,13 +107,14 @@ impl MirFunction {
             };
 
             if calling_function_memory.has_linked_value(&linked_place) {
-                let value = calling_function_memory.get_linked_value(&linked_place);
-                self.memory.link(place, value.clone());
+                let value = calling_function_memory.get_linked_value(&linked_place)?;
+                self.memory.link(place, value.clone())?;
                 debug!(
                     "LINKED PLACE {linked_place:?} IN CALLING FUNCTION TO PLACE {place:?} IN CALLED FUNCTION"
                 );
             }
         }
+        Ok(())
     }
 
     /// Prepares the start place for the next block.
@@ -5,12 +5,13 @@
 //! The `BasicBlock` stores one reference to the place in the Petri net.
 
 use crate::data_structures::petri_net_interface::{
-    PetriNet, PlaceRef, TransitionRef, connect_places,
+    PetriNet, PlaceRef, TransitionRef, add_arc_place_transition, connect_places,
 };
 use crate::naming::basic_block::{
     assert_cleanup_transition_label, assert_transition_label, drop_cleanup_transition_label,
-    drop_transition_label, goto_transition_label, place_label, switch_int_transition_label,
-    unreachable_transition_label, unwind_transition_label,
+    drop_transition_label, goto_transition_label, opaque_diverging_transition_label,
+    opaque_return_transition_label, opaque_transition_label, place_label,
+    switch_int_transition_label, unreachable_transition_label, unwind_transition_label,
 };
 
 pub struct BasicBlock {
@@ -89,4 +90,26 @@ impl BasicBlock {
         let label = unreachable_transition_label(&self.function_name, self.index);
         connect_places(net, &self.place, end_place, &label);
     }
+
+    /// Connects the end place of this block to the start place of the `target` basic block
+    /// through an opaque transition that stands for an unsupported terminator.
+    pub fn opaque(&self, target: &Self, target_index: usize, net: &mut PetriNet) {
+        let label = opaque_transition_label(&self.function_name, self.index, target_index);
+        connect_places(net, &self.place, &target.place, &label);
+    }
+
+    /// Connects the end place of this block to the end place of the function
+    /// through an opaque transition that stands for an unsupported terminator.
+    pub fn opaque_return(&self, end_place: &PlaceRef, net: &mut PetriNet) {
+        let label = opaque_return_transition_label(&self.function_name, self.index);
+        connect_places(net, &self.place, end_place, &label);
+    }
+
+    /// Connects the end place of this block to a new opaque transition without output places.
+    /// It models an unsupported terminator that does not continue, similar to a diverging call.
+    pub fn opaque_diverging(&self, net: &mut PetriNet) {
+        let label = opaque_diverging_transition_label(&self.function_name, self.index);
+        let transition = net.add_transition(&label);
+        add_arc_place_transition(net, &self.place, &transition);
+    }
 }
@@ -26,6 +26,7 @@ use log::debug;
 use mir_locals_vec::MirLocalsVec;
 use std::rc::Rc;
 
+use crate::error::TranslationErrorKind;
 use crate::translator::sync::{Condvar, Mutex, MutexGuard, Thread};
 pub use value::{CondvarRef, MutexGuardRef, MutexRef, Single, ThreadRef, Value};
 
@@ -72,40 +73,77 @@ impl<'tcx> Memory {
     /// Supports field numbers but only one level.
     /// Returns a reference to the linked value.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the place contains more than 2 levels of field indirection, then the function panics
-    pub fn link(&mut self, place: Place<'tcx>, value: Value) -> &Value {
+    /// If the place is already linked to a different value, then an error is returned.
+    /// If the place contains more than 1 level of field indirection, then an error is returned.
+    pub fn link(
+        &mut self,
+        place: Place<'tcx>,
+        value: Value,
+    ) -> Result<&Value, TranslationErrorKind> {
         let (local, field_numbers) = Self::extract_local_and_field_number(&place);
 
+        if local < self.data.len()
+            && matches!(self.data[local], Value::Single(_))
+            && self.data[local] == value
+        {
+            // In some cases the MIR shows two separate assignments that
+            // lead to the same linking. Nothing to do in this case.
+            debug!("PLACE {place:?} LINKED TO SAME {:?}", self.data[local]);
+            return Ok(&self.data[local]);
+        }
+        self.check_link(&place)?;
         match &mut self.data[local] {
-            Value::None => {
-                self.data[local] = value;
-                &self.data[local]
-            }
+            Value::None => self.data[local] = value,
+            Value::Aggregate(old_values) => old_values[field_numbers[0]] = value,
             Value::Single(_) => {
-                if self.data[local] == value {
-                    // In some cases the MIR shows two separate assignments that
-                    // lead to the same linking. Nothing to do in this case.
-                    debug!("PLACE {place:?} LINKED TO SAME {:?}", self.data[local]);
-                    &self.data[local]
-                } else {
-                    // The type of the local never changes, this is a hard error.
-                    panic!("PLACE {place:?} WAS LINKED TO A {:?}", self.data[local]);
-                }
+                unreachable!("BUG: `check_link` accepted a place linked to a value")
             }
+        }
+        Ok(&self.data[local])
+    }
+
+    /// Checks that the place can be linked to a new value, without linking it.
+    /// The handlers of the calls check it before adding transitions to the net,
+    /// so that a call that cannot be translated leaves the net unchanged.
+    ///
+    /// # Errors
+    ///
+    /// If the place is already linked to a value, then an error is returned.
+    /// If the place contains more than 1 level of field indirection, then an error is returned.
+    pub fn check_link(&self, place: &Place<'tcx>) -> Result<(), TranslationErrorKind> {
+        let (local, field_numbers) = Self::extract_local_and_field_number(place);
+        if local >= self.data.len() {
+            return Ok(());
+        }
+
+        match &self.data[local] {
+            Value::None => Ok(()),
+            // The type of the local never changes, this is a hard error.
+            Value::Single(_) => Err(TranslationErrorKind::MemoryLinkConflict(format!(
+                "place {place:?} was already linked to a {}",
+                self.data[local]
+            ))),
             Value::Aggregate(old_values) => {
-                assert!(
-                    !field_numbers.is_empty(),
-                    "BUG: {place:?} was linked to an Aggregate {old_values:?}"
-                );
-                assert!(
-                    field_numbers.len() <= 1,
-                    "BUG: Value can only be linked with one level of field indirection, found {}",
-                    field_numbers.len()
-                );
-                old_values[field_numbers[0]] = value;
-                &self.data[local]
+                if field_numbers.is_empty() {
+                    return Err(TranslationErrorKind::MemoryLinkConflict(format!(
+                        "place {place:?} was already linked to an {}",
+                        self.data[local]
+                    )));
+                }
+                if field_numbers.len() > 1 {
+                    return Err(TranslationErrorKind::MemoryLinkConflict(format!(
+                        "place {place:?} can only be linked with one level of field indirection, found {}",
+                        field_numbers.len()
+                    )));
+                }
+                if field_numbers[0] >= old_values.len() {
+                    return Err(TranslationErrorKind::MemoryLinkConflict(format!(
+                        "place {place:?} refers to a field that does not exist in the aggregate"
+                    )));
+                }
+                Ok(())
             }
         }
     }
@@ -113,11 +151,19 @@ impl<'tcx> Memory {
     /// Links a given place to a given mutex.
     /// Prints debug messages if the place was already linked.
     /// Returns a reference to the linked mutex.
-    pub fn link_mutex(&mut self, place: Place<'tcx>, mutex: Mutex) -> &MutexRef {
+    ///
+    /// # Errors
+    ///
+    /// If the place cannot be linked, then an error is returned.
+    pub fn link_mutex(
+        &mut self,
+        place: Place<'tcx>,
+        mutex: Mutex,
+    ) -> Result<&MutexRef, TranslationErrorKind> {
         let mutex_ref: Rc<Mutex> = Rc::new(mutex);
         let value = Value::Single(Single::Mutex(mutex_ref));
-        match self.link(place, value) {
-            Value::Single(Single::Mutex(mutex_ref)) => mutex_ref,
+        match self.link(place, value)? {
+            Value::Single(Single::Mutex(mutex_ref)) => Ok(mutex_ref),
             value => panic!("BUG: Stored a mutex but got {value:?} back"),
         }
     }
@@ -125,15 +171,19 @@ impl<'tcx> Memory {
     /// Links a given place to a given mutex guard.
     /// Prints debug messages if the place was already linked.
     /// Returns a reference to the linked mutex guard.
+    ///
+    /// # Errors
+    ///
+    /// If the place cannot be linked, then an error is returned.
     pub fn link_mutex_guard(
         &mut self,
         place: Place<'tcx>,
         mutex_guard: MutexGuard,
-    ) -> &MutexGuardRef {
+    ) -> Result<&MutexGuardRef, TranslationErrorKind> {
         let mutex_guard_ref = Rc::new(mutex_guard);
         let value = Value::Single(Single::MutexGuard(mutex_guard_ref));
-        match self.link(place, value) {
-            Value::Single(Single::MutexGuard(mutex_guard_ref)) => mutex_guard_ref,
+        match self.link(place, value)? {
+            Value::Single(Single::MutexGuard(mutex_guard_ref)) => Ok(mutex_guard_ref),
             value => panic!("BUG: Stored a mutex guard but got {value:?} back"),
         }
     }
@@ -141,11 +191,19 @@ impl<'tcx> Memory {
     /// Links a given place to a given join handle.
     /// Prints debug messages if the place was already linked.
     /// Returns a reference to the linked join handle.
-    pub fn link_join_handle(&mut self, place: Place<'tcx>, thread: Thread) -> &ThreadRef {
+    ///
+    /// # Errors
+    ///
+    /// If the place cannot be linked, then an error is returned.
+    pub fn link_join_handle(
+        &mut self,
+        place: Place<'tcx>,
+        thread: Thread,
+    ) -> Result<&ThreadRef, TranslationErrorKind> {
         let thread_ref = Rc::new(thread);
         let value = Value::Single(Single::JoinHandle(thread_ref));
-        match self.link(place, value) {
-            Value::Single(Single::JoinHandle(thread_ref)) => thread_ref,
+        match self.link(place, value)? {
+            Value::Single(Single::JoinHandle(thread_ref)) => Ok(thread_ref),
             value => panic!("BUG: Stored a join handle but got {value:?} back"),
         }
     }
@@ -153,11 +211,19 @@ impl<'tcx> Memory {
     /// Links a given place to a given condition variable.
     /// Prints debug messages if the place was already linked.
     /// Returns a reference to the linked condition variable.
-    pub fn link_condvar(&mut self, place: Place<'tcx>, condvar: Condvar) -> &CondvarRef {
+    ///
+    /// # Errors
+    ///
+    /// If the place cannot be linked, then an error is returned.
+    pub fn link_condvar(
+        &mut self,
+        place: Place<'tcx>,
+        condvar: Condvar,
+    ) -> Result<&CondvarRef, TranslationErrorKind> {
         let condvar_ref = Rc::new(condvar);
         let value = Value::Single(Single::Condvar(condvar_ref));
-        match self.link(place, value) {
-            Value::Single(Single::Condvar(condvar_ref)) => condvar_ref,
+        match self.link(place, value)? {
+            Value::Single(Single::Condvar(condvar_ref)) => Ok(condvar_ref),
             value => panic!("BUG: Stored a condition variable but got {value:?} back"),
         }
     }
@@ -166,17 +232,19 @@ impl<'tcx> Memory {
     /// After this operation, both places point to the same value, i.e.
     /// the first place is an alias for the second place.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the `place_linked` is not linked to a value, then the function panics.
+    /// If the `place_linked` is not linked to a value, then an error is returned.
+    /// If the `place_to_link` cannot be linked, then an error is returned.
     pub fn link_place_to_same_value(
         &mut self,
         place_to_link: Place<'tcx>,
         place_linked: Place<'tcx>,
-    ) {
-        let value = self.get_linked_value(&place_linked);
-        self.link(place_to_link, value.clone());
+    ) -> Result<(), TranslationErrorKind> {
+        let value = self.get_linked_value(&place_linked)?;
+        self.link(place_to_link, value.clone())?;
         debug!("SAME VALUE: {place_to_link:?} = {place_linked:?}");
+        Ok(())
     }
 
     // Checks if the place is linked to a value, i.e, it contains a sync variable
@@ -231,66 +299,51 @@ impl<'tcx> Memory {
     /// Returns an immutable reference to the value linked to the given place.
     /// If the place contains fields, it accesses the aggregates until it finds the value.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If the place is not linked to a value, then the function panics.
-    pub fn get_linked_value(&self, place: &Place<'tcx>) -> &Value {
+    /// If the place is not linked to a value, then an error is returned.
+    pub fn get_linked_value(&self, place: &Place<'tcx>) -> Result<&Value, TranslationErrorKind> {
         let (local, field_numbers) = Self::extract_local_and_field_number(place);
-        assert!(
-            local < self.data.len(),
-            "BUG: The place {place:?} is out of bound with respect to the memory data"
-        );
+        let untracked = || {
+            TranslationErrorKind::UntrackedSyncVariable(format!(
+                "place {place:?} is not linked to a sync variable"
+            ))
+        };
+        if local >= self.data.len() {
+            return Err(untracked());
+        }
 
         match &self.data[local] {
-            Value::None => {
-                panic!("BUG: The place {place:?} should be linked to a value")
-            }
+            Value::None => Err(untracked()),
             value @ Value::Aggregate(values) => {
                 if field_numbers.is_empty() {
-                    value
+                    Ok(value)
                 } else {
-                    Self::get_value(values, &field_numbers)
+                    Self::get_value(values, &field_numbers).ok_or_else(untracked)
                 }
             }
-            value @ Value::Single(_) => value,
+            value @ Value::Single(_) => Ok(value),
         }
     }
 
     /// Get to the `Single` value stored in the local and the field numbers.
     /// Supports any level of field indirection, e.g. `_2 = _1.0.1.2.3`
-    ///
-    /// # Panics
-    ///
-    /// If a single value cannot be found, then the function panics.
-    fn get_value<'value>(values: &'value [Value], field_numbers: &[FieldNumber]) -> &'value Value {
-        let mut index = 0;
-        let last_index = field_numbers.len() - 1;
+    /// Returns `None` if a single value cannot be found.
+    fn get_value<'value>(
+        values: &'value [Value],
+        field_numbers: &[FieldNumber],
+    ) -> Option<&'value Value> {
+        let (last_field_number, field_numbers) = field_numbers.split_last()?;
         let mut current_values = values;
 
-        while index <= last_index {
-            let next_index = field_numbers[index];
-
-            match current_values.get(next_index) {
-                None => panic!(
-                    "BUG: A single value in {current_values:?} with field number {next_index} cannot be found"
-                ),
-                Some(value) => {
-                    if index == last_index {
-                        return value;
-                    }
-                    match value {
-                        Value::Single(_) | Value::None => panic!(
-                            "BUG: Encountered a single value where an aggregate was expected"
-                        ),
-                        Value::Aggregate(next_values) => {
-                            current_values = next_values;
-                            index += 1;
-                        }
-                    }
-                }
+        for next_index in field_numbers {
+            match current_values.get(*next_index)? {
+                // Encountered a single value where an aggregate was expected
+                Value::Single(_) | Value::None => return None,
+                Value::Aggregate(next_values) => current_values = next_values,
             }
         }
-        panic!("BUG: A single value could not be found after traversing all field numbers");
+        current_values.get(*last_field_number)
     }
 
     /// Returns the return value (PLACE `_0`) if the place is linked to a value, otherwise returns `None`.
@@ -300,11 +353,7 @@ impl<'tcx> Memory {
             projection: rustc_middle::ty::List::empty(),
         };
 
-        if self.has_linked_value(&return_place) {
-            Some(self.get_linked_value(&return_place).clone())
-        } else {
-            None
-        }
+        self.get_linked_value_or_none(&return_place)
     }
 
     /// Returns a copy of the value linked to the given place.
@@ -312,50 +361,73 @@ impl<'tcx> Memory {
     /// If the place is not linked, it returns `None`.
     pub fn get_linked_value_or_none(&self, place: &Place<'tcx>) -> Option<Value> {
         if self.has_linked_value(place) {
-            Some(self.get_linked_value(place).clone())
+            self.get_linked_value(place).ok().cloned()
         } else {
             None
         }
     }
 
+    /// Returns a reference to the single value linked to the given place.
+    /// The function `unpack` extracts the expected sync variable from the single value.
+    ///
+    /// # Errors
+    ///
+    /// If the place is not linked to the expected sync variable, then an error is returned.
+    fn get_single<'value, T>(
+        &'value self,
+        place: &Place<'tcx>,
+        expected: &str,
+        unpack: impl FnOnce(&'value Single) -> Option<&'value T>,
+    ) -> Result<&'value T, TranslationErrorKind> {
+        let value = self.get_linked_value(place)?;
+        let unpacked = match value {
+            Value::Single(single) => unpack(single),
+            Value::Aggregate(_) | Value::None => None,
+        };
+        unpacked.ok_or_else(|| {
+            TranslationErrorKind::UntrackedSyncVariable(format!(
+                "place {place:?} should contain a {expected} but it contains a {value}"
+            ))
+        })
+    }
+
     /// Returns a reference to the mutex linked to the given place.
-    pub fn get_mutex(&self, place: &Place<'tcx>) -> &MutexRef {
-        match self.get_linked_value(place) {
-            Value::Single(single) => single.unpack_mutex().unwrap(),
-            value @ (Value::Aggregate(_) | Value::None) => {
-                panic!("BUG: The value does not contain a mutex, it contains: {value:?}.")
-            }
-        }
+    ///
+    /// # Errors
+    ///
+    /// If the place is not linked to a mutex, then an error is returned.
+    pub fn get_mutex(&self, place: &Place<'tcx>) -> Result<&MutexRef, TranslationErrorKind> {
+        self.get_single(place, "mutex", Single::unpack_mutex)
     }
 
     /// Returns a reference to the mutex guard linked to the given place.
-    pub fn get_mutex_guard(&self, place: &Place<'tcx>) -> &MutexGuardRef {
-        match self.get_linked_value(place) {
-            Value::Single(single) => single.unpack_mutex_guard().unwrap(),
-            value @ (Value::Aggregate(_) | Value::None) => {
-                panic!("BUG: The value does not contain a mutex guard, it contains: {value:?}.")
-            }
-        }
+    ///
+    /// # Errors
+    ///
+    /// If the place is not linked to a mutex guard, then an error is returned.
+    pub fn get_mutex_guard(
+        &self,
+        place: &Place<'tcx>,
+    ) -> Result<&MutexGuardRef, TranslationErrorKind> {
+        self.get_single(place, "mutex guard", Single::unpack_mutex_guard)
     }
 
     /// Returns a reference to the join handle linked to the given place.
-    pub fn get_join_handle(&self, place: &Place<'tcx>) -> &ThreadRef {
-        match self.get_linked_value(place) {
-            Value::Single(single) => single.unpack_join_handle().unwrap(),
-            value @ (Value::Aggregate(_) | Value::None) => {
-                panic!("BUG: The value does not contain a join handle, it contains: {value:?}.")
-            }
-        }
+    ///
+    /// # Errors
+    ///
+    /// If the place is not linked to a join handle, then an error is returned.
+    pub fn get_join_handle(&self, place: &Place<'tcx>) -> Result<&ThreadRef, TranslationErrorKind> {
+        self.get_single(place, "join handle", Single::unpack_join_handle)
     }
 
     /// Returns a reference to the condition variable linked to the given place.
-    pub fn get_condvar(&self, place: &Place<'tcx>) -> &CondvarRef {
-        match self.get_linked_value(place) {
-            Value::Single(single) => single.unpack_condvar().unwrap(),
-            value @ (Value::Aggregate(_) | Value::None) => panic!(
-                "BUG: The value does not contain a condition variable, it contains: {value:?}."
-            ),
-        }
+    ///
+    /// # Errors
+    ///
+    /// If the place is not linked to a condition variable, then an error is returned.
+    pub fn get_condvar(&self, place: &Place<'tcx>) -> Result<&CondvarRef, TranslationErrorKind> {
+        self.get_single(place, "condition variable", Single::unpack_condvar)
     }
 
     /// Checks whether the place is linked to a mutex guard.
@@ -363,8 +435,10 @@ impl<'tcx> Memory {
         if !self.has_linked_value(place) {
             return false;
         }
-        let value = self.get_linked_value(place);
-        matches!(value, Value::Single(Single::MutexGuard(_)))
+        matches!(
+            self.get_linked_value(place),
+            Ok(Value::Single(Single::MutexGuard(_)))
+        )
     }
 
     /// Creates a new aggregate value from the places with sync variables to aggregate.
@@ -372,25 +446,26 @@ impl<'tcx> Memory {
     /// It maps `None` to a `Other` type of value.
     /// Links the new aggregate value to the given place.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If there is a value linked to the place for the aggregate, then the function panics.
+    /// If there is a different value linked to the place for the aggregate, then an error is returned.
+    /// If one of the places of the fields is not linked to a value, then an error is returned.
     pub fn create_aggregate(
         &mut self,
         place: Place<'tcx>,
         places_of_aggregate_fields: &[Option<Place<'tcx>>],
-    ) {
-        let values: Vec<Value> = places_of_aggregate_fields
+    ) -> Result<(), TranslationErrorKind> {
+        let values = places_of_aggregate_fields
             .iter()
             .map(|place| {
-                place.as_ref().map_or(Value::None, |place| {
-                    let value = self.get_linked_value(place);
-                    value.clone()
+                place.as_ref().map_or(Ok(Value::None), |place| {
+                    self.get_linked_value(place).cloned()
                 })
             })
-            .collect();
+            .collect::<Result<Vec<Value>, TranslationErrorKind>>()?;
 
-        self.link(place, Value::Aggregate(values.clone()));
+        self.link(place, Value::Aggregate(values.clone()))?;
         debug!("CREATED AGGREGATE AT {place:?} WITH VALUES {values:?}");
+        Ok(())
     }
 }
@@ -116,4 +116,36 @@ impl MirFunction {
         let active_block = self.get_active_block();
         active_block.unreachable(end_place, net);
     }
+
+    /// Over-approximates an unsupported terminator by connecting the active basic block
+    /// to every possible basic block target. This models the execution flow taking any of the paths.
+    /// If there are no targets, the execution flow simply stops, like after a diverging call.
+    /// Adds the corresponding block if it is not present already.
+    ///
+    /// # Panics
+    ///
+    /// If there is no active basic block set, then the function panics.
+    pub fn opaque(&mut self, targets: Vec<rustc_middle::mir::BasicBlock>, net: &mut PetriNet) {
+        if targets.is_empty() {
+            let active_block = self.get_active_block();
+            active_block.opaque_diverging(net);
+        }
+        for basic_block in targets {
+            let (active_block, target_block) =
+                self.get_pair_active_block_target_block(basic_block, net);
+            let index = basic_block.index();
+            active_block.opaque(target_block, index, net);
+        }
+    }
+
+    /// Over-approximates an unsupported terminator that leaves the function
+    /// by connecting the active basic block to the end place of the function.
+    ///
+    /// # Panics
+    ///
+    /// If there is no active basic block set, then the function panics.
+    pub fn opaque_return(&self, net: &mut PetriNet) {
+        let active_block = self.get_active_block();
+        active_block.opaque_return(&self.end_place, net);
+    }
 }
@@ -17,6 +17,7 @@ use log::debug;
 
 use super::Translator;
 use super::sync::{handle_aggregate_assignment, link_if_sync_variable, mutex};
+use crate::error::TranslationErrorKind;
 
 impl<'tcx> Visitor<'tcx> for Translator<'tcx> {
     /// Entering a new basic block of the current MIR function.
@@ -26,12 +27,36 @@ impl<'tcx> Visitor<'tcx> for Translator<'tcx> {
         block: rustc_middle::mir::BasicBlock,
         data: &rustc_middle::mir::BasicBlockData<'tcx>,
     ) {
+        if self.error.is_some() {
+            return; // Skip the rest of the MIR after an error.
+        }
+        self.enter_location(rustc_middle::mir::Location {
+            block,
+            statement_index: 0,
+        });
         let function = self.call_stack.peek_mut();
         function.activate_block(block, &mut self.net);
+        if block.index() == 0 {
+            self.record_arguments();
+        }
 
         self.super_basic_block_data(block, data);
     }
 
+    /// Entering a new statement of the current basic block.
+    /// Set it as the origin of the places and transitions added while translating it.
+    fn visit_statement(
+        &mut self,
+        statement: &rustc_middle::mir::Statement<'tcx>,
+        location: rustc_middle::mir::Location,
+    ) {
+        if self.error.is_some() {
+            return; // Skip the rest of the MIR after an error.
+        }
+        self.enter_location(location);
+        self.super_statement(statement, location);
+    }
+
     /// Keep track of synchronization variables in assignments
     /// (mutexes, mutex guards, join handles and condition variables).
     /// The idea is to link the right-hand side with the left-hand side of the assignment
@@ -42,21 +67,36 @@ impl<'tcx> Visitor<'tcx> for Translator<'tcx> {
         rvalue: &rustc_middle::mir::Rvalue<'tcx>,
         location: rustc_middle::mir::Location,
     ) {
-        match rvalue {
+        if self.error.is_some() {
+            return; // Skip the rest of the MIR after an error.
+        }
+        let operand = match rvalue {
+            rustc_middle::mir::Rvalue::Use(operand, _) => Some(operand),
+            _ => None,
+        };
+        self.record_assignment(place, operand);
+        let function = self.call_stack.peek_mut();
+        let result = match rvalue {
             rustc_middle::mir::Rvalue::Use(
                 rustc_middle::mir::Operand::Copy(rhs) | rustc_middle::mir::Operand::Move(rhs),
                 _,
             )
             | rustc_middle::mir::Rvalue::Ref(_, _, rhs) => {
-                let function = self.call_stack.peek_mut();
-                link_if_sync_variable(place, rhs, &mut function.memory, function.def_id, self.tcx);
+                link_if_sync_variable(place, rhs, &mut function.memory, function.def_id, self.tcx)
             }
             rustc_middle::mir::Rvalue::Aggregate(_, operands) => {
-                let function = self.call_stack.peek_mut();
-                handle_aggregate_assignment(place, &operands.raw, &mut function.memory);
+                handle_aggregate_assignment(place, &operands.raw, &mut function.memory)
             }
             // No need to do anything for the other cases for now.
-            _ => {}
+            _ => Ok(()),
+        };
+        if let Err(kind) = result {
+            let body = self.tcx.optimized_mir(function.def_id);
+            let span = body.source_info(location).span;
+            // In lenient mode, the place is simply not linked to the sync variable.
+            if !self.report_unsupported(kind, span) {
+                return;
+            }
         }
 
         self.super_assign(place, rvalue, location);
@@ -68,6 +108,10 @@ impl<'tcx> Visitor<'tcx> for Translator<'tcx> {
         terminator: &rustc_middle::mir::Terminator<'tcx>,
         location: rustc_middle::mir::Location,
     ) {
+        if self.error.is_some() {
+            return; // Skip the rest of the MIR after an error.
+        }
+        self.enter_location(location);
         let function = self.call_stack.peek_mut();
 
         match terminator.kind {
@@ -75,9 +119,11 @@ impl<'tcx> Visitor<'tcx> for Translator<'tcx> {
                 function.goto(target, &mut self.net);
             }
             SwitchInt {
-                discr: _,
+                ref discr,
                 ref targets,
             } => {
+                self.record_branches(discr, targets, location);
+                let function = self.call_stack.peek_mut();
                 // Convert the specific type for the targets vector into a `std::collections::Vec`
                 // <rustc_middle::mir::terminator::SwitchTargets>
                 function.switch_int(targets.all_targets().to_vec(), &mut self.net);
@@ -128,14 +174,38 @@ impl<'tcx> Visitor<'tcx> for Translator<'tcx> {
                 unwind,
                 fn_span: _,
                 call_source: _,
-            } => match self.call_function(func, args, destination, target, unwind) {
-                Some(return_value) => {
-                    let function = self.call_stack.peek_mut();
-                    function.memory.link(destination, return_value);
-                    debug!("LINKED PLACE {destination:?} TO RETURN VALUE OF FUNCTION {func:?}");
+            } => {
+                // The value returned is not known, the caller's block assigns it when the call starts.
+                self.record_assignment(&destination, None);
+                match self.call_function(func, args, destination, target, unwind) {
+                    Ok(Some(return_value)) => {
+                        let function = self.call_stack.peek_mut();
+                        if let Err(kind) = function.memory.link(destination, return_value) {
+                            // In lenient mode, the destination is simply not linked to the return value.
+                            if !self.report_unsupported(kind, terminator.source_info.span) {
+                                return;
+                            }
+                        } else {
+                            debug!(
+                                "LINKED PLACE {destination:?} TO RETURN VALUE OF FUNCTION {func:?}"
+                            );
+                        }
+                    }
+                    Ok(None) => return,
+                    Err(kind) => {
+                        if self.report_unsupported(kind, terminator.source_info.span) {
+                            // Replace the call with a jump to any of its successors.
+                            // The call added no transition, but its synchronization is lost:
+                            // the result is an under-approximation of the callee.
+                            let mut targets: Vec<_> = target.into_iter().collect();
+                            targets.extend(cleanup_block(unwind));
+                            let function = self.call_stack.peek_mut();
+                            function.opaque(targets, &mut self.net);
+                        }
+                        return;
+                    }
                 }
-                None => return,
-            },
+            }
             Assert {
                 cond: _,
                 expected: _,
@@ -158,30 +228,88 @@ impl<'tcx> Visitor<'tcx> for Translator<'tcx> {
                     }
                 }
             }
-            Yield { .. } => {
-                unimplemented!("TerminatorKind::Yield not implemented yet")
+            Yield {
+                resume, ref drop, ..
+            } => {
+                let kind = TranslationErrorKind::UnsupportedTerminator("Yield".to_string());
+                if !self.report_unsupported(kind, terminator.source_info.span) {
+                    return;
+                }
+                let mut targets = vec![resume];
+                targets.extend(drop);
+                let function = self.call_stack.peek_mut();
+                function.opaque(targets, &mut self.net);
             }
             CoroutineDrop => {
-                unimplemented!("TerminatorKind::CoroutineDrop not implemented yet")
+                let kind = TranslationErrorKind::UnsupportedTerminator("CoroutineDrop".to_string());
+                if !self.report_unsupported(kind, terminator.source_info.span) {
+                    return;
+                }
+                let function = self.call_stack.peek();
+                function.opaque_return(&mut self.net);
             }
-            FalseEdge { .. } => {
-                unimplemented!("TerminatorKind::FalseEdge not implemented yet")
+            FalseEdge {
+                real_target,
+                imaginary_target,
+            } => {
+                let kind = TranslationErrorKind::UnsupportedTerminator("FalseEdge".to_string());
+                if !self.report_unsupported(kind, terminator.source_info.span) {
+                    return;
+                }
+                let function = self.call_stack.peek_mut();
+                function.opaque(vec![real_target, imaginary_target], &mut self.net);
             }
-            FalseUnwind { .. } => {
-                unimplemented!("TerminatorKind::FalseUnwind not implemented yet")
+            FalseUnwind {
+                real_target,
+                unwind,
+            } => {
+                let kind = TranslationErrorKind::UnsupportedTerminator("FalseUnwind".to_string());
+                if !self.report_unsupported(kind, terminator.source_info.span) {
+                    return;
+                }
+                let mut targets = vec![real_target];
+                targets.extend(cleanup_block(unwind));
+                let function = self.call_stack.peek_mut();
+                function.opaque(targets, &mut self.net);
             }
-            InlineAsm { .. } => {
-                unimplemented!("TerminatorKind::InlineAsm not implemented yet")
+            InlineAsm {
+                ref targets,
+                unwind,
+                ..
+            } => {
+                let kind = TranslationErrorKind::UnsupportedTerminator("InlineAsm".to_string());
+                if !self.report_unsupported(kind, terminator.source_info.span) {
+                    return;
+                }
+                let mut targets = targets.to_vec();
+                targets.extend(cleanup_block(unwind));
+                let function = self.call_stack.peek_mut();
+                function.opaque(targets, &mut self.net);
             }
             TailCall {
                 func: _,
                 args: _,
                 fn_span: _,
             } => {
-                unimplemented!("TerminatorKind::TailCall not implemented yet")
+                // A tail call replaces the current function, so its return is the return of the function.
+                let kind = TranslationErrorKind::UnsupportedTerminator("TailCall".to_string());
+                if !self.report_unsupported(kind, terminator.source_info.span) {
+                    return;
+                }
+                let function = self.call_stack.peek();
+                function.opaque_return(&mut self.net);
             }
         }
 
         self.super_terminator(terminator, location);
     }
 }
+
+/// Returns the cleanup block of an unwind action, if any.
+/// Used to over-approximate unsupported terminators, which may jump to their cleanup block.
+const fn cleanup_block(unwind: UnwindAction) -> Option<rustc_middle::mir::BasicBlock> {
+    match unwind {
+        UnwindAction::Cleanup(cleanup) => Some(cleanup),
+        UnwindAction::Continue | UnwindAction::Unreachable | UnwindAction::Terminate(..) => None,
+    }
+}
@@ -0,0 +1,269 @@
+//! Submodule for the pre-flight support check.
+//!
+//! The support check walks the MIR of every function reachable from the entry point,
+//! resolving the calls in the same way as `Translator::translate_function_call`,
+//! but without building a Petri net.
+//! It collects every construct that the translator cannot handle, together with its location:
+//!
+//! - Unsupported terminators, e.g. `InlineAsm`, `TailCall` or `Yield`.
+//! - Calls that cannot be resolved statically, e.g. calls through function pointers.
+//! - Closures whose body is never translated, e.g. closures passed to iterator adapters.
+//! - Sync-like types that are not modelled, e.g. `RwLock`, channels or a `Mutex` inside a `Vec`.
+
+use log::info;
+use rustc_middle::mir::TerminatorKind::{
+    Call, CoroutineDrop, FalseEdge, FalseUnwind, InlineAsm, TailCall, Yield,
+};
+use std::collections::{HashSet, VecDeque};
+
+use crate::error::{TranslationError, TranslationErrorKind};
+use crate::source_span::SourceSpan;
+use crate::translator::special_function::is_foreign_function;
+use crate::translator::sync::is_supported_function;
+use crate::utils::extract_def_id_of_called_function_from_operand;
+
+/// Synchronization primitives of the standard library that the translator does not model.
+const UNSUPPORTED_SYNC_TYPES: [&str; 8] = [
+    "std::sync::RwLock<",
+    "std::sync::Barrier",
+    "std::sync::mpsc::Sender<",
+    "std::sync::mpsc::SyncSender<",
+    "std::sync::mpsc::Receiver<",
+    "std::sync::atomic::",
+    "std::sync::ReentrantLock<",
+    "std::sync::Once",
+];
+
+/// Synchronization primitives of the standard library that the translator models.
+const SUPPORTED_SYNC_TYPES: [&str; 4] = [
+    "std::sync::Mutex<",
+    "std::sync::MutexGuard<",
+    "std::sync::Condvar",
+    "std::thread::JoinHandle<",
+];
+
+/// Containers (vectors, collections, arrays and slices) in which
+/// the memory of the translator cannot track the supported sync primitives.
+const UNTRACKED_CONTAINERS: [&str; 3] = ["std::vec::Vec<", "std::collections::", "["];
+
+/// Walks the MIR reachable from the entry point and returns every unsupported construct found.
+///
+/// # Errors
+///
+/// If the source code does not contain a main function, then an error is returned.
+pub fn check_support(
+    tcx: rustc_middle::ty::TyCtxt,
+) -> Result<Vec<TranslationError>, TranslationError> {
+    let Some((main_function_id, _)) = tcx.entry_fn(()) else {
+        return Err(TranslationError::new(TranslationErrorKind::NoEntryPoint));
+    };
+    let mut checker = SupportChecker::new(tcx);
+    checker.pending.push_back(main_function_id);
+    checker.run();
+    Ok(checker.unsupported)
+}
+
+struct SupportChecker<'tcx> {
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    /// Functions with a MIR representation that still need to be checked.
+    pending: VecDeque<rustc_hir::def_id::DefId>,
+    /// Functions that were already checked or queued.
+    visited: HashSet<rustc_hir::def_id::DefId>,
+    /// Closures created in the checked functions, with the function and the span where they are created.
+    closures: Vec<(rustc_hir::def_id::DefId, String, rustc_span::Span)>,
+    /// The unsupported constructs found so far.
+    unsupported: Vec<TranslationError>,
+}
+
+impl<'tcx> SupportChecker<'tcx> {
+    fn new(tcx: rustc_middle::ty::TyCtxt<'tcx>) -> Self {
+        Self {
+            tcx,
+            pending: VecDeque::new(),
+            visited: HashSet::new(),
+            closures: Vec::new(),
+            unsupported: Vec::new(),
+        }
+    }
+
+    /// Checks the pending functions until all reachable functions were checked.
+    /// Then reports the closures whose body is never reached.
+    fn run(&mut self) {
+        while let Some(def_id) = self.pending.pop_front() {
+            if !self.visited.insert(def_id) {
+                continue;
+            }
+            self.check_function(def_id);
+        }
+        for (closure_def_id, function_name, span) in std::mem::take(&mut self.closures) {
+            if self.visited.contains(&closure_def_id) {
+                continue;
+            }
+            let closure_name = self.tcx.def_path_str(closure_def_id);
+            self.report(
+                TranslationErrorKind::UnsupportedFeature(format!(
+                    "the body of the closure `{closure_name}` is never translated because it is not called directly or passed to `std::thread::spawn`"
+                )),
+                &function_name,
+                span,
+            );
+        }
+    }
+
+    /// Records an unsupported construct. Duplicates at the same location are ignored.
+    fn report(&mut self, kind: TranslationErrorKind, function_name: &str, span: rustc_span::Span) {
+        let span = SourceSpan::from_span(span, self.tcx);
+        let error = TranslationError::located(kind, function_name.to_string(), span);
+        if !self.unsupported.contains(&error) {
+            info!("Found unsupported construct: {error}");
+            self.unsupported.push(error);
+        }
+    }
+
+    /// Checks the local variables, the statements and the terminators of a function.
+    fn check_function(&mut self, def_id: rustc_hir::def_id::DefId) {
+        let function_name = self.tcx.def_path_str(def_id);
+        info!("Checking support for function {function_name}");
+        let body = self.tcx.optimized_mir(def_id);
+
+        for local_decl in &body.local_decls {
+            if let rustc_middle::ty::TyKind::Closure(closure_def_id, _) = local_decl.ty.kind() {
+                self.closures.push((
+                    *closure_def_id,
+                    function_name.clone(),
+                    local_decl.source_info.span,
+                ));
+            }
+        }
+        // Only check the variables declared by the user (and the arguments).
+        // The temporaries introduced by the compiler would repeat the same findings.
+        for var_debug_info in &body.var_debug_info {
+            let rustc_middle::mir::VarDebugInfoContents::Place(place) = var_debug_info.value else {
+                continue;
+            };
+            let ty_string = place.ty(body, self.tcx).ty.to_string();
+            if let Some(kind) = check_sync_type(&ty_string) {
+                self.report(kind, &function_name, var_debug_info.source_info.span);
+            }
+        }
+
+        for block_data in body.basic_blocks.iter() {
+            self.check_terminator(def_id, &function_name, block_data.terminator());
+        }
+    }
+
+    /// Checks a terminator. Calls are resolved like in the translator
+    /// and the functions with a MIR representation are queued to be checked.
+    fn check_terminator(
+        &mut self,
+        caller_def_id: rustc_hir::def_id::DefId,
+        function_name: &str,
+        terminator: &rustc_middle::mir::Terminator<'tcx>,
+    ) {
+        let span = terminator.source_info.span;
+        let unsupported_terminator = match &terminator.kind {
+            Call { func, args, .. } => {
+                self.check_call(caller_def_id, function_name, func, args, span);
+                return;
+            }
+            Yield { .. } => "Yield",
+            CoroutineDrop => "CoroutineDrop",
+            FalseEdge { .. } => "FalseEdge",
+            FalseUnwind { .. } => "FalseUnwind",
+            InlineAsm { .. } => "InlineAsm",
+            TailCall { .. } => "TailCall",
+            _ => return,
+        };
+        self.report(
+            TranslationErrorKind::UnsupportedTerminator(unsupported_terminator.to_string()),
+            function_name,
+            span,
+        );
+    }
+
+    /// Resolves a function call and queues the called function if the translator would translate its body.
+    /// The function run by `std::thread::spawn` is queued as well.
+    fn check_call(
+        &mut self,
+        caller_def_id: rustc_hir::def_id::DefId,
+        function_name: &str,
+        func: &rustc_middle::mir::Operand<'tcx>,
+        args: &[rustc_span::Spanned<rustc_middle::mir::Operand<'tcx>>],
+        span: rustc_span::Span,
+    ) {
+        let function_def_id =
+            match extract_def_id_of_called_function_from_operand(func, caller_def_id, self.tcx) {
+                Ok(def_id) => def_id,
+                Err(kind) => {
+                    self.report(kind, function_name, span);
+                    return;
+                }
+            };
+        let called_function_name = self.tcx.def_path_str(function_def_id);
+
+        if called_function_name == "std::thread::spawn" {
+            let Some(function_to_be_run) = args.first() else {
+                return;
+            };
+            match extract_def_id_of_called_function_from_operand(
+                &function_to_be_run.node,
+                caller_def_id,
+                self.tcx,
+            ) {
+                Ok(thread_function_def_id) => self.pending.push_back(thread_function_def_id),
+                Err(kind) => self.report(kind, function_name, span),
+            }
+            return;
+        }
+        // Closures without captured variables are passed as constants, not as local variables.
+        let body = self.tcx.optimized_mir(caller_def_id);
+        let closure_args = args
+            .iter()
+            .filter_map(|arg| match arg.node.ty(body, self.tcx).kind() {
+                rustc_middle::ty::TyKind::Closure(closure_def_id, _) => Some(*closure_def_id),
+                _ => None,
+            });
+        if is_supported_function(&called_function_name) {
+            // The translator models the closures passed to the supported functions,
+            // e.g. the condition of `std::sync::Condvar::wait_while`.
+            self.visited.extend(closure_args);
+            return;
+        }
+        self.closures.extend(
+            closure_args.map(|closure_def_id| (closure_def_id, function_name.to_string(), span)),
+        );
+        if is_foreign_function(function_def_id, &called_function_name, self.tcx) {
+            return;
+        }
+        self.pending.push_back(function_def_id);
+    }
+}
+
+/// Checks whether the type string contains a sync-like type that the translator does not model.
+/// Returns the corresponding error kind if that is the case.
+fn check_sync_type(ty_string: &str) -> Option<TranslationErrorKind> {
+    if let Some(sync_type) = UNSUPPORTED_SYNC_TYPES
+        .iter()
+        .find(|sync_type| ty_string.contains(*sync_type))
+    {
+        return Some(TranslationErrorKind::UntrackedSyncVariable(format!(
+            "the type `{ty_string}` contains `{}` which is not supported",
+            sync_type.trim_end_matches(['<', ':'])
+        )));
+    }
+    // The container must wrap the sync variable, e.g. `Vec<Mutex<i32>>` but not `Mutex<Vec<i32>>`.
+    let sync_type_position = SUPPORTED_SYNC_TYPES
+        .iter()
+        .filter_map(|sync_type| ty_string.find(sync_type))
+        .min()?;
+    let wrapping_types = &ty_string[..sync_type_position];
+    if UNTRACKED_CONTAINERS
+        .iter()
+        .any(|container| wrapping_types.contains(container))
+    {
+        return Some(TranslationErrorKind::UntrackedSyncVariable(format!(
+            "the type `{ty_string}` stores sync variables in a container that is not tracked"
+        )));
+    }
+    None
+}
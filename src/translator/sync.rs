@@ -8,6 +8,7 @@ pub mod thread;
 use log::debug;
 
 use crate::data_structures::petri_net_interface::PetriNet;
+use crate::error::TranslationErrorKind;
 use crate::translator::function::{Places, PostprocessingTask};
 use crate::translator::mir_function::memory::Memory;
 use crate::utils::get_type_string;
@@ -35,6 +36,10 @@ pub fn is_supported_function(function_name: &str) -> bool {
 }
 
 /// Calls the corresponding handler for the supported synchronization or multithreading functions.
+///
+/// # Errors
+///
+/// If the handler cannot translate the call, then the error of the handler is returned.
 pub fn call_function<'tcx>(
     function_name: &str,
     index: usize,
@@ -43,32 +48,32 @@ pub fn call_function<'tcx>(
     places: Places,
     net: &mut PetriNet,
     memory: &mut Memory,
-) -> Option<PostprocessingTask> {
+) -> Result<Option<PostprocessingTask>, TranslationErrorKind> {
     match function_name {
         "std::sync::Condvar::new" => {
-            condvar::call_new(function_name, index, destination, places, net, memory);
-            None
+            condvar::call_new(function_name, index, destination, places, net, memory)?;
+            Ok(None)
         }
         "std::sync::Condvar::notify_one" => {
-            condvar::call_notify_one(function_name, index, args, places, net, memory);
-            None
+            condvar::call_notify_one(function_name, index, args, places, net, memory)?;
+            Ok(None)
         }
         "std::sync::Condvar::wait" | "std::sync::Condvar::wait_while" => {
             let task =
-                condvar::call_wait(function_name, index, args, destination, places, net, memory);
-            Some(task)
+                condvar::call_wait(function_name, index, args, destination, places, net, memory)?;
+            Ok(Some(task))
         }
         "std::sync::Mutex::<T>::lock" => {
-            mutex::call_lock(function_name, index, args, destination, places, net, memory);
-            None
+            mutex::call_lock(function_name, index, args, destination, places, net, memory)?;
+            Ok(None)
         }
         "std::sync::Mutex::<T>::new" => {
-            let task = mutex::call_new(function_name, index, destination, places, net, memory);
-            Some(task)
+            let task = mutex::call_new(function_name, index, destination, places, net, memory)?;
+            Ok(Some(task))
         }
         "std::thread::JoinHandle::<T>::join" => {
-            thread::call_join(function_name, index, args, places, net, memory);
-            None
+            thread::call_join(function_name, index, args, places, net, memory)?;
+            Ok(None)
         }
         _ => panic!("BUG: Call handler for {function_name} is not defined"),
     }
@@ -151,11 +156,15 @@ fn should_link_to_same_value<'tcx>(
 /// - It respects the order in which the places appear (i.e. the order of the fields in the aggregate)
 ///
 /// It passes it on to the function memory to create the corresponding aggregate
+///
+/// # Errors
+///
+/// If the aggregate cannot be linked to the place, then an error is returned.
 pub fn handle_aggregate_assignment<'tcx>(
     place: &rustc_middle::mir::Place<'tcx>,
     operands: &Vec<rustc_middle::mir::Operand<'tcx>>,
     memory: &mut Memory,
-) {
+) -> Result<(), TranslationErrorKind> {
     let mut has_linked_value = false;
     let mut aggregate_fields: Vec<Option<rustc_middle::mir::Place<'tcx>>> = Vec::new();
 
@@ -178,8 +187,9 @@ pub fn handle_aggregate_assignment<'tcx>(
     }
 
     if has_linked_value {
-        memory.create_aggregate(*place, &aggregate_fields);
+        memory.create_aggregate(*place, &aggregate_fields)?;
     }
+    Ok(())
 }
 
 /// Checks if `place_to_link` contains a mutex, a mutex guard, a join handle or a condition variable.
@@ -198,13 +208,17 @@ pub fn handle_aggregate_assignment<'tcx>(
 ///
 /// It also works for checking if a function argument is a sync variable
 /// and then linking the return value to the argument.
+///
+/// # Errors
+///
+/// If `place_to_link` is already linked to a different sync variable, then an error is returned.
 pub fn link_if_sync_variable<'tcx>(
     place_to_link: &rustc_middle::mir::Place<'tcx>,
     place_linked: &rustc_middle::mir::Place<'tcx>,
     memory: &mut Memory,
     caller_function_def_id: rustc_hir::def_id::DefId,
     tcx: rustc_middle::ty::TyCtxt<'tcx>,
-) {
+) -> Result<(), TranslationErrorKind> {
     if should_link_to_same_value(
         place_to_link,
         place_linked,
@@ -212,6 +226,7 @@ pub fn link_if_sync_variable<'tcx>(
         caller_function_def_id,
         tcx,
     ) {
-        memory.link_place_to_same_value(*place_to_link, *place_linked);
+        memory.link_place_to_same_value(*place_to_link, *place_linked)?;
     }
+    Ok(())
 }
@@ -26,6 +26,7 @@ use crate::data_structures::petri_net_interface::{PetriNet, PlaceRef, Transition
 use crate::data_structures::petri_net_interface::{
     add_arc_place_transition, add_arc_transition_place,
 };
+use crate::error::TranslationErrorKind;
 use crate::naming::condvar::{place_labels, transition_labels};
 use crate::translator::function::{Places, PostprocessingTask};
 use crate::translator::mir_function::memory::{Memory, MutexGuardRef};
@@ -81,18 +82,21 @@ impl Condvar {
     /// Connects the `notify_received` transition to the `end_place`.
     /// Unlocks the mutex when the waiting starts, lock it when the waiting ends.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If this function is called more than once, then the function panics.
+    /// If this function is called more than once, then an error is returned.
     pub fn link_to_wait_call(
         &self,
         start_place: &PlaceRef,
         end_place: &PlaceRef,
         mutex_guard_ref: &MutexGuardRef,
         net: &mut PetriNet,
-    ) {
+    ) -> Result<(), TranslationErrorKind> {
         if self.already_linked_to_call.get().is_some() {
-            unimplemented!("Multiple calls to `wait` or `wait_while` are not supported yet");
+            return Err(TranslationErrorKind::UnsupportedFeature(
+                "multiple calls to `wait` or `wait_while` on the same condition variable"
+                    .to_string(),
+            ));
         }
         add_arc_place_transition(net, start_place, &self.wait_start);
         add_arc_transition_place(net, &self.notify_received, end_place);
@@ -105,6 +109,7 @@ impl Condvar {
         self.already_linked_to_call.set(()).expect(
             "BUG: The condvar was already linked to a wait call before calling `link_to_wait_call`",
         );
+        Ok(())
     }
 
     /// Links the Petri net model of the condition variable to the representation of
@@ -120,6 +125,10 @@ impl Condvar {
 ///
 /// - Creates a new `Condvar`.
 /// - Links the return place to the `Condvar`.
+///
+/// # Errors
+///
+/// If the return place cannot be linked to the new condition variable, then an error is returned.
 pub fn call_new(
     function_name: &str,
     index: usize,
@@ -127,13 +136,15 @@ pub fn call_new(
     places: Places,
     net: &mut PetriNet,
     memory: &mut Memory,
-) {
+) -> Result<(), TranslationErrorKind> {
+    memory.check_link(&destination)?;
     call_foreign_function(function_name, index, places, net);
     // Create a new condvar
     let condvar = Condvar::new(index, net);
     // The return value contains a new condition variable. Link the local variable to it.
-    memory.link_condvar(destination, condvar);
+    memory.link_condvar(destination, condvar)?;
     debug!("NEW CONDVAR: {destination:?}");
+    Ok(())
 }
 
 /// Call to `std::sync::Condvar::notify_one`.
@@ -148,6 +159,10 @@ pub fn call_new(
 /// The reason is that any call may fail, which is equivalent to saying that the `notify_one`
 /// was never present in the program, leading to a false lost signal.
 /// In conclusion: Ignore the cleanup place, do not model it. Assume `notify_one` never unwinds.
+///
+/// # Errors
+///
+/// If the self reference is not linked to a condition variable, then an error is returned.
 pub fn call_notify_one(
     function_name: &str,
     index: usize,
@@ -155,15 +170,16 @@ pub fn call_notify_one(
     places: Places,
     net: &mut PetriNet,
     memory: &Memory,
-) {
-    let places = places.ignore_cleanup_place();
-    let transitions = call_foreign_function(function_name, index, places, net);
+) -> Result<(), TranslationErrorKind> {
     // Retrieve the condvar from the local variable passed to the function as an argument.
     let self_ref = extract_nth_argument_as_place(args, 0).unwrap_or_else(|| {
         panic!("BUG: `{function_name}` should receive the self reference as a place")
     });
-    let condvar_ref = memory.get_condvar(&self_ref);
+    let condvar_ref = memory.get_condvar(&self_ref)?;
+    let places = places.ignore_cleanup_place();
+    let transitions = call_foreign_function(function_name, index, places, net);
     condvar_ref.link_to_notify_one_call(transitions.get_default(), net);
+    Ok(())
 }
 
 /// Call to `std::sync::Condvar::wait`.
@@ -182,6 +198,11 @@ pub fn call_notify_one(
 /// The reason is that any call may fail, which is equivalent to saying that the `wait`
 /// was never present in the program, leading to a false model.
 /// In conclusion: Ignore the cleanup place, do not model it. Assume `wait` never unwinds.
+///
+/// # Errors
+///
+/// If the arguments are not linked to a condition variable and a mutex guard, then an error is returned.
+/// If the condition variable was already used in another call to `wait`, then an error is returned.
 pub fn call_wait<'tcx>(
     function_name: &str,
     index: usize,
@@ -190,28 +211,35 @@ pub fn call_wait<'tcx>(
     places: Places,
     net: &mut PetriNet,
     memory: &mut Memory,
-) -> PostprocessingTask {
+) -> Result<PostprocessingTask, TranslationErrorKind> {
     // Retrieve the condvar from the local variable passed to the function as an argument.
     let self_ref = extract_nth_argument_as_place(args, 0).unwrap_or_else(|| {
         panic!("BUG: `{function_name}` should receive the self reference as a place")
     });
-    let condvar_ref = memory.get_condvar(&self_ref);
+    let condvar_ref = memory.get_condvar(&self_ref)?;
     // Retrieve the mutex guard from the local variable passed to the function as an argument.
     let mutex_guard = extract_nth_argument_as_place(args, 1).unwrap_or_else(|| {
         panic!("BUG: `{function_name}` should receive the first argument as a place")
     });
-    let mutex_guard_ref = memory.get_mutex_guard(&mutex_guard);
+    let mutex_guard_ref = memory.get_mutex_guard(&mutex_guard)?.clone();
+    let condvar_ref = condvar_ref.clone();
+
+    // The return value contains the mutex guard passed to the function. Link the local variable to it.
+    // The link is done first, `link_to_wait_call` only fails before adding arcs.
+    memory.link_place_to_same_value(destination, mutex_guard)?;
 
     // Connect the start and end place to the condition variable
     let places = places.ignore_cleanup_place();
     let (start_place, end_place) = places.get_start_end_place();
-    condvar_ref.link_to_wait_call(&start_place, &end_place, mutex_guard_ref, net);
+    condvar_ref.link_to_wait_call(&start_place, &end_place, &mutex_guard_ref, net)?;
     let wait_start = condvar_ref.wait_start.clone();
 
-    // The return value contains the mutex guard passed to the function. Link the local variable to it.
-    memory.link_place_to_same_value(destination, mutex_guard);
-
     // Create a postprocessing task to link the mutex to the condvar.
     // This creates the condition and skip logic.
-    PostprocessingTask::link_mutex_to_condvar(index, start_place, end_place, wait_start)
+    Ok(PostprocessingTask::link_mutex_to_condvar(
+        index,
+        start_place,
+        end_place,
+        wait_start,
+    ))
 }
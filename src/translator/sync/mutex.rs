@@ -19,6 +19,7 @@ use crate::data_structures::petri_net_interface::{PetriNet, PlaceRef, Transition
 use crate::data_structures::petri_net_interface::{
     add_arc_place_transition, add_arc_transition_place, connect_places,
 };
+use crate::error::TranslationErrorKind;
 use crate::naming::condvar::wait_skip_label;
 use crate::naming::mutex::{condition_place_labels, place_label};
 use crate::translator::function::{Places, PostprocessingTask};
@@ -138,6 +139,10 @@ impl Guard {
 /// For instance, a simple double lock deadlock is not detected
 /// because the second call could take the unwind path.
 /// In conclusion: Ignore the cleanup place, do not model it. Assume `lock` never unwinds.
+///
+/// # Errors
+///
+/// If the self reference is not linked to a mutex, then an error is returned.
 pub fn call_lock<'tcx>(
     function_name: &str,
     index: usize,
@@ -146,24 +151,26 @@ pub fn call_lock<'tcx>(
     places: Places,
     net: &mut PetriNet,
     memory: &mut Memory,
-) {
-    let places = places.ignore_cleanup_place();
-    let transitions = call_foreign_function(function_name, index, places, net);
-    let lock_transition = transitions.get_default();
-
+) -> Result<(), TranslationErrorKind> {
     // Retrieve the mutex from the local variable passed to the function as an argument.
     let self_ref = extract_nth_argument_as_place(args, 0).unwrap_or_else(|| {
         panic!("BUG: `{function_name}` should receive the self reference as a place")
     });
-    let mutex_ref = memory.get_mutex(&self_ref);
+    let mutex_ref = memory.get_mutex(&self_ref)?;
+    memory.check_link(&destination)?;
+
+    let places = places.ignore_cleanup_place();
+    let transitions = call_foreign_function(function_name, index, places, net);
+    let lock_transition = transitions.get_default();
     mutex_ref.add_lock_arc(lock_transition, net);
 
     // Create a new mutex guard
     let mutex_guard = Guard::new(mutex_ref.clone());
 
     // The return value contains a new mutex guard. Link the local variable to it.
-    memory.link_mutex_guard(destination, mutex_guard);
+    memory.link_mutex_guard(destination, mutex_guard)?;
     debug!("NEW MUTEX GUARD {destination:?} DUE TO TRANSITION {lock_transition}");
+    Ok(())
 }
 
 /// Call to `std::sync::Mutex::<T>::new`.
@@ -172,6 +179,10 @@ pub fn call_lock<'tcx>(
 /// - Creates a new `Mutex`.
 /// - Links the return place to the `Mutex`.
 /// - Returns a postprocessing task to notify the creation of this mutex.
+///
+/// # Errors
+///
+/// If the return place cannot be linked to the new mutex, then an error is returned.
 pub fn call_new(
     function_name: &str,
     index: usize,
@@ -179,15 +190,16 @@ pub fn call_new(
     places: Places,
     net: &mut PetriNet,
     memory: &mut Memory,
-) -> PostprocessingTask {
+) -> Result<PostprocessingTask, TranslationErrorKind> {
+    memory.check_link(&destination)?;
     call_foreign_function(function_name, index, places, net);
     // Create a new mutex
     let mutex = Mutex::new(index, net);
     // The return value contains a new mutex. Link the local variable to it.
-    let mutex_ref = memory.link_mutex(destination, mutex);
+    let mutex_ref = memory.link_mutex(destination, mutex)?;
     debug!("NEW MUTEX: {destination:?}");
     // Notify the translator that a new mutex has been created
-    PostprocessingTask::new_mutex(mutex_ref.clone())
+    Ok(PostprocessingTask::new_mutex(mutex_ref.clone()))
 }
 
 /// Checks whether the variable to be dropped is a mutex guard.
@@ -201,7 +213,9 @@ pub fn handle_mutex_guard_drop(
     memory: &Memory,
 ) {
     if memory.is_mutex_guard(&place) {
-        let mutex_guard_ref = memory.get_mutex_guard(&place);
+        let mutex_guard_ref = memory
+            .get_mutex_guard(&place)
+            .expect("BUG: The place should be linked to a mutex guard");
         mutex_guard_ref.mutex.add_unlock_arc(unlock_transition, net);
         debug!("DROP MUTEX GUARD {place:?} DUE TO TRANSITION {unlock_transition}");
     }
@@ -27,6 +27,7 @@ use crate::data_structures::petri_net_interface::{PetriNet, PlaceRef, Transition
 use crate::data_structures::petri_net_interface::{
     add_arc_place_transition, add_arc_transition_place,
 };
+use crate::error::TranslationErrorKind;
 use crate::naming::thread::{end_place_label, start_place_label};
 use crate::translator::function::Places;
 use crate::translator::mir_function::MirFunction;
@@ -118,6 +119,10 @@ impl Thread {
 /// For instance, a thread that never returns will not cause a deadlock
 /// when joining it because the call could take the unwind path.
 /// In conclusion: Ignore the cleanup place, do not model it. Assume `join` never unwinds.
+///
+/// # Errors
+///
+/// If the self reference is not linked to a join handle, then an error is returned.
 pub fn call_join(
     function_name: &str,
     index: usize,
@@ -125,15 +130,16 @@ pub fn call_join(
     places: Places,
     net: &mut PetriNet,
     memory: &Memory,
-) {
-    let places = places.ignore_cleanup_place();
-    let transitions = call_foreign_function(function_name, index, places, net);
-    let transition = transitions.default();
+) -> Result<(), TranslationErrorKind> {
     // Retrieve the join handle from the local variable passed to the function as an argument.
     let self_ref = extract_nth_argument_as_place(args, 0).unwrap_or_else(|| {
         panic!("BUG: `{function_name}` should receive the self reference as a place")
     });
-    let thread_ref = memory.get_join_handle(&self_ref);
+    let thread_ref = memory.get_join_handle(&self_ref)?;
+    let places = places.ignore_cleanup_place();
+    let transitions = call_foreign_function(function_name, index, places, net);
+    let transition = transitions.default();
     thread_ref.set_join_transition(transition);
     info!("Found join call for thread {}", thread_ref.index);
+    Ok(())
 }
@@ -0,0 +1,59 @@
+//! Module that prepares the Petri net to check whether the program can end while a spawned thread still runs.
+//!
+//! A thread that is never joined, e.g. `examples/programs/thread/detached.rs`, may still run
+//! or be blocked forever when the main thread returns. The process then exits and stops the thread wherever it is,
+//! e.g. in the middle of a critical section. The net lets the thread continue after `PROGRAM_END` is marked,
+//! so neither the deadlock check nor the end of the program reveal it.
+//!
+//! The net does not record which threads run, so the property is checked on an instrumented copy
+//! where the transition that spawns a thread, i.e. that marks `THREAD_{i}_START`, puts a token in the place `THREADS_RUNNING`
+//! and every transition that ends a thread, i.e. that marks `THREAD_{i}_END`, takes one.
+//! The property `Property::UnjoinedThread` is then that `PROGRAM_END` and `THREADS_RUNNING` are marked together.
+
+use crate::data_structures::petri_net_interface::{
+    PetriNet, add_arc_place_transition, add_arc_transition_place, copy_net,
+};
+use crate::naming::THREADS_RUNNING;
+use crate::naming::thread::{ended_thread, started_thread};
+
+/// Copies the net and adds the place `THREADS_RUNNING`, which holds one token for every spawned thread that did not end.
+/// A thread ends only after its spawn put a token in `THREADS_RUNNING`, so the arc taken by the end never disables it
+/// and the witness path, whose labels are those of the original transitions, is a path of the original net.
+#[must_use]
+pub fn instrument(net: &PetriNet) -> PetriNet {
+    let (mut copy, _, transition_refs) = copy_net(net);
+    let running = copy.add_place(THREADS_RUNNING);
+    for (transition_ref, transition) in net.transitions_iter() {
+        let copy_ref = &transition_refs[transition_ref.label()];
+        for place_ref in transition.get_postset() {
+            if started_thread(place_ref.label()).is_some() {
+                add_arc_transition_place(&mut copy, copy_ref, &running);
+            } else if ended_thread(place_ref.label()).is_some() {
+                add_arc_place_transition(&mut copy, &running, copy_ref);
+            }
+        }
+    }
+    copy
+}
+
+/// Returns the indices of the threads that are spawned but do not end in the witness path, in the order of their spawn.
+#[must_use]
+pub fn unfinished_threads(net: &PetriNet, witness_path: &[String]) -> Vec<usize> {
+    let mut unfinished = Vec::new();
+    for label in witness_path {
+        let Some((_, transition)) = net
+            .transitions_iter()
+            .find(|(transition_ref, _)| transition_ref.label() == label)
+        else {
+            continue;
+        };
+        for place_ref in transition.get_postset() {
+            if let Some(index) = started_thread(place_ref.label()) {
+                unfinished.push(index);
+            } else if let Some(index) = ended_thread(place_ref.label()) {
+                unfinished.retain(|thread| *thread != index);
+            }
+        }
+    }
+    unfinished
+}
@@ -3,6 +3,8 @@
 //! These functions should involve some kind of processing of the compiler types
 //! which does not need additional translation data structures.
 
+use crate::error::TranslationErrorKind;
+
 /// Extracts the definition ID of the called function from the `rustc_middle::mir::Operand`.
 ///
 /// First obtains the type (`rustc_middle::ty::Ty`) of the operand for every possible case.
@@ -14,11 +16,16 @@
 ///
 /// This method is used to know which function will be called as part of the `Call` MIR Terminator.
 /// <https://doc.rust-lang.org/stable/nightly-rustc/rustc_middle/mir/syntax/enum.TerminatorKind.html#variant.Call>
+///
+/// # Errors
+///
+/// If the operand is a function pointer or another type that does not resolve
+/// statically to a function definition, then an error is returned.
 pub fn extract_def_id_of_called_function_from_operand<'tcx>(
     operand: &rustc_middle::mir::Operand<'tcx>,
     caller_function_def_id: rustc_hir::def_id::DefId,
     tcx: rustc_middle::ty::TyCtxt<'tcx>,
-) -> rustc_hir::def_id::DefId {
+) -> Result<rustc_hir::def_id::DefId, TranslationErrorKind> {
     let function_type = match operand {
         rustc_middle::mir::Operand::Copy(place) | rustc_middle::mir::Operand::Move(place) => {
             // Find the type through the local declarations of the caller function.
@@ -29,20 +36,20 @@ pub fn extract_def_id_of_called_function_from_operand<'tcx>(
         }
         rustc_middle::mir::Operand::Constant(constant) => constant.ty(),
         rustc_middle::mir::Operand::RuntimeChecks(_) => {
-            panic!("RuntimeChecks found in an operand which was expected to contain a place")
-        } // We don't care about this case
+            return Err(TranslationErrorKind::UnresolvedCall(
+                "runtime checks found where a function was expected".to_string(),
+            ));
+        }
     };
     match function_type.kind() {
-        rustc_middle::ty::TyKind::FnPtr(_, _) => {
-            unimplemented!(
-                "TyKind::FnPtr not implemented yet. Function pointers are present in the MIR"
-            );
-        }
         rustc_middle::ty::TyKind::FnDef(def_id, _)
-        | rustc_middle::ty::TyKind::Closure(def_id, _) => *def_id,
-        _ => {
-            panic!("TyKind::FnDef, a function definition, but got: {function_type:?}");
-        }
+        | rustc_middle::ty::TyKind::Closure(def_id, _) => Ok(*def_id),
+        rustc_middle::ty::TyKind::FnPtr(_, _) => Err(TranslationErrorKind::UnresolvedCall(
+            format!("calls through function pointers are not supported, found `{function_type}`"),
+        )),
+        _ => Err(TranslationErrorKind::UnresolvedCall(format!(
+            "expected a function definition or a closure, found `{function_type}`"
+        ))),
     }
 }
 
@@ -69,6 +69,8 @@ fn generates_lola_output_by_default() {
     );
     std::fs::remove_file("./does_not_generate_output_by_default.lola")
         .expect("Could not delete output file");
+    std::fs::remove_file("./does_not_generate_output_by_default.map.json")
+        .expect("Could not delete output file");
 
     // Check that the other formats were not generated
     assert!(
@@ -80,3 +82,1178 @@ fn generates_lola_output_by_default() {
         "Should not generate a .pnml file by default"
     );
 }
+
+#[test]
+fn passes_edition_to_rustc() {
+    let file = assert_fs::NamedTempFile::new("let_chains.rs")
+        .expect("Could not create temporary file for test");
+    // `let` chains are only available starting with the 2024 edition
+    file.write_str("fn main() { if let Some(x) = Some(1) && x > 0 {} }")
+        .expect("Could not write test file contents");
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--skip-analysis")
+        .arg("--")
+        .arg("--edition")
+        .arg("2024");
+    cmd.assert().success();
+    output_folder
+        .child("net.lola")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn passes_cfg_to_rustc() {
+    let file = assert_fs::NamedTempFile::new("cfg_main.rs")
+        .expect("Could not create temporary file for test");
+    file.write_str("#[cfg(feature = \"enabled\")]\nfn main() {}")
+        .expect("Could not write test file contents");
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--skip-analysis")
+        .arg("--")
+        .arg("--cfg")
+        .arg("feature=\"enabled\"");
+    cmd.assert().success();
+    output_folder
+        .child("net.lola")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn reports_compilation_errors() {
+    let file = assert_fs::NamedTempFile::new("type_error.rs")
+        .expect("Could not create temporary file for test");
+    file.write_str("fn main() { let x: i32 = \"not a number\"; }")
+        .expect("Could not write test file contents");
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--skip-analysis");
+    cmd.assert().code(3).stderr(predicate::str::contains(
+        "Translation failed: `rustc` failed to compile the source code",
+    ));
+    output_folder
+        .child("net.lola")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn reports_unsupported_code_with_location() {
+    let file = assert_fs::NamedTempFile::new("function_pointer.rs")
+        .expect("Could not create temporary file for test");
+    file.write_str("fn foo() {}\nfn main() {\n    let f: fn() = foo;\n    f();\n}")
+        .expect("Could not write test file contents");
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--skip-analysis");
+    cmd.assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "calls through function pointers are not supported",
+        ))
+        .stderr(predicate::str::contains("in function `main`"))
+        .stderr(predicate::str::contains("function_pointer.rs:4:5"));
+}
+
+#[test]
+fn lenient_mode_approximates_unsupported_code() {
+    let file = assert_fs::NamedTempFile::new("function_pointer_deadlock.rs")
+        .expect("Could not create temporary file for test");
+    file.write_str(
+        "fn foo() {}\nfn main() {\n    let f: fn() = foo;\n    f();\n    let data = std::sync::Mutex::new(0);\n    let _d1 = data.lock();\n    let _d2 = data.lock();\n}",
+    )
+    .expect("Could not write test file contents");
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--lenient");
+    cmd.assert()
+        .code(7)
+        .stdout(predicate::str::contains(
+            "Deadlock can be reached under 1 approximation(s)",
+        ))
+        .stdout(predicate::str::contains("function_pointer_deadlock.rs:4:5"));
+}
+
+#[test]
+fn lenient_mode_is_inconclusive_without_a_deadlock() {
+    let file = assert_fs::NamedTempFile::new("function_pointer_hidden_deadlock.rs")
+        .expect("Could not create temporary file for test");
+    file.write_str(
+        "use std::sync::Mutex;\nfn foo(data: &Mutex<i32>) {\n    let _d1 = data.lock();\n    let _d2 = data.lock();\n}\nfn main() {\n    let data = Mutex::new(0);\n    let f: fn(&Mutex<i32>) = foo;\n    f(&data);\n}",
+    )
+    .expect("Could not write test file contents");
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--lenient");
+    // The deadlock in `foo` is hidden by the approximation of the call through the function pointer.
+    cmd.assert()
+        .code(7)
+        .stdout(predicate::str::contains(
+            "The analysis is inconclusive according to the model checker `LoLA`: the 1 approximation(s) of unsupported constructs may hide a deadlock",
+        ))
+        .stdout(predicate::str::contains(
+            "function_pointer_hidden_deadlock.rs:9:5",
+        ));
+}
+
+#[test]
+fn check_support_lists_unsupported_constructs() {
+    let file = assert_fs::NamedTempFile::new("unsupported_constructs.rs")
+        .expect("Could not create temporary file for test");
+    file.write_str(
+        "fn foo() {}\nfn main() {\n    let f: fn() = foo;\n    f();\n    let lock = std::sync::RwLock::new(0);\n    let _r = lock.read();\n}",
+    )
+    .expect("Could not write test file contents");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg("--check-support");
+    cmd.assert()
+        .code(5)
+        .stdout(predicate::str::contains("Found 2 unsupported construct(s)"))
+        .stdout(predicate::str::contains("unsupported_constructs.rs:4:5"))
+        .stdout(predicate::str::contains("unsupported_constructs.rs:5:9"));
+}
+
+#[test]
+fn check_support_accepts_supported_code() {
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/thread/dining_philosophers.rs")
+        .arg("--check-support");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "All the constructs reachable from the entry point are supported",
+    ));
+}
+
+#[test]
+fn outputs_the_mapping_to_the_source_code() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--skip-analysis");
+    cmd.assert().success();
+    output_folder
+        .child("net.map.json")
+        .assert(predicate::str::contains("\"PROGRAM_START\": null"))
+        .assert(predicate::str::contains(
+            "\"std_sync_Mutex_T_lock_1_CALL\": {\"function\": \"main\"",
+        ))
+        .assert(predicate::str::contains(
+            "\"start_line\": 4, \"start_column\": 15",
+        ));
+}
+
+#[test]
+fn witness_path_prints_the_deadlock_trace() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--witness-path");
+    cmd.assert()
+        .code(6)
+        .stdout(predicate::str::contains("Trace to the deadlock:"))
+        .stdout(predicate::str::contains(
+            "[main thread] ./examples/programs/mutex/double_lock_deadlock.rs:3:15: locks mutex 0 | let _d1 = data.lock();",
+        ))
+        .stdout(predicate::str::contains(
+            "main thread holds mutex 0 and waits for mutex 0 at ./examples/programs/mutex/double_lock_deadlock.rs:4:15",
+        ));
+    output_folder
+        .child("witness-path.txt")
+        .assert(predicate::path::exists());
+}
+
+#[test]
+fn reports_the_deadlock_as_a_compiler_warning() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ));
+    cmd.assert()
+        .code(6)
+        .stderr(predicate::str::contains("warning: deadlock can be reached"))
+        .stderr(predicate::str::contains(
+            "main thread blocks here waiting for mutex 0",
+        ))
+        .stderr(predicate::str::contains(
+            "mutex 0 is locked here by main thread",
+        ));
+    output_folder
+        .child("witness-path.txt")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn message_format_json_prints_the_analysis_report() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--message-format=json");
+    cmd.assert()
+        .code(6)
+        .stdout(predicate::str::contains("Result:").not())
+        .stdout(predicate::str::contains(
+            "\"property\": {\"name\": \"deadlock\"",
+        ))
+        .stdout(predicate::str::contains("\"verdict\": \"deadlock\""))
+        .stdout(predicate::str::contains(
+            "\"net\": {\"places\": 11, \"transitions\": 9, \"arcs\": 24}",
+        ))
+        .stdout(predicate::str::contains(
+            "\"model_checker\": {\"name\": \"LoLA\", \"runtime_ms\": ",
+        ))
+        .stdout(predicate::str::contains(
+            "\"transitions\": [\"std_sync_Mutex_T_new_0_CALL\", \"std_sync_Mutex_T_lock_0_CALL\"]",
+        ))
+        .stdout(predicate::str::contains(
+            "{\"thread\": null, \"holds\": [0], \"waits_for\": [{\"resource\": \"mutex 0\"",
+        ));
+}
+
+#[test]
+fn reduce_flag_shrinks_the_net_and_expands_the_witness() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock_with_arc.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--message-format=json")
+        .arg("--reduce");
+    cmd.assert()
+        .code(6)
+        .stdout(predicate::str::contains(
+            "\"net\": {\"places\": 9, \"transitions\": 8, \"arcs\": 23}",
+        ))
+        .stdout(predicate::str::contains(
+            "\"transitions\": [\"std_sync_Mutex_T_new_0_CALL\", \"std_sync_Arc_T_new_0_CALL\", \"std_ops_Deref_deref_0_CALL\", \"std_sync_Mutex_T_lock_0_CALL\", \"std_ops_Deref_deref_1_CALL\"]",
+        ))
+        .stdout(predicate::str::contains(
+            "{\"thread\": null, \"holds\": [0], \"waits_for\": [{\"resource\": \"mutex 0\"",
+        ));
+}
+
+#[test]
+fn message_format_json_reports_unsupported_constructs() {
+    let file = assert_fs::NamedTempFile::new("unsupported_constructs_json.rs")
+        .expect("Could not create temporary file for test");
+    file.write_str("fn foo() {}\nfn main() {\n    let f: fn() = foo;\n    f();\n}")
+        .expect("Could not write test file contents");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg("--message-format=json")
+        .arg("--skip-analysis");
+    cmd.assert()
+        .code(3)
+        .stdout(predicate::str::contains(
+            "\"verdict\": \"translation_failed\"",
+        ))
+        .stdout(predicate::str::contains(
+            "\"error\": {\"message\": \"unresolved function call",
+        ));
+}
+
+#[test]
+fn message_format_json_reports_the_errors_of_the_analysis() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let command = || {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg("--message-format=json");
+        cmd
+    };
+
+    command()
+        .arg("--lola-path=./does/not/exist/lola")
+        .assert()
+        .code(8)
+        .stdout(predicate::str::contains(
+            "\"verdict\": \"model_checker_failed\"",
+        ))
+        .stdout(predicate::str::contains(
+            "\"reason\": \"the executable `./does/not/exist/lola` was not found",
+        ));
+
+    command()
+        .arg("--formula=EF (")
+        .assert()
+        .code(9)
+        .stdout(predicate::str::contains("\"property\": null"))
+        .stdout(predicate::str::contains(
+            "\"verdict\": \"invalid_property\"",
+        ));
+
+    command()
+        .arg("--formula=EF locked(other)")
+        .assert()
+        .code(9)
+        .stdout(predicate::str::contains(
+            "\"property\": {\"name\": \"formula\"",
+        ))
+        .stdout(predicate::str::contains(
+            "\"reason\": \"Invalid formula: unknown mutex `other`",
+        ));
+}
+
+#[test]
+fn sarif_flag_writes_the_deadlock_as_a_result() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--sarif");
+    cmd.assert().code(6);
+    output_folder
+        .child("net.sarif")
+        .assert(predicate::str::contains("\"version\": \"2.1.0\""))
+        .assert(predicate::str::contains("\"ruleId\": \"deadlock\""))
+        .assert(predicate::str::contains(
+            "{\"physicalLocation\": {\"artifactLocation\": {\"uri\": \"examples/programs/mutex/double_lock_deadlock.rs\"}, \"region\": {\"startLine\": 4, \"startColumn\": 15",
+        ))
+        .assert(predicate::str::contains(
+            "{\"id\": \"main thread\", \"locations\": [",
+        ))
+        .assert(predicate::str::contains(
+            "\"message\": {\"text\": \"blocks waiting for mutex 0\"}",
+        ));
+}
+
+#[test]
+fn baseline_accepts_known_deadlocks() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let baseline = output_folder.child("baseline.txt");
+    let command = |extra_args: &[&str]| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--baseline={}", baseline.path().to_string_lossy()))
+            .args(extra_args);
+        cmd
+    };
+
+    // Without an existing baseline, the deadlock is a new finding
+    command(&[])
+        .assert()
+        .code(6)
+        .stdout(predicate::str::contains("Fingerprint of the finding: "));
+
+    command(&["--update-baseline"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Baseline with 1 finding(s) written to",
+        ));
+    baseline.assert(predicate::str::contains(
+        "deadlock in `main`: main thread waits for mutex 0 at ./examples/programs/mutex/double_lock_deadlock.rs:4:15",
+    ));
+
+    command(&[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(accepted by the baseline)"));
+}
+
+#[test]
+fn baseline_ignores_the_indices_of_the_mutexes() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let baseline = output_folder.child("baseline.txt");
+    let file = output_folder.child("double_lock.rs");
+    // The mutex created by `setup` comes first in the translation and shifts the index of `a`,
+    // without changing any location in `main`.
+    let program = |setup: &str| {
+        format!(
+            "use std::sync::Mutex;\n\
+             \n\
+             fn main() {{\n\
+             \x20   setup();\n\
+             \x20   let a = Mutex::new(0);\n\
+             \x20   let _first = a.lock().unwrap();\n\
+             \x20   let _second = a.lock().unwrap();\n\
+             }}\n\
+             \n\
+             fn setup() {{\n\
+             \x20   {setup}\n\
+             }}\n"
+        )
+    };
+    let command = |extra_args: &[&str]| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(file.path())
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--baseline={}", baseline.path().to_string_lossy()))
+            .args(extra_args);
+        cmd
+    };
+
+    file.write_str(&program(""))
+        .expect("Could not write test file contents");
+    command(&["--update-baseline"]).assert().success();
+    baseline.assert(predicate::str::contains("waits for mutex 0"));
+
+    file.write_str(&program("let _unrelated = Mutex::new(0);"))
+        .expect("Could not write test file contents");
+    command(&[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(accepted by the baseline)"));
+}
+
+#[test]
+fn native_checker_finds_the_shortest_witness_path() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--checker=native")
+        .arg("--witness-path");
+    cmd.assert()
+        .code(6)
+        .stdout(predicate::str::contains(
+            "Result: Deadlock can be reached according to the model checker `native`",
+        ))
+        .stdout(predicate::str::contains(
+            "main thread holds mutex 0 and waits for mutex 0 at ./examples/programs/mutex/double_lock_deadlock.rs:4:15",
+        ));
+    output_folder
+        .child("witness-path.txt")
+        .assert("std_sync_Mutex_T_new_0_CALL\nstd_sync_Mutex_T_lock_0_CALL\n");
+    // The net in `LoLA` format is only generated on request with the `native` model checker
+    output_folder
+        .child("net.lola")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn lola_path_selects_the_executable() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let command = || {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ));
+        cmd
+    };
+
+    command()
+        .arg("--lola-path=./does/not/exist/lola")
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains(
+            "Model checker failed: the executable `./does/not/exist/lola` was not found",
+        ));
+
+    command()
+        .env("LOLA_PATH", "./assets/lola")
+        .assert()
+        .code(6)
+        .stdout(predicate::str::contains(
+            "Result: Deadlock can be reached according to the model checker `LoLA`",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn lola_timeout_makes_the_result_inconclusive() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    // An executable that never finishes in time
+    let executable = output_folder.child("slow_lola");
+    executable
+        .write_str("#!/bin/sh\nsleep 10\n")
+        .expect("Could not write test file contents");
+    std::fs::set_permissions(executable.path(), std::fs::Permissions::from_mode(0o755))
+        .expect("Could not make the test file executable");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg(format!(
+            "--lola-path={}",
+            executable.path().to_string_lossy()
+        ))
+        .arg("--lola-timeout=1");
+    cmd.assert().code(7).stdout(predicate::str::contains(
+        "Result: The analysis is inconclusive according to the model checker `LoLA`: the time limit of 1 s was exceeded",
+    ));
+}
+
+#[test]
+#[cfg(unix)]
+fn lola_memory_limit_makes_the_result_inconclusive() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--lola-memory-limit=1")
+        .arg("--message-format=json");
+    cmd.assert()
+        .code(7)
+        .stdout(predicate::str::contains("\"verdict\": \"inconclusive\""))
+        .stdout(predicate::str::contains(
+            "\"reason\": \"the memory limit of 1 MiB was exceeded\"",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn lola_killed_by_a_signal_reports_the_signal() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    // An executable that terminates itself with `SIGTERM`, which is not a failed allocation
+    let executable = output_folder.child("killed_lola");
+    executable
+        .write_str("#!/bin/sh\nkill -TERM $$\n")
+        .expect("Could not write test file contents");
+    std::fs::set_permissions(executable.path(), std::fs::Permissions::from_mode(0o755))
+        .expect("Could not make the test file executable");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg(format!(
+            "--lola-path={}",
+            executable.path().to_string_lossy()
+        ))
+        .arg("--lola-memory-limit=64");
+    cmd.assert().code(7).stdout(predicate::str::contains(
+        "Result: The analysis is inconclusive according to the model checker `LoLA`: `LoLA` was terminated by the signal 15",
+    ));
+}
+
+#[test]
+fn compare_reduction_reports_the_state_counts() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg("./examples/programs/condvar/producer_consumer.rs")
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--checker=native")
+        .arg("--compare-reduction");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "States explored: 753 with partial-order reduction, 25069 without",
+    ));
+}
+
+#[test]
+fn lost_signal_is_reported_separately() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let command = |source_file: &str, checker: &str| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(source_file)
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"))
+            .arg("--sarif");
+        cmd
+    };
+
+    for checker in ["lola", "native"] {
+        // The lost signals are checked on the original net even if the net is reduced.
+        command(
+            "./examples/programs/condvar/self_notify_lost_signal.rs",
+            checker,
+        )
+        .arg("--reduce")
+        .assert()
+        .code(6)
+        .stdout(predicate::str::contains(
+            "is lost: it is sent at ./examples/programs/condvar/self_notify_lost_signal.rs:5:5",
+        ));
+        command(
+            "./examples/programs/condvar/self_notify_lost_signal.rs",
+            checker,
+        )
+        .assert()
+        .code(6)
+        .stdout(predicate::str::contains(
+            "The signal on condvar 0 created at ./examples/programs/condvar/self_notify_lost_signal.rs:3:16 is lost: it is sent at ./examples/programs/condvar/self_notify_lost_signal.rs:5:5 while no thread waits, then a thread waits forever at ./examples/programs/condvar/self_notify_lost_signal.rs:6:19",
+        ));
+        output_folder
+            .child("net.sarif")
+            .assert(predicate::str::contains("\"ruleId\": \"lost_signal\""));
+
+        // The thread waits forever but no signal was ever sent
+        command(
+            "./examples/programs/condvar/infinite_wait_deadlock.rs",
+            checker,
+        )
+        .assert()
+        .code(6)
+        .stdout(predicate::str::contains("is lost").not());
+        output_folder
+            .child("net.sarif")
+            .assert(predicate::str::contains("lost_signal").not());
+    }
+}
+
+#[test]
+fn formula_is_checked_instead_of_deadlocks() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let command = |formula: &str, checker: &str| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg("./examples/programs/mutex/double_lock_deadlock.rs")
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"))
+            .arg(format!("--formula={formula}"));
+        cmd
+    };
+
+    for checker in ["lola", "native"] {
+        command("EF holds(main, data)", checker)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Result: The formula `EF holds(main, data)` holds",
+            ));
+        command("AG NOT locked(mutex 0)", checker)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Result: The formula `AG NOT locked(mutex 0)` does not hold",
+            ));
+    }
+
+    command("EF locked(other)", "native")
+        .assert()
+        .code(9)
+        .stderr(predicate::str::contains(
+            "Invalid formula: unknown mutex `other`, the known ones are: `data` (mutex 0)",
+        ));
+    command("A G F locked(data)", "native")
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains("unsupported property"));
+}
+
+#[test]
+fn check_panics_reports_the_panicking_call() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let file = output_folder.child("unwrap_none.rs");
+    file.write_str("fn main() {\n    let value: Option<i32> = None;\n    value.unwrap();\n}")
+        .expect("Could not write test file contents");
+    let command = |source_file: &std::path::Path, checker: &str| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(source_file)
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"))
+            .arg("--check-panics");
+        cmd
+    };
+
+    for checker in ["lola", "native"] {
+        command(
+            std::path::Path::new("./examples/programs/statement/panic.rs"),
+            checker,
+        )
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Result: A panic can be reached"))
+        .stdout(predicate::str::contains(
+            "Main thread panics at ./examples/programs/statement/panic.rs:2:5: it calls a function that panics",
+        ));
+        command(file.path(), checker)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Main thread panics at"))
+            .stdout(predicate::str::contains(
+                "unwrap_none.rs:3:5: it unwraps an `Err` or `None` value in `value.unwrap();`",
+            ));
+        command(
+            std::path::Path::new("./examples/programs/mutex/double_lock_deadlock.rs"),
+            checker,
+        )
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Result: The program cannot panic"));
+    }
+}
+
+#[test]
+fn check_livelocks_reports_the_looping_blocks() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let loop_while_joined = output_folder.child("loop_while_joined.rs");
+    loop_while_joined
+        .write_str(
+            "use std::thread;\n\nfn main() {\n    let handle = thread::spawn(|| loop {});\n    handle.join().unwrap();\n}\n",
+        )
+        .expect("Could not write test file contents");
+    let command = |source_file: &str, checker: &str| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(source_file)
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"))
+            .arg("--check-livelocks");
+        cmd
+    };
+
+    for checker in ["lola", "native"] {
+        command("./examples/programs/statement/infinite_loop.rs", checker)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Result: A livelock can be reached",
+            ))
+            .stdout(predicate::str::contains(
+                "main_BB1 of the main thread at ./examples/programs/statement/infinite_loop.rs:2:5",
+            ));
+
+        command("./examples/programs/statement/empty_main.rs", checker)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Result: The program is livelock-free",
+            ));
+
+        // The main thread is blocked in `join` while the spawned thread loops forever.
+        command(&loop_while_joined.path().to_string_lossy(), checker)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Result: A livelock can be reached",
+            ))
+            .stdout(predicate::str::contains(
+                "main__closure_0__BB1 of the thread 0 at",
+            ));
+    }
+}
+
+#[test]
+fn check_unjoined_threads_reports_the_running_thread() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let command = |source_file: &str, checker: &str| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(source_file)
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"))
+            .arg("--check-unjoined-threads");
+        cmd
+    };
+
+    for checker in ["lola", "native"] {
+        command("./examples/programs/thread/detached.rs", checker)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Result: The program can end while a spawned thread still runs",
+            ))
+            .stdout(predicate::str::contains(
+                "Thread 0 spawned at ./examples/programs/thread/detached.rs:2:5 has not ended",
+            ));
+
+        command(
+            "./examples/programs/thread/spawn_with_empty_closure.rs",
+            checker,
+        )
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Result: Every spawned thread ends before the program",
+        ));
+    }
+}
+
+#[test]
+fn lock_order_reports_the_cycle_without_the_model_checker() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let command = |source_file: &str| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(source_file)
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg("--lock-order")
+            .arg("--skip-analysis");
+        cmd
+    };
+
+    for reduce in [false, true] {
+        let mut cmd = command("./examples/programs/thread/dating_philosophers.rs");
+        if reduce {
+            cmd.arg("--reduce");
+        }
+        cmd.assert()
+            .code(7)
+            .stdout(predicate::str::contains(
+                "Result: The lock-order graph has 1 cycle(s)",
+            ))
+            .stdout(predicate::str::contains(
+                "Lock-order cycle between mutex 0 and mutex 1: thread 0 locks mutex 1 at ./examples/programs/thread/dating_philosophers.rs:21:26 while holding mutex 0",
+            ));
+    }
+
+    command("./examples/programs/mutex/two_threads_sharing_mutex.rs")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Result: The lock-order graph has no cycle",
+        ));
+}
+
+#[test]
+fn all_deadlocks_reports_every_class() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let file = output_folder.child("two_double_locks.rs");
+    file.write_str(
+        "use std::sync::Mutex;\n\
+         \n\
+         fn main() {\n\
+         \x20   let a = Mutex::new(0);\n\
+         \x20   let b = Mutex::new(0);\n\
+         \x20   if std::env::args().count() > 1 {\n\
+         \x20       let _first = a.lock().unwrap();\n\
+         \x20       let _second = a.lock().unwrap();\n\
+         \x20   } else {\n\
+         \x20       let _first = b.lock().unwrap();\n\
+         \x20       let _second = b.lock().unwrap();\n\
+         \x20   }\n\
+         }\n",
+    )
+    .expect("Could not write test file contents");
+
+    for checker in ["lola", "native"] {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(file.path())
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"))
+            .arg("--all-deadlocks");
+
+        cmd.assert()
+            .code(6)
+            .stdout(predicate::str::contains(
+                "2 distinct deadlock(s) can be reached:",
+            ))
+            .stdout(predicate::str::contains(
+                "Deadlock where the main thread waits for mutex 0 at",
+            ))
+            .stdout(predicate::str::contains(
+                "Deadlock where the main thread waits for mutex 1 at",
+            ));
+    }
+}
+
+#[test]
+fn contradicting_branches_are_refined() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let file = output_folder.child("same_flag.rs");
+    file.write_str(
+        "use std::sync::Mutex;\n\
+         \n\
+         fn main() {\n\
+         \x20   let a = Mutex::new(0);\n\
+         \x20   let flag = std::env::args().count() > 1;\n\
+         \x20   let first = a.lock().unwrap();\n\
+         \x20   if flag {\n\
+         \x20       drop(first);\n\
+         \x20   }\n\
+         \x20   if flag {\n\
+         \x20       let _second = a.lock().unwrap();\n\
+         \x20   }\n\
+         }\n",
+    )
+    .expect("Could not write test file contents");
+
+    // The reduction fuses the branches, the refinement must give the same result on the original net.
+    for (checker, reduce) in [
+        ("lola", false),
+        ("native", false),
+        ("lola", true),
+        ("native", true),
+    ] {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(file.path())
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"));
+        if reduce {
+            cmd.arg("--reduce");
+        }
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("The program is deadlock-free"))
+            .stdout(predicate::str::contains(
+                "Discarded 1 spurious witness(es) by tracking the boolean local(s) `flag` in `main`",
+            ));
+    }
+
+    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    cmd.arg("check-deadlock")
+        .arg(file.path())
+        .arg(format!(
+            "--output-folder={}",
+            output_folder.path().to_string_lossy()
+        ))
+        .arg("--checker=native")
+        .arg("--no-branch-refinement");
+
+    cmd.assert()
+        .code(6)
+        .stdout(predicate::str::contains("Deadlock can be reached"));
+}
+
+#[test]
+fn local_written_through_a_reference_is_not_refined() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let file = output_folder.child("flag_by_reference.rs");
+    file.write_str(
+        "use std::sync::Mutex;\n\
+         \n\
+         fn set(flag: &mut bool) {\n\
+         \x20   *flag = true;\n\
+         }\n\
+         \n\
+         fn main() {\n\
+         \x20   let a = Mutex::new(0);\n\
+         \x20   let mut flag = std::env::args().count() > 1;\n\
+         \x20   let first = a.lock().unwrap();\n\
+         \x20   if flag {\n\
+         \x20       drop(first);\n\
+         \x20   } else {\n\
+         \x20       set(&mut flag);\n\
+         \x20   }\n\
+         \x20   if flag {\n\
+         \x20       let _second = a.lock().unwrap();\n\
+         \x20   }\n\
+         }\n",
+    )
+    .expect("Could not write test file contents");
+
+    // The second branch is taken after the reference sets the flag, the deadlock is real.
+    for checker in ["lola", "native"] {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(file.path())
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"));
+
+        cmd.assert()
+            .code(6)
+            .stdout(predicate::str::contains("Deadlock can be reached"))
+            .stdout(predicate::str::contains("spurious witness").not());
+    }
+}
+
+#[test]
+fn structural_proof_writes_the_certificate() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let command = |source_file: &str| {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(source_file)
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg("--structural")
+            .arg("--skip-analysis");
+        cmd
+    };
+
+    command("./examples/programs/mutex/two_threads_sharing_mutex.rs")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Result: The program is deadlock-free according to the structural analysis",
+        ));
+    output_folder
+        .child("net.certificate.txt")
+        .assert(predicate::str::contains("Invariant: MUTEX_0 + "));
+
+    // The proof fails on a deadlock, and the model checker is skipped.
+    command("./examples/programs/mutex/double_lock_deadlock.rs")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("structural analysis").not());
+}
+
+#[test]
+fn guard_held_at_thread_end_is_reported() {
+    let output_folder = assert_fs::TempDir::new().expect("Could not create temporary folder");
+    let file = output_folder.child("forget_guard.rs");
+    file.write_str(
+        "use std::sync::{Arc, Mutex};\n\
+         use std::thread;\n\
+         \n\
+         fn main() {\n\
+         \x20   let data = Arc::new(Mutex::new(0));\n\
+         \x20   let data_clone = Arc::clone(&data);\n\
+         \x20   let handle = thread::spawn(move || {\n\
+         \x20       let guard = data_clone.lock().unwrap();\n\
+         \x20       std::mem::forget(guard);\n\
+         \x20   });\n\
+         \x20   handle.join().unwrap();\n\
+         }\n",
+    )
+    .expect("Could not write test file contents");
+
+    for checker in ["lola", "native"] {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+        cmd.arg("check-deadlock")
+            .arg(file.path())
+            .arg(format!(
+                "--output-folder={}",
+                output_folder.path().to_string_lossy()
+            ))
+            .arg(format!("--checker={checker}"))
+            .arg("--check-held-guards");
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Result: The program is deadlock-free",
+            ))
+            .stdout(predicate::str::contains(
+                "Thread 0 returns while holding mutex 0",
+            ))
+            .stdout(predicate::str::contains(
+                "in `let guard = data_clone.lock().unwrap();` is never released",
+            ));
+    }
+}
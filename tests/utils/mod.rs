@@ -48,41 +48,57 @@ pub fn assert_output_files(source_code_file: &str, output_folder: &str) {
 
         std::fs::remove_file(output_path).expect("Could not delete output file");
     }
+    let map_path = PathBuf::from(format!("{output_folder}test.map.json"));
+    std::fs::remove_file(map_path).expect("Could not delete output file");
 }
 
-/// Asserts that the result of running the model checker  `LoLA` matches the expected result
+/// Asserts that the result of running the model checkers `LoLA` and `native` matches the expected result
 /// (program has a deadlock or deadlock-free) after running `cargo-check-deadlock` on the given source code file.
+/// Both model checkers must agree on the result.
 ///
 /// # Panics
 ///
 /// If the command `cargo-check-deadlock` is not found, then the function panics.
-/// If the command `cargo-check-deadlock` fails to complete execution successfully, then the function panics.
+/// If the command `cargo-check-deadlock` does not exit with the exit code for the expected result,
+/// i.e. 6 for a deadlock and 0 for a deadlock-free program, then the function panics.
 #[allow(dead_code)]
 pub fn assert_lola_result(
     source_code_file: &str,
     output_folder: &str,
     output_should_have_deadlock: bool,
 ) {
-    let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
+    for checker in ["LoLA", "native"] {
+        let mut cmd = Command::new(cargo_bin!("cargo-check-deadlock"));
 
-    // Current workdir is always the project root folder
-    cmd.arg("check-deadlock")
-        .arg(source_code_file)
-        .arg(format!("--output-folder={output_folder}"))
-        .arg("--filename=deadlock_test");
+        // Current workdir is always the project root folder
+        cmd.arg("check-deadlock")
+            .arg(source_code_file)
+            .arg(format!("--output-folder={output_folder}"))
+            .arg("--filename=deadlock_test")
+            .arg(format!("--checker={}", checker.to_lowercase()));
 
-    if output_should_have_deadlock {
-        cmd.assert().success().stdout(predicate::str::contains(
-            "Result: Deadlock can be reached according to the model checker `LoLA`",
-        ));
-    } else {
-        cmd.assert().success().stdout(predicate::str::contains(
-            "Result: The program is deadlock-free according to the model checker `LoLA`",
-        ));
+        if output_should_have_deadlock {
+            cmd.assert()
+                .code(6)
+                .stdout(predicate::str::contains(format!(
+                    "Result: Deadlock can be reached according to the model checker `{checker}`"
+                )));
+        } else {
+            cmd.assert().success().stdout(predicate::str::contains(format!(
+                "Result: The program is deadlock-free according to the model checker `{checker}`"
+            )));
+        }
+        // Delete the output files, only `LoLA` needs the net in `LoLA` format
+        let filenames: &[&str] = if checker == "LoLA" {
+            &["deadlock_test.lola", "deadlock_test.map.json"]
+        } else {
+            &["deadlock_test.map.json"]
+        };
+        for filename in filenames {
+            let output_filename = PathBuf::from(format!("{output_folder}{filename}"));
+            std::fs::remove_file(output_filename).expect("Could not delete output file");
+        }
     }
-    // Delete the output file
-    let output_filename = PathBuf::from(format!("{output_folder}deadlock_test.lola"));
-    std::fs::remove_file(output_filename).expect("Could not delete output file");
 }
 
 /// This macro generates the test code for the three supported file formats.